@@ -8,6 +8,7 @@
 //!
 //! See `audits/STATUS.md` P2-6.
 
+use chrono::prelude::*;
 use regex::Regex;
 use wikibase::{Entity, EntityTrait, Reference, Snak, SnakType, Statement, Value};
 
@@ -42,6 +43,82 @@ pub fn titles_are_equal(t1: &str, t2: &str) -> bool {
     t1 == t2
 }
 
+/// Language code for monolingual text whose language the source doesn't
+/// state. Valid for P1476, but not for labels.
+pub const UNDETERMINED_LANGUAGE: &str = "und";
+
+/// ISO 639-2 codes (bibliographic and terminology variants) of the
+/// languages MEDLINE and DataCite records commonly use, with the code
+/// Wikidata uses for labels and monolingual text.
+const ISO_639_2_CODES: &[(&str, &str)] = &[
+    ("afr", "af"),
+    ("ara", "ar"),
+    ("bul", "bg"),
+    ("cat", "ca"),
+    ("ces", "cs"),
+    ("chi", "zh"),
+    ("cze", "cs"),
+    ("dan", "da"),
+    ("deu", "de"),
+    ("dut", "nl"),
+    ("ell", "el"),
+    ("eng", "en"),
+    ("est", "et"),
+    ("fas", "fa"),
+    ("fin", "fi"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("ger", "de"),
+    ("gre", "el"),
+    ("heb", "he"),
+    ("hin", "hi"),
+    ("hrv", "hr"),
+    ("hun", "hu"),
+    ("ice", "is"),
+    ("ind", "id"),
+    ("isl", "is"),
+    ("ita", "it"),
+    ("jpn", "ja"),
+    ("kor", "ko"),
+    ("lav", "lv"),
+    ("lit", "lt"),
+    ("nld", "nl"),
+    ("nor", "nb"),
+    ("per", "fa"),
+    ("pol", "pl"),
+    ("por", "pt"),
+    ("ron", "ro"),
+    ("rum", "ro"),
+    ("rus", "ru"),
+    ("slk", "sk"),
+    ("slo", "sk"),
+    ("slv", "sl"),
+    ("spa", "es"),
+    ("srp", "sr"),
+    ("swe", "sv"),
+    ("tha", "th"),
+    ("tur", "tr"),
+    ("ukr", "uk"),
+    ("vie", "vi"),
+    ("zho", "zh"),
+];
+
+/// Maps a language as reported by a source (`"ger"`, `"de"`, `"en-US"`)
+/// to the code Wikidata uses for labels. Returns `None` for codes it
+/// doesn't know, including "undetermined" and "multiple languages".
+pub fn wikidata_language_code(language: &str) -> Option<String> {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    match primary.len() {
+        2 if primary.chars().all(|c| c.is_ascii_lowercase()) => Some(primary.to_string()),
+        3 => ISO_639_2_CODES
+            .iter()
+            .find(|(iso, _)| *iso == primary)
+            .map(|(_, code)| code.to_string()),
+        _ => None,
+    }
+}
+
 /// Removes the affiliation-marker glyphs (`†`, `‡`) that some
 /// publishers append to author names, then trims whitespace.
 pub fn sanitize_author_name(author_name: &str) -> String {
@@ -74,6 +151,83 @@ pub fn wb_time_from_partial(
     Statement::new_normal(snak, vec![], references)
 }
 
+/// A "retrieved" (P813) snak for today, at day precision.
+pub fn retrieved_today() -> Snak {
+    let today = Utc::now().format("+%Y-%m-%dT00:00:00Z").to_string();
+    Snak::new_time("P813", today, 11)
+}
+
+/// Adds `statement` to `item` unless the item already has a claim for that
+/// property. If one of the existing claims has the same value, the
+/// references of `statement` are merged into it instead; a reference is only
+/// added if none of the existing ones is "stated in" (P248) the same source.
+pub fn add_or_merge_statement(item: &mut Entity, statement: Statement) {
+    if !item.has_claims_with_property(statement.property()) {
+        item.add_claim(statement);
+        return;
+    }
+    let existing = item.claims_mut().iter_mut().find(|s| {
+        s.property() == statement.property()
+            && s.main_snak().data_value() == statement.main_snak().data_value()
+    });
+    if let Some(existing) = existing {
+        merge_references(existing, &statement);
+    }
+}
+
+/// Like `add_or_merge_statement`, for monolingual text properties such as
+/// title (P1476), which have one value per language: `statement` is added
+/// unless the item has a claim for the property in the same language. Its
+/// references are only merged into a claim with the same value.
+pub fn add_or_merge_monolingual_statement(item: &mut Entity, statement: Statement) {
+    let existing = item.claims_mut().iter_mut().find(|s| {
+        s.property() == statement.property()
+            && s.main_snak().data_value() == statement.main_snak().data_value()
+    });
+    if let Some(existing) = existing {
+        merge_references(existing, &statement);
+        return;
+    }
+    let language = monolingual_language(statement.main_snak());
+    let has_language = item.claims().iter().any(|s| {
+        s.property() == statement.property() && monolingual_language(s.main_snak()) == language
+    });
+    if !has_language {
+        item.add_claim(statement);
+    }
+}
+
+/// Adds the references of `statement` to `existing`; a reference is only
+/// added if none of the existing ones is "stated in" (P248) the same source.
+fn merge_references(existing: &mut Statement, statement: &Statement) {
+    let mut references = existing.references().to_owned();
+    for reference in statement.references() {
+        let source = stated_in(reference);
+        if source.is_none() || !references.iter().any(|r| stated_in(r) == source) {
+            references.push(reference.to_owned());
+        }
+    }
+    existing.set_references(references);
+}
+
+/// The language of a monolingual text snak
+fn monolingual_language(snak: &Snak) -> Option<&str> {
+    match snak.data_value().as_ref().map(|dv| dv.value()) {
+        Some(Value::MonoLingual(text)) => Some(text.language()),
+        _ => None,
+    }
+}
+
+/// The "stated in" (P248) value of a reference, if any
+fn stated_in(reference: &Reference) -> Option<&Value> {
+    reference
+        .snaks()
+        .iter()
+        .find(|snak| snak.property() == "P248")
+        .and_then(|snak| snak.data_value().as_ref())
+        .map(|dv| dv.value())
+}
+
 /// Finds the first `string`-valued claim on `item` whose property matches
 /// `property` (and whose snak type is `Value`, i.e. not "no value" or
 /// "unknown value"), and returns its string.
@@ -205,6 +359,27 @@ mod tests {
 
     // === wb_time_from_partial ===============================================
 
+    #[test]
+    fn wikidata_language_code_maps_iso_639_2() {
+        assert_eq!(wikidata_language_code("ger").as_deref(), Some("de"));
+        assert_eq!(wikidata_language_code("deu").as_deref(), Some("de"));
+        assert_eq!(wikidata_language_code("ENG").as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn wikidata_language_code_strips_region() {
+        assert_eq!(wikidata_language_code("en-US").as_deref(), Some("en"));
+        assert_eq!(wikidata_language_code("fr").as_deref(), Some("fr"));
+    }
+
+    #[test]
+    fn wikidata_language_code_rejects_unknown() {
+        assert_eq!(wikidata_language_code("und"), None);
+        assert_eq!(wikidata_language_code("mul"), None);
+        assert_eq!(wikidata_language_code(""), None);
+        assert_eq!(wikidata_language_code("english"), None);
+    }
+
     #[test]
     fn wb_time_year_only_has_precision_9() {
         let stmt = wb_time_from_partial("P577", 2021, None, None, vec![]);
@@ -305,4 +480,83 @@ mod tests {
     fn parse_date_empty_returns_none() {
         assert_eq!(parse_date(""), None);
     }
+
+    // === add_or_merge_statement =============================================
+
+    fn stated_in_reference(source: &str) -> Reference {
+        Reference::new(vec![Snak::new_item("P248", source), retrieved_today()])
+    }
+
+    fn volume_statement(volume: &str, references: Vec<Reference>) -> Statement {
+        Statement::new_normal(Snak::new_string("P478", volume), vec![], references)
+    }
+
+    #[test]
+    fn add_or_merge_statement_adds_new_property() {
+        let mut item = Entity::new_empty_item();
+        add_or_merge_statement(&mut item, volume_statement("12", vec![stated_in_reference("Q1")]));
+        assert_eq!(item.claims().len(), 1);
+        assert_eq!(item.claims()[0].references().len(), 1);
+    }
+
+    #[test]
+    fn add_or_merge_statement_merges_references_into_same_value() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(volume_statement("12", vec![stated_in_reference("Q1")]));
+        add_or_merge_statement(&mut item, volume_statement("12", vec![stated_in_reference("Q2")]));
+        // Same source again: no second reference
+        add_or_merge_statement(&mut item, volume_statement("12", vec![stated_in_reference("Q2")]));
+        assert_eq!(item.claims().len(), 1);
+        assert_eq!(item.claims()[0].references().len(), 2);
+    }
+
+    #[test]
+    fn add_or_merge_statement_keeps_existing_different_value() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(volume_statement("12", vec![]));
+        add_or_merge_statement(&mut item, volume_statement("13", vec![stated_in_reference("Q2")]));
+        assert_eq!(item.claims().len(), 1);
+        assert!(item.claims()[0].references().is_empty());
+    }
+
+    fn title_statement(title: &str, language: &str, references: Vec<Reference>) -> Statement {
+        Statement::new_normal(
+            Snak::new_monolingual_text("P1476", title, language),
+            vec![],
+            references,
+        )
+    }
+
+    #[test]
+    fn add_or_merge_monolingual_statement_adds_other_languages() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(title_statement("A title", "en", vec![]));
+        add_or_merge_monolingual_statement(&mut item, title_statement("Ein Titel", "de", vec![]));
+        assert_eq!(item.claims().len(), 2);
+    }
+
+    #[test]
+    fn add_or_merge_monolingual_statement_keeps_one_value_per_language() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(title_statement("A title", "en", vec![stated_in_reference("Q1")]));
+        let other = title_statement("Another title", "en", vec![stated_in_reference("Q2")]);
+        add_or_merge_monolingual_statement(&mut item, other);
+        let same = title_statement("A title", "en", vec![stated_in_reference("Q2")]);
+        add_or_merge_monolingual_statement(&mut item, same);
+        assert_eq!(item.claims().len(), 1);
+        assert_eq!(item.claims()[0].references().len(), 2);
+    }
+
+    #[test]
+    fn retrieved_today_is_p813_at_day_precision() {
+        let snak = retrieved_today();
+        assert_eq!(snak.property(), "P813");
+        let today = Utc::now().format("+%Y-%m-%dT00:00:00Z").to_string();
+        if let Some(Value::Time(tv)) = snak.data_value().as_ref().map(|dv| dv.value()) {
+            assert_eq!(tv.time(), today);
+            assert_eq!(*tv.precision(), 11u64);
+        } else {
+            panic!("Expected Time value");
+        }
+    }
 }
@@ -0,0 +1,90 @@
+//! A line writer for report files that is shared between tasks.
+//!
+//! Report writers such as `QuickStatementsWriter` write whole lines to one
+//! file from many concurrent inputs. Each write holds the lock for all lines
+//! of one record and flushes, so an interrupted run leaves only complete
+//! records in the file.
+//!
+//! Files are opened for appending, so resuming a run with `--checkpoint`
+//! adds to the reports of the earlier runs instead of replacing them.
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+
+/// Writes lines to a file (or any writer), shared between all clones.
+#[derive(Clone)]
+pub struct AppendWriter {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+    is_new: bool,
+}
+
+impl fmt::Debug for AppendWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AppendWriter")
+    }
+}
+
+impl AppendWriter {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self { out: Arc::new(Mutex::new(Box::new(out))), is_new: true }
+    }
+
+    /// Opens `path` for appending; it is created if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_new = file.metadata()?.len() == 0;
+        Ok(Self { is_new, ..Self::new(BufWriter::new(file)) })
+    }
+
+    /// Whether nothing had been written to the output before, so that a
+    /// header is due.
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    /// Writes `lines`, each followed by a newline, and flushes.
+    pub fn write_lines<S: AsRef<str>>(&self, lines: &[S]) -> Result<()> {
+        let mut out = self.out.lock().map_err(|_| anyhow!("writer poisoned"))?;
+        for line in lines {
+            writeln!(out, "{}", line.as_ref())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::SharedBuffer;
+
+    #[test]
+    fn clones_write_to_the_same_output() {
+        let buffer = SharedBuffer::default();
+        let writer = AppendWriter::new(buffer.clone());
+        writer.write_lines(&["a", "b"]).unwrap();
+        writer.clone().write_lines(&["c"]).unwrap();
+        writer.write_lines::<&str>(&[]).unwrap();
+        assert_eq!(buffer.text(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn files_are_appended_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        let writer = AppendWriter::open(&path).unwrap();
+        assert!(writer.is_new());
+        writer.write_lines(&["a"]).unwrap();
+        let writer = AppendWriter::open(&path).unwrap();
+        assert!(!writer.is_new());
+        writer.write_lines(&["b"]).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+    }
+}
@@ -61,6 +61,10 @@ impl ScientificPublicationAdapter for Arxiv2Wikidata {
         "Arxiv2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q118398") // arXiv
+    }
+
     fn publication_property(&self) -> Option<IdProp> {
         Some(IdProp::ARXIV)
     }
@@ -153,14 +157,6 @@ impl ScientificPublicationAdapter for Arxiv2Wikidata {
             None => vec![],
         }
     }
-
-    async fn update_statements_for_publication_id(
-        &self,
-        _publication_id: &str,
-        _item: &mut Entity,
-    ) {
-        // No extra statements beyond the defaults
-    }
 }
 
 #[cfg(test)]
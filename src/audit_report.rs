@@ -0,0 +1,372 @@
+//! Where an existing publication item doesn't match its sources, for
+//! curation worklists. Nothing here edits the item; see
+//! [`WikidataPapers::audit`].
+//!
+//! [`WikidataPapers::audit`]: crate::wikidata_papers::WikidataPapers::audit
+
+use std::fmt;
+
+use crate::{
+    adapter_helpers::titles_are_equal,
+    discrepancy_report::{compatible, item_values},
+    generic_author_info::GenericAuthorInfo,
+    identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp},
+    publication_record::{format_date, PublicationRecord, RecordField},
+    *,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditFindingKind {
+    /// A DOI on the item isn't uppercase
+    DoiCase,
+    /// Sources know a DOI, but the item has none
+    MissingDoi,
+    /// No source reports a date compatible with the item's
+    DateMismatch,
+    /// No source reports a title equal to one of the item's
+    TitleMismatch,
+    /// The item has a different number of authors than every source
+    AuthorCount,
+    /// Author statements without a series ordinal (P1545)
+    MissingOrdinal,
+}
+
+impl AuditFindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditFindingKind::DoiCase => "doi_case",
+            AuditFindingKind::MissingDoi => "missing_doi",
+            AuditFindingKind::DateMismatch => "date_mismatch",
+            AuditFindingKind::TitleMismatch => "title_mismatch",
+            AuditFindingKind::AuthorCount => "author_count",
+            AuditFindingKind::MissingOrdinal => "missing_ordinal",
+        }
+    }
+
+    pub fn property(&self) -> &'static str {
+        match self {
+            AuditFindingKind::DoiCase | AuditFindingKind::MissingDoi => "P356",
+            AuditFindingKind::DateMismatch => "P577",
+            AuditFindingKind::TitleMismatch => "P1476",
+            AuditFindingKind::AuthorCount => "P50",
+            AuditFindingKind::MissingOrdinal => "P1545",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditFinding {
+    pub kind: AuditFindingKind,
+    /// The values on the item
+    pub item: Vec<String>,
+    /// `(source, value)` for what the sources report
+    pub sources: Vec<(String, String)>,
+}
+
+impl AuditFinding {
+    fn to_json(&self) -> serde_json::Value {
+        let sources: Vec<serde_json::Value> = self
+            .sources
+            .iter()
+            .map(|(source, value)| json!({"source": source, "value": value}))
+            .collect();
+        json!({
+            "kind": self.kind.as_str(),
+            "property": self.kind.property(),
+            "item": self.item,
+            "sources": sources,
+        })
+    }
+}
+
+impl fmt::Display for AuditFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sources: Vec<String> =
+            self.sources.iter().map(|(source, value)| format!("{source}={value}")).collect();
+        write!(f, "{}: item has {}", self.kind.as_str(), self.item.join(" / "))?;
+        if !sources.is_empty() {
+            write!(f, ", sources say {}", sources.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditReport {
+    q: String,
+    /// Adapters that know the work
+    sources: Vec<String>,
+    findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Compares `item` with `record`, and its DOIs with `source_ids`, the
+    /// identifiers each source knows for the work as `(source, id)`.
+    pub fn new(
+        item: &Entity,
+        record: &PublicationRecord,
+        source_ids: &[(String, GenericWorkIdentifier)],
+    ) -> Self {
+        let mut findings = vec![];
+        findings.extend(doi_findings(item, source_ids));
+        findings.extend(date_finding(item, record));
+        findings.extend(title_finding(item, record));
+        findings.extend(author_findings(item, record));
+        let mut sources: Vec<String> = record.sources().into_iter().map(String::from).collect();
+        for (source, _) in source_ids {
+            if !sources.contains(source) {
+                sources.push(source.to_owned());
+            }
+        }
+        Self { q: item.id().to_string(), sources, findings }
+    }
+
+    pub fn findings(&self) -> &[AuditFinding] {
+        &self.findings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "q": self.q,
+            "sources": self.sources,
+            "findings": self.findings.iter().map(|f| f.to_json()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.findings.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+fn doi_findings(
+    item: &Entity,
+    source_ids: &[(String, GenericWorkIdentifier)],
+) -> Vec<AuditFinding> {
+    let item_dois = item_values(item, "P356");
+    let source_dois: Vec<(String, String)> = source_ids
+        .iter()
+        .filter(|(_, id)| id.work_type() == &GenericWorkType::Property(IdProp::DOI))
+        .map(|(source, id)| (source.to_owned(), id.id().to_string()))
+        .collect();
+    let mut findings = vec![];
+    let lowercase: Vec<String> =
+        item_dois.iter().filter(|doi| doi.to_uppercase() != **doi).cloned().collect();
+    if !lowercase.is_empty() {
+        let sources =
+            lowercase.iter().map(|doi| ("expected".to_string(), doi.to_uppercase())).collect();
+        findings.push(AuditFinding { kind: AuditFindingKind::DoiCase, item: lowercase, sources });
+    }
+    if item_dois.is_empty() && !source_dois.is_empty() {
+        findings.push(AuditFinding {
+            kind: AuditFindingKind::MissingDoi,
+            item: vec![],
+            sources: source_dois,
+        });
+    }
+    findings
+}
+
+fn date_finding(item: &Entity, record: &PublicationRecord) -> Option<AuditFinding> {
+    let item_dates = item_values(item, "P577");
+    let sources: Vec<(String, String)> = record
+        .publication_dates
+        .iter()
+        .map(|c| (c.source.to_owned(), format_date(c.value)))
+        .collect();
+    let mismatch = !item_dates.is_empty()
+        && !sources.is_empty()
+        && item_dates.iter().all(|date| {
+            sources.iter().all(|(_, v)| !compatible(RecordField::PublicationDate, date, v))
+        });
+    if !mismatch {
+        return None;
+    }
+    Some(AuditFinding { kind: AuditFindingKind::DateMismatch, item: item_dates, sources })
+}
+
+fn title_finding(item: &Entity, record: &PublicationRecord) -> Option<AuditFinding> {
+    let item_titles: Vec<String> = item
+        .claims()
+        .iter()
+        .filter(|statement| statement.property() == "P1476")
+        .filter_map(|statement| match statement.main_snak().data_value().as_ref()?.value() {
+            Value::MonoLingual(text) => Some(text.text().to_string()),
+            _ => None,
+        })
+        .collect();
+    let sources: Vec<(String, String)> =
+        record.titles.iter().map(|c| (c.source.to_owned(), c.value.value().to_string())).collect();
+    let mismatch = !item_titles.is_empty()
+        && !sources.is_empty()
+        && !item_titles.iter().any(|t| sources.iter().any(|(_, s)| titles_are_equal(t, s)));
+    if !mismatch {
+        return None;
+    }
+    Some(AuditFinding { kind: AuditFindingKind::TitleMismatch, item: item_titles, sources })
+}
+
+fn author_findings(item: &Entity, record: &PublicationRecord) -> Vec<AuditFinding> {
+    let authors: Vec<GenericAuthorInfo> = item
+        .claims()
+        .iter()
+        .filter(|statement| ["P50", "P2093"].contains(&statement.property()))
+        .filter_map(GenericAuthorInfo::new_from_statement)
+        .collect();
+    let mut findings = vec![];
+
+    let sources: Vec<(String, String)> =
+        record.authors.iter().map(|c| (c.source.to_owned(), c.value.len().to_string())).collect();
+    let count = authors.len().to_string();
+    if !authors.is_empty() && !sources.is_empty() && sources.iter().all(|(_, n)| *n != count) {
+        findings.push(AuditFinding {
+            kind: AuditFindingKind::AuthorCount,
+            item: vec![count],
+            sources,
+        });
+    }
+
+    let unnumbered: Vec<String> = authors
+        .iter()
+        .filter(|author| author.list_number().is_none())
+        .filter_map(|author| author.wikidata_item().or(author.name()).map(String::from))
+        .collect();
+    if !unnumbered.is_empty() {
+        findings.push(AuditFinding {
+            kind: AuditFindingKind::MissingOrdinal,
+            item: unnumbered,
+            sources: vec![],
+        });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{adapter_helpers::wb_time_from_partial, publication_record::Sourced};
+
+    fn item_with(statements: Vec<Statement>) -> Entity {
+        let mut item = Entity::new_empty_item();
+        statements.into_iter().for_each(|s| item.add_claim(s));
+        item
+    }
+
+    fn string_statement(property: &str, value: &str) -> Statement {
+        Statement::new_normal(Snak::new_string(property, value), vec![], vec![])
+    }
+
+    fn title_statement(title: &str) -> Statement {
+        Statement::new_normal(Snak::new_monolingual_text("P1476", title, "en"), vec![], vec![])
+    }
+
+    fn author(property: &str, value: &str, ordinal: Option<&str>) -> Statement {
+        let snak = match property {
+            "P50" => Snak::new_item(property, value),
+            _ => Snak::new_string(property, value),
+        };
+        let qualifiers = ordinal.map(|n| Snak::new_string("P1545", n)).into_iter().collect();
+        Statement::new_normal(snak, qualifiers, vec![])
+    }
+
+    fn doi(source: &str, doi: &str) -> (String, GenericWorkIdentifier) {
+        (source.to_string(), GenericWorkIdentifier::new_prop(IdProp::DOI, doi))
+    }
+
+    fn kinds(report: &AuditReport) -> Vec<AuditFindingKind> {
+        report.findings().iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn lowercase_doi_is_flagged() {
+        let item = item_with(vec![string_statement("P356", "10.1000/abc")]);
+        let report = AuditReport::new(&item, &PublicationRecord::default(), &[]);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::DoiCase]);
+        assert_eq!(report.findings()[0].sources[0].1, "10.1000/ABC");
+    }
+
+    #[test]
+    fn missing_doi_is_flagged() {
+        let item = item_with(vec![]);
+        let ids = [doi("Crossref2Wikidata", "10.1000/abc")];
+        let report = AuditReport::new(&item, &PublicationRecord::default(), &ids);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::MissingDoi]);
+        assert_eq!(report.to_json()["sources"], json!(["Crossref2Wikidata"]));
+    }
+
+    #[test]
+    fn uppercase_doi_is_fine() {
+        let item = item_with(vec![string_statement("P356", "10.1000/ABC")]);
+        let ids = [doi("Crossref2Wikidata", "10.1000/abc")];
+        let report = AuditReport::new(&item, &PublicationRecord::default(), &ids);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn dates_must_be_compatible_with_a_source() {
+        let item = item_with(vec![wb_time_from_partial("P577", 2020, Some(3), None, vec![])]);
+        let mut record = PublicationRecord {
+            publication_dates: vec![Sourced::new((2020, Some(3), Some(5)), "A", vec![])],
+            ..Default::default()
+        };
+        assert!(AuditReport::new(&item, &record, &[]).is_empty());
+
+        record.publication_dates = vec![Sourced::new((2021, None, None), "A", vec![])];
+        let report = AuditReport::new(&item, &record, &[]);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::DateMismatch]);
+        assert_eq!(report.findings()[0].item, vec!["2020-03"]);
+    }
+
+    #[test]
+    fn titles_are_compared_loosely() {
+        let item = item_with(vec![title_statement("A Study of Things.")]);
+        let mut record = PublicationRecord {
+            titles: vec![Sourced::new(LocaleString::new("en", "a study of things"), "A", vec![])],
+            ..Default::default()
+        };
+        assert!(AuditReport::new(&item, &record, &[]).is_empty());
+
+        record.titles = vec![Sourced::new(LocaleString::new("en", "Other things"), "A", vec![])];
+        let report = AuditReport::new(&item, &record, &[]);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::TitleMismatch]);
+    }
+
+    #[test]
+    fn author_count_differing_from_every_source() {
+        let item =
+            item_with(vec![author("P50", "Q5", Some("1")), author("P2093", "Jane Doe", Some("2"))]);
+        let two = vec![GenericAuthorInfo::new(); 2];
+        let three = vec![GenericAuthorInfo::new(); 3];
+        let mut record = PublicationRecord {
+            authors: vec![Sourced::new(three.clone(), "A", vec![]), Sourced::new(two, "B", vec![])],
+            ..Default::default()
+        };
+        assert!(AuditReport::new(&item, &record, &[]).is_empty());
+
+        record.authors = vec![Sourced::new(three, "A", vec![])];
+        let report = AuditReport::new(&item, &record, &[]);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::AuthorCount]);
+        assert_eq!(report.findings()[0].to_string(), "author_count: item has 2, sources say A=3");
+    }
+
+    #[test]
+    fn authors_without_ordinal_are_listed() {
+        let item = item_with(vec![
+            author("P50", "Q5", None),
+            author("P2093", "Jane Doe", None),
+            author("P2093", "John Doe", Some("3")),
+        ]);
+        let report = AuditReport::new(&item, &PublicationRecord::default(), &[]);
+        assert_eq!(kinds(&report), vec![AuditFindingKind::MissingOrdinal]);
+        assert_eq!(report.findings()[0].item, vec!["Q5", "Jane Doe"]);
+        let j = report.to_json();
+        assert_eq!(j["findings"][0]["kind"], "missing_ordinal");
+        assert_eq!(j["findings"][0]["property"], "P1545");
+    }
+}
@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 use wikibase::mediawiki::api::Api;
 
 use crate::{
+    edit_output::EditOutput,
     generic_author_info::GenericAuthorInfo,
     http_client::{HttpJsonFetcher, JsonFetcher},
     wikidata_interaction::WikidataInteraction,
@@ -85,11 +86,16 @@ fn parse_coauthor_qs_bindings(
 pub struct AuthorNameString {
     pub logging_level: u8,
     fetcher: Arc<dyn JsonFetcher>,
+    output: EditOutput,
 }
 
 impl Default for AuthorNameString {
     fn default() -> Self {
-        Self { logging_level: 0, fetcher: Arc::new(HttpJsonFetcher::default()) }
+        Self {
+            logging_level: 0,
+            fetcher: Arc::new(HttpJsonFetcher::default()),
+            output: EditOutput::default(),
+        }
     }
 }
 
@@ -98,7 +104,13 @@ impl AuthorNameString {
     /// callers use `Self::new(level, Arc::new(HttpJsonFetcher::default()))`;
     /// tests inject a `MockJsonFetcher`.
     pub fn new(logging_level: u8, fetcher: Arc<dyn JsonFetcher>) -> Self {
-        Self { logging_level, fetcher }
+        Self { logging_level, fetcher, output: EditOutput::default() }
+    }
+
+    /// Whether edits are written to Wikidata or only reported. Live by
+    /// default.
+    pub fn set_output(&mut self, output: EditOutput) {
+        self.output = output;
     }
 
     fn log<S: Into<String>>(&self, level: u8, msg: S) {
@@ -166,16 +178,17 @@ impl AuthorNameString {
         mw_api: &Arc<RwLock<Api>>,
         paper_qs: &Vec<String>,
         name2author_qs: &HashMap<String, Vec<String>>,
-    ) -> Result<()> {
+    ) -> Result<Vec<String>> {
         let author_q = match self.get_or_create_author(ans, cache, mw_api, name2author_qs).await {
             Some(q) => q,
-            None => return Ok(()),
+            None => return Ok(vec![]),
         };
 
         let mut author = GenericAuthorInfo::new();
         author.set_name(Some(ans.clone()));
         author.set_wikidata_item(Some(author_q.clone()));
         let mut papers = WikidataPapers::new(cache.clone());
+        papers.set_output(self.output.clone());
         let api = mw_api.read().await;
         papers.entities_mut().load_entities(&api, paper_qs).await?;
         drop(api);
@@ -188,7 +201,7 @@ impl AuthorNameString {
             papers.entities_mut().reload_entities(&api, &edited_qs).await?;
             drop(api);
         }
-        Ok(())
+        Ok(edited_qs)
     }
 
     async fn create_p50_statements(
@@ -292,12 +305,14 @@ impl AuthorNameString {
         author_q
     }
 
+    /// Replaces author name strings on the papers of `root_author_q`'s
+    /// co-authors; returns the papers that were edited.
     pub async fn process_author_q(
         &self,
         root_author_q: String,
         mw_api: &Arc<RwLock<Api>>,
         cache: &Arc<WikidataStringCache>,
-    ) -> Result<()> {
+    ) -> Result<Vec<String>> {
         self.log(1, format!("Processing {}", root_author_q));
         let mut author = GenericAuthorInfo::new();
         author.set_wikidata_item(Some(root_author_q.to_owned()));
@@ -312,11 +327,13 @@ impl AuthorNameString {
             futures.push(future);
         }
 
-        let _ = futures::stream::iter(futures)
+        let results = futures::stream::iter(futures)
             .buffer_unordered(MAX_PROCESS_PAPERS_CONCURRENCY)
             .collect::<Vec<_>>()
             .await;
-        Ok(())
+        let mut edited_qs: Vec<String> = results.into_iter().flatten().flatten().collect();
+        edited_qs.sort();
+        Ok(edited_qs)
     }
 
     /// Get coauthors of a given author, author name string to paper Qids
@@ -359,7 +376,9 @@ impl AuthorNameString {
         self.log(1, format!("CREATING AUTHOR {ans}"));
         let mut author = GenericAuthorInfo::new();
         author.set_name(Some(ans.clone()));
-        let author = author.get_or_create_author_item(mw_api.clone(), cache.clone(), true).await;
+        let author = author
+            .get_or_create_author_item(mw_api.clone(), cache.clone(), true, &self.output)
+            .await;
         self.log(1, format!("CREATED AUTHOR {ans} => {author:?}"));
         Some(author.wikidata_item()?.to_string())
     }
@@ -1,9 +1,22 @@
-use std::{io, io::prelude::*, sync::Arc, time::Duration};
+use std::{cell::RefCell, io, io::prelude::*, sync::Arc, time::Duration};
 
 use futures::prelude::*;
 use papers::{
-    author_name_string::AuthorNameString, identifiers::GenericWorkIdentifier,
-    sourcemd_bot::SourceMDbot, sourcemd_config::SourceMD, wikidata_papers::WikidataPapers, *,
+    author_name_string::AuthorNameString,
+    checkpoint::Checkpoint,
+    citation_export::{items_from_sparql_result, load_citations, CitationFormat},
+    duplicates_log::DuplicatesLog,
+    edit_output::EditOutput,
+    identifier_index::build_index,
+    identifiers::GenericWorkIdentifier,
+    language_cache::DEFAULT_WIKIDATA_API_URL,
+    merge_candidate::{DuplicatePolicy, MergeCandidate, MergeCandidateWriter},
+    quickstatements::QuickStatementsWriter,
+    reference_import::ReferenceFormat,
+    sourcemd_bot::SourceMDbot,
+    sourcemd_config::SourceMD,
+    wikidata_papers::{EditResult, ItemUpdate, WikidataPapers},
+    *,
 };
 use pico_args::Arguments;
 use rand::seq::SliceRandom;
@@ -14,10 +27,199 @@ use crate::{sourcemd_command::SourceMDcommand, wikidata_string_cache::WikidataSt
 
 const INI_FILE: &str = "bot.ini";
 
-async fn command_authors(ini_file: &str) {
+/// Command-line options shared by the subcommands.
+#[derive(Debug, Clone, Default)]
+struct Options {
+    /// `--dry-run` and `--quickstatements` report edits instead of writing
+    /// them
+    output: EditOutput,
+    /// `--discrepancies-json` prints source disagreements as JSON
+    discrepancies_json: bool,
+    /// `--output jsonl` prints one JSON object per input line
+    jsonl: bool,
+    /// `--concurrency <n>`: inputs `papers` works on at the same time
+    concurrency: usize,
+    /// `--checkpoint <file>`: inputs `papers` has finished, and skips
+    checkpoint: Option<Arc<Checkpoint>>,
+    /// `--format bibtex|ris`: `papers` reads a reference list instead of one
+    /// ID per line
+    format: Option<ReferenceFormat>,
+    /// `--export-format csl-json|bibtex`: what `export` prints
+    export_format: CitationFormat,
+    /// `--sparql <query>`: `export` exports the items this query finds,
+    /// instead of those on stdin
+    sparql: Option<String>,
+    /// `--duplicates refuse|oldest`: what `papers` does with IDs on several
+    /// items; `None` to read it from the configuration file
+    duplicates: Option<DuplicatePolicy>,
+    /// `--merge-candidates <file>`: where `papers` reports IDs on several
+    /// items
+    merge_candidates: Option<MergeCandidateWriter>,
+    /// `--cache <file>`: keep identifier lookups in <file> between runs
+    cache_file: Option<String>,
+    /// `--id-index <file>`: look IDs up in <file> before searching; what
+    /// `index` writes
+    id_index: Option<String>,
+    /// `--duplicates-log <file>`: where IDs that are on several items are
+    /// listed
+    duplicates_log: Option<DuplicatesLog>,
+}
+
+/// One paper for `papers` to process: an input line, or an entry of a
+/// reference list with the IDs found in it.
+#[derive(Clone)]
+struct PaperInput {
+    input: String,
+    ids: Option<Vec<GenericWorkIdentifier>>,
+}
+
+impl PaperInput {
+    /// The property/key pairs the cache will look up for this paper.
+    fn cache_keys(&self) -> Vec<(String, String)> {
+        match &self.ids {
+            Some(ids) => WikidataStringCache::keys_for_ids(ids),
+            None => {
+                let ids = GenericWorkIdentifier::parse_ids_from_str(&self.input);
+                WikidataStringCache::keys_for_ids(&ids)
+            },
+        }
+    }
+}
+
+/// Outcome of one input line, printed as one JSON object per line with
+/// `--output jsonl`.
+#[derive(Debug, Default)]
+struct LineResult {
+    input: String,
+    /// IDs in the input line
+    parsed_ids: Vec<GenericWorkIdentifier>,
+    /// IDs found by the adapters, in addition to `parsed_ids`
+    discovered_ids: Vec<GenericWorkIdentifier>,
+    q: Option<String>,
+    /// `created`, `updated`, `unchanged`, `skipped`, `duplicate` or `error`;
+    /// `would_create` or `would_update` if the output isn't live
+    action: &'static str,
+    error: Option<String>,
+    skip_reason: Option<String>,
+    /// The items the IDs were found on, if more than one
+    merge_candidate: Option<MergeCandidate>,
+    /// Other items that were edited (papers, for `ans`)
+    edited_items: Vec<String>,
+}
+
+impl LineResult {
+    fn new(input: &str) -> Self {
+        Self { input: input.to_string(), ..Default::default() }
+    }
+
+    fn skipped(mut self, reason: &str) -> Self {
+        self.action = "skipped";
+        self.skip_reason = Some(reason.to_string());
+        self
+    }
+
+    /// An edit to an existing item: `updated`, or `would_update` if the
+    /// output isn't live.
+    fn updated(mut self, options: &Options) -> Self {
+        self.action = if options.output.is_live() { "updated" } else { "would_update" };
+        self
+    }
+
+    fn error(mut self, error: anyhow::Error) -> Self {
+        self.action = "error";
+        self.error = Some(format!("{error:#}"));
+        self
+    }
+
+    /// Records the outcome of creating or updating the item.
+    fn finish(mut self, update: anyhow::Result<ItemUpdate>, options: &Options) -> Self {
+        match update {
+            Ok(ItemUpdate::Done(er)) => {
+                report_discrepancies(&self.input, &er, options.discrepancies_json);
+                if let Some(candidate) = er.merge_candidate() {
+                    report_merge_candidate(&self.input, candidate, Some(er.q()), options);
+                    self.merge_candidate = Some(candidate.to_owned());
+                }
+                self.q = Some(er.q().to_string()).filter(|q| !q.is_empty());
+                self.action = match (er.edited(), er.created(), er.written()) {
+                    (true, true, true) => "created",
+                    (true, false, true) => "updated",
+                    (true, true, false) => "would_create",
+                    (true, false, false) => "would_update",
+                    (false, _, _) => "unchanged",
+                };
+                self
+            },
+            Ok(ItemUpdate::Skipped(reason)) => self.skipped(reason.as_str()),
+            Ok(ItemUpdate::Duplicates(candidate)) => {
+                report_merge_candidate(&self.input, &candidate, None, options);
+                self.action = "duplicate";
+                self.merge_candidate = Some(candidate);
+                self
+            },
+            Err(e) => self.error(e),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let ids = |ids: &[GenericWorkIdentifier]| -> Vec<serde_json::Value> {
+            ids.iter()
+                .map(
+                    |id| serde_json::json!({"property": id.work_type().to_string(), "id": id.id()}),
+                )
+                .collect()
+        };
+        serde_json::json!({
+            "type": "result",
+            "input": self.input,
+            "parsed_ids": ids(&self.parsed_ids),
+            "discovered_ids": ids(&self.discovered_ids),
+            "q": self.q,
+            "action": self.action,
+            "error": self.error,
+            "skip_reason": self.skip_reason,
+            "merge_candidate": self.merge_candidate.as_ref().map(MergeCandidate::to_json),
+            "edited_items": self.edited_items,
+        })
+    }
+
+    /// Prints the result as JSON or, as before `--output jsonl`, as text.
+    fn print(&self, options: &Options) {
+        if options.jsonl {
+            println!("{}", self.to_json());
+            return;
+        }
+        let q = self.q.as_deref().unwrap_or_default();
+        match self.action {
+            "created" | "updated" => {
+                println!("Created or updated https://www.wikidata.org/wiki/{q}")
+            },
+            "unchanged" => println!("Exists as https://www.wikidata.org/wiki/{q}, no changes"),
+            "would_create" => println!("Would create an item for '{}'", self.input),
+            "would_update" => println!("Would update https://www.wikidata.org/wiki/{q}"),
+            "skipped" => println!(
+                "Skipped '{}': {}",
+                self.input,
+                self.skip_reason.as_deref().unwrap_or_default()
+            ),
+            "duplicate" => println!(
+                "Not editing '{}', its IDs are on several items: {}",
+                self.input,
+                self.merge_candidate.as_ref().map(|c| c.to_string()).unwrap_or_default()
+            ),
+            _ => eprintln!(
+                "Error processing '{}': {}",
+                self.input,
+                self.error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+async fn command_authors(ini_file: &str, options: &Options) {
     let smd = Arc::new(RwLock::new(SourceMD::new(ini_file).await.unwrap()));
     let mw_api = smd.read().await.mw_api();
-    let cache = Arc::new(WikidataStringCache::new(mw_api.clone()));
+    let cache = string_cache(ini_file, mw_api.clone(), options);
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
         let line = match line {
@@ -27,23 +229,56 @@ async fn command_authors(ini_file: &str) {
         if line.is_empty() {
             continue;
         }
-        println!("Processing {}", line);
-        author_from_id(&line, cache.clone(), smd.clone()).await;
+        if !options.jsonl {
+            println!("Processing {}", line);
+        }
+        author_from_id(&line, cache.clone(), smd.clone(), options).await;
     }
 }
 
-async fn author_from_id(id: &str, cache: Arc<WikidataStringCache>, smd: Arc<RwLock<SourceMD>>) {
+async fn author_from_id(
+    id: &str,
+    cache: Arc<WikidataStringCache>,
+    smd: Arc<RwLock<SourceMD>>,
+    options: &Options,
+) {
     let mut command = SourceMDcommand::new_dummy(id);
-    let bot = SourceMDbot::new(smd.clone(), cache.clone(), 0).await.unwrap();
-    bot.process_author_metadata(&mut command).await.unwrap();
+    let mut bot = SourceMDbot::new_standalone(smd.clone(), cache.clone());
+    bot.set_output(options.output.clone());
+    let result = bot.process_author_metadata(&mut command).await;
+    if !options.jsonl {
+        match result {
+            Ok(_) => println!("{}: {}", id, command.note),
+            Err(e) => eprintln!("Error processing '{}': {:#}", id, e),
+        }
+        return;
+    }
+    let mut line = LineResult::new(id);
+    line.q = Some(command.q.to_owned()).filter(|q| !q.is_empty());
+    line = match result {
+        // The note starts with "edited" or "not edited"; see process_author_metadata
+        Ok(true) if command.note.starts_with("edited") => line.updated(options),
+        Ok(true) => LineResult { action: "unchanged", ..line },
+        Ok(false) => line.skipped(&command.note),
+        Err(e) => line.error(e),
+    };
+    line.print(options);
 }
 
-async fn command_ans(ini_file: &str) {
+async fn command_ans(ini_file: &str, options: &Options) {
     const MAX_AUTHORS_IN_PARALLEL: usize = 5;
     let smd = Arc::new(RwLock::new(SourceMD::new(ini_file).await.unwrap()));
     let mw_api = smd.read().await.mw_api();
-    let cache = Arc::new(WikidataStringCache::new(mw_api.clone()));
-    let ans = AuthorNameString::new(2, std::sync::Arc::new(papers::http_client::HttpJsonFetcher::default()));
+    let cache = string_cache(ini_file, mw_api.clone(), options);
+    let logging_level = if options.jsonl { 0 } else { 2 };
+    let mut ans = AuthorNameString::new(
+        logging_level,
+        std::sync::Arc::new(papers::http_client::HttpJsonFetcher::default()),
+    );
+    ans.set_output(options.output.clone());
+    let ans = &ans;
+    let mw_api = &mw_api;
+    let cache = &cache;
 
     let mut futures: Vec<_> = io::stdin()
         .lock()
@@ -52,7 +287,20 @@ async fn command_ans(ini_file: &str) {
         .map_while(Result::ok)
         .map(|line| line.trim().to_string())
         .filter(|line| !line.is_empty())
-        .map(|line| ans.process_author_q(line, &mw_api, &cache))
+        .map(|line| async move {
+            let result = ans.process_author_q(line.to_owned(), mw_api, cache).await;
+            if !options.jsonl {
+                return;
+            }
+            let mut line = LineResult::new(&line);
+            line.q = Some(line.input.to_owned());
+            line = match result {
+                Ok(edited) if edited.is_empty() => LineResult { action: "unchanged", ..line },
+                Ok(edited) => LineResult { edited_items: edited, ..line }.updated(options),
+                Err(e) => line.error(e),
+            };
+            line.print(options);
+        })
         .collect();
     futures.shuffle(&mut rand::rng());
 
@@ -60,29 +308,154 @@ async fn command_ans(ini_file: &str) {
     stream.collect::<Vec<_>>().await;
 }
 
-async fn command_papers(ini_file: &str) {
+/// Reuses `WikidataPapers` instances, and so their adapters, across
+/// papers. The adapters keep every work they have fetched, so an instance
+/// is dropped after `MAX_PAPERS_PER_INSTANCE` papers.
+struct PapersPool {
+    cache: Arc<WikidataStringCache>,
+    create_journals: bool,
+    output: EditOutput,
+    duplicates: DuplicatePolicy,
+    idle: RefCell<Vec<(WikidataPapers, usize)>>,
+}
+
+impl PapersPool {
+    const MAX_PAPERS_PER_INSTANCE: usize = 100;
+
+    fn new(
+        cache: Arc<WikidataStringCache>,
+        create_journals: bool,
+        output: EditOutput,
+        duplicates: DuplicatePolicy,
+    ) -> Self {
+        Self { cache, create_journals, output, duplicates, idle: RefCell::new(vec![]) }
+    }
+
+    /// An idle instance, or a new one, with the number of papers it has
+    /// processed.
+    fn take(&self) -> (WikidataPapers, usize) {
+        if let Some(idle) = self.idle.borrow_mut().pop() {
+            return idle;
+        }
+        let mut wdp = WikidataPapers::with_default_adapters(self.cache.clone());
+        wdp.set_create_journals(self.create_journals);
+        wdp.set_output(self.output.clone());
+        wdp.set_duplicate_policy(self.duplicates);
+        (wdp, 0)
+    }
+
+    fn give_back(&self, mut wdp: WikidataPapers, papers: usize) {
+        if papers < Self::MAX_PAPERS_PER_INSTANCE {
+            wdp.clear_entities();
+            self.idle.borrow_mut().push((wdp, papers));
+        }
+    }
+}
+
+async fn command_papers(ini_file: &str, options: &Options) {
     let mw_api = Arc::new(RwLock::new(SourceMD::create_mw_api(ini_file).await.unwrap()));
+    let create_journals = SourceMD::create_journals_from_ini(ini_file);
+    let cache = string_cache(ini_file, mw_api.clone(), options);
+    let duplicates =
+        options.duplicates.unwrap_or_else(|| SourceMD::duplicate_policy_from_ini(ini_file));
+    let pool = PapersPool::new(cache.clone(), create_journals, options.output.clone(), duplicates);
+    let pool = &pool;
+    let mw_api = &mw_api;
+    let checkpoint = options.checkpoint.as_deref();
+    if let Some(checkpoint) = checkpoint {
+        tracing::info!(done = checkpoint.len(), "skipping inputs in the checkpoint");
+    }
+    // Nothing was written to Wikidata, so the inputs aren't done
+    let mark_done = options.output.is_live();
+
     let stdin = io::stdin();
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l.trim().to_string(),
-            Err(_) => break,
-        };
-        if line.is_empty() {
-            continue;
+    let inputs: Box<dyn Iterator<Item = PaperInput>> = match options.format {
+        None => Box::new(
+            stdin
+                .lock()
+                .lines()
+                .map_while(Result::ok)
+                .map(|line| PaperInput { input: line.trim().to_string(), ids: None }),
+        ),
+        Some(format) => Box::new(reference_inputs(format, options).into_iter()),
+    };
+    let inputs: Vec<PaperInput> = inputs
+        .filter(|paper| {
+            let input = &paper.input;
+            !input.is_empty() && !checkpoint.is_some_and(|c| c.is_done(input))
+        })
+        .collect();
+
+    // One SPARQL query per few hundred IDs, instead of one search per ID.
+    // The cache only holds so many, so the IDs are prefetched one window of
+    // inputs at a time, as the inputs before them are started.
+    let cache = &cache;
+    let windows: Vec<Vec<PaperInput>> =
+        inputs.chunks(cache.prefetch_window()).map(<[PaperInput]>::to_vec).collect();
+    futures::stream::iter(windows)
+        .then(|window| async move {
+            let pairs: Vec<(String, String)> =
+                window.iter().flat_map(PaperInput::cache_keys).collect();
+            cache.prefetch(&pairs).await;
+            futures::stream::iter(window)
+        })
+        .flatten()
+        .map(|paper| async move {
+            let (mut wdp, papers) = pool.take();
+            let result = match paper.ids {
+                Some(ids) => {
+                    let line = LineResult::new(&paper.input);
+                    paper_from_ids(line, ids, &mut wdp, mw_api.clone(), options).await
+                },
+                None => paper_from_id(&paper.input, &mut wdp, mw_api.clone(), options).await,
+            };
+            pool.give_back(wdp, papers + 1);
+            result.print(options);
+            // Failed inputs are tried again on the next run
+            if mark_done && result.action != "error" {
+                if let Some(Err(error)) = checkpoint.map(|c| c.mark_done(&paper.input)) {
+                    tracing::error!(%error, input = %paper.input, "could not update checkpoint");
+                }
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .for_each(|_| futures::future::ready(()))
+        .await;
+}
+
+/// Reads a reference list from stdin, and reports the entries without any
+/// ID on stderr for looking them up by hand.
+fn reference_inputs(format: ReferenceFormat, options: &Options) -> Vec<PaperInput> {
+    let mut text = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut text) {
+        eprintln!("Can't read the reference list: {e}");
+        return vec![];
+    }
+    let (found, unresolved): (Vec<_>, Vec<_>) =
+        format.parse(&text).into_iter().partition(|reference| !reference.ids.is_empty());
+    for reference in unresolved {
+        if options.jsonl {
+            eprintln!("{}", serde_json::json!({"unresolved": reference.to_json()}));
+        } else {
+            eprintln!("No ID found for {reference}");
         }
-        // println!("Processing {}", &line);
-        paper_from_id(&line, mw_api.clone()).await;
     }
+    found
+        .into_iter()
+        .map(|reference| PaperInput { input: reference.label(), ids: Some(reference.ids) })
+        .collect()
 }
 
-async fn paper_from_id(id: &str, mw_api: Arc<RwLock<Api>>) {
-    let cache = Arc::new(WikidataStringCache::new(mw_api.clone()));
-    let mut wdp = WikidataPapers::with_default_adapters(cache.clone());
-
+async fn paper_from_id(
+    id: &str,
+    wdp: &mut WikidataPapers,
+    mw_api: Arc<RwLock<Api>>,
+    options: &Options,
+) -> LineResult {
+    let line = LineResult::new(id);
     if papers::identifiers::is_qid(id) {
-        save_item_changes(&mut wdp, mw_api.clone(), id).await;
-        return;
+        let update = wdp.create_or_update_item_from_q(mw_api, id).await;
+        return line.finish(update, options);
     }
 
     let mut ids = GenericWorkIdentifier::parse_ids_from_str(id);
@@ -91,46 +464,258 @@ async fn paper_from_id(id: &str, mw_api: Arc<RwLock<Api>>) {
     ids.retain(|id| !id.id().is_empty());
 
     if ids.is_empty() {
-        println!("Can't find a valid ID in '{}'", id);
+        return line.skipped("no valid ID");
+    }
+    paper_from_ids(line, ids, wdp, mw_api, options).await
+}
+
+async fn paper_from_ids(
+    mut line: LineResult,
+    ids: Vec<GenericWorkIdentifier>,
+    wdp: &mut WikidataPapers,
+    mw_api: Arc<RwLock<Api>>,
+    options: &Options,
+) -> LineResult {
+    line.parsed_ids = ids.clone();
+    let ids = wdp.update_from_paper_ids(&ids).await;
+    line.discovered_ids = ids.iter().filter(|id| !line.parsed_ids.contains(id)).cloned().collect();
+    let update = wdp.create_or_update_item_from_ids(mw_api, &ids).await;
+    line.finish(update, options)
+}
+
+/// Prints where the sources disagreed to stderr, either as text or as one
+/// JSON object per paper.
+fn report_discrepancies(id: &str, er: &EditResult, as_json: bool) {
+    let report = er.discrepancies();
+    if report.is_empty() {
         return;
     }
-    // println!("IDs: {:?}", &ids);
-    ids = wdp.update_from_paper_ids(&ids).await;
+    if as_json {
+        let j = serde_json::json!({"id": id, "q": er.q(), "discrepancies": report.to_json()});
+        eprintln!("{j}");
+    } else {
+        eprintln!("Discrepancies for {} ({}): {}", id, er.q(), report);
+    }
+}
 
-    match wdp.create_or_update_item_from_ids(mw_api, &ids).await {
-        Ok(Some(er)) => {
-            if er.edited() {
-                println!("Created or updated https://www.wikidata.org/wiki/{}", er.q())
-            } else {
-                println!("Exists as https://www.wikidata.org/wiki/{}, no changes ", er.q())
-            }
+/// Writes a paper whose IDs are on several items to `--merge-candidates`,
+/// with the item that was edited, if any.
+fn report_merge_candidate(
+    input: &str,
+    candidate: &MergeCandidate,
+    edited: Option<&str>,
+    options: &Options,
+) {
+    if let Some(writer) = &options.merge_candidates {
+        if let Err(e) = writer.write(input, candidate, edited) {
+            eprintln!("Can't write merge candidate for '{input}': {e:#}");
+        }
+    }
+}
+
+/// Prints what the adapters report for each paper ID on stdin, as one JSON
+/// object per line. Needs neither a login nor a configuration file.
+async fn command_lookup() {
+    let mw_api = match Api::new(DEFAULT_WIKIDATA_API_URL).await {
+        Ok(api) => Arc::new(RwLock::new(api)),
+        Err(e) => {
+            eprintln!("Can't connect to Wikidata: {e}");
+            return;
+        },
+    };
+    let cache = Arc::new(WikidataStringCache::new(mw_api));
+    let mut wdp = WikidataPapers::with_default_adapters(cache);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(l) => l.trim().to_string(),
+            Err(_) => break,
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let mut ids = GenericWorkIdentifier::parse_ids_from_str(&line);
+        ids.retain(|id| !id.id().is_empty());
+        if ids.is_empty() {
+            eprintln!("Can't find a valid ID in '{}'", line);
+            continue;
+        }
+        match wdp.lookup(&ids).await {
+            Ok(mut j) => {
+                j["input"] = serde_json::json!(line);
+                println!("{j}");
+            },
+            Err(e) => eprintln!("Error looking up '{}': {:#}", line, e),
+        }
+    }
+}
+
+/// Compares each publication item on stdin with what the adapters report,
+/// and prints the findings as one JSON object per item. Needs neither a
+/// login nor a configuration file, and edits nothing.
+async fn command_audit() {
+    let mw_api = match Api::new(DEFAULT_WIKIDATA_API_URL).await {
+        Ok(api) => Arc::new(RwLock::new(api)),
+        Err(e) => {
+            eprintln!("Can't connect to Wikidata: {e}");
+            return;
+        },
+    };
+    let cache = Arc::new(WikidataStringCache::new(mw_api.clone()));
+    let mut wdp = WikidataPapers::with_default_adapters(cache);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let q = match line {
+            Ok(l) => l.trim().to_string(),
+            Err(_) => break,
+        };
+        if q.is_empty() {
+            continue;
+        }
+        if !papers::identifiers::is_qid(&q) {
+            eprintln!("Not an item: '{q}'");
+            continue;
+        }
+        let item = {
+            let api = mw_api.read().await;
+            wdp.entities_mut().load_entity(&api, q.to_owned()).await.map(|item| item.to_owned())
+        };
+        match item {
+            Ok(item) => println!("{}", wdp.audit(&item).await.to_json()),
+            Err(e) => eprintln!("Can't load {q}: {e}"),
+        }
+        wdp.clear_entities();
+    }
+}
+
+/// Writes the identifier index for the Wikidata JSON dump (plain or
+/// gzipped) on stdin to `--id-index <file>`.
+fn command_index(options: &Options) {
+    let Some(file) = &options.id_index else {
+        eprintln!("`index` needs --id-index <file>");
+        std::process::exit(1);
+    };
+    let out = match std::fs::File::create(file) {
+        Ok(out) => io::BufWriter::new(out),
+        Err(e) => {
+            eprintln!("Can't create {file}: {e}");
+            std::process::exit(1);
+        },
+    };
+    match build_index(io::stdin().lock(), out) {
+        Ok(entries) => eprintln!("Wrote {entries} entries to {file}"),
+        Err(e) => {
+            eprintln!("Can't build the index: {e:#}");
+            std::process::exit(1);
         },
-        Ok(None) => println!("No item ID for '{}'!", id),
-        Err(e) => eprintln!("Error processing '{}': {:#}", id, e),
     }
 }
 
-async fn save_item_changes(wdp: &mut WikidataPapers, mw_api: Arc<RwLock<Api>>, q: &str) {
-    match wdp.create_or_update_item_from_q(mw_api, q).await {
-        Ok(Some(er)) => {
-            if er.edited() {
-                println!("Created or updated https://www.wikidata.org/wiki/{}", er.q())
-            } else {
-                println!("https://www.wikidata.org/wiki/{}, no changes ", er.q())
-            }
+/// Prints the items on stdin, or those found by `--sparql`, as CSL-JSON or
+/// BibTeX. Needs neither a login nor a configuration file.
+async fn command_export(options: &Options) {
+    let api = match Api::new(DEFAULT_WIKIDATA_API_URL).await {
+        Ok(api) => api,
+        Err(e) => {
+            eprintln!("Can't connect to Wikidata: {e}");
+            return;
         },
-        Ok(None) => println!("No item ID!"),
-        Err(e) => eprintln!("Error saving {}: {:#}", q, e),
+    };
+    let qs: Vec<String> = match &options.sparql {
+        Some(query) => match api.sparql_query(query).await {
+            Ok(result) => items_from_sparql_result(&result),
+            Err(e) => {
+                eprintln!("SPARQL query failed: {e}");
+                return;
+            },
+        },
+        None => io::stdin()
+            .lock()
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+    };
+    for q in qs.iter().filter(|q| !papers::identifiers::is_qid(q)) {
+        eprintln!("Not an item: '{q}'");
+    }
+    match load_citations(&api, &qs).await {
+        Ok(citations) => println!("{}", options.export_format.render(&citations)),
+        Err(e) => eprintln!("Can't load the items: {e:#}"),
     }
 }
 
 fn usage(prog: &str) {
     println!("USAGE: {} [--config <file>] <subcommand>", prog);
-    println!("Subcommands: papers, authors, bot, ans");
+    println!("Subcommands: papers, authors, bot, ans, lookup, export, audit, index");
+    println!("  `lookup` prints what the sources report for each paper ID on");
+    println!("  stdin as JSON, without logging in or editing.");
+    println!("  `export` prints the items on stdin as CSL-JSON or BibTeX.");
+    println!("  `audit` compares the items on stdin with the sources, and prints");
+    println!("  the mismatches as JSON, without editing.");
+    println!("  `index` reads a Wikidata JSON dump (plain or gzipped) on stdin, and");
+    println!("  writes the identifier index for --id-index.");
     println!("  --config <file>  Configuration file (default: {})", INI_FILE);
     println!("                   For the `bot` subcommand the file must also");
     println!("                   contain a [client] section with `user` and");
     println!("                   `password` for the SourceMD MySQL DB.");
+    println!("  --dry-run        `papers`, `authors`, `ans`: don't edit Wikidata;");
+    println!("                   print every edit and item creation that would");
+    println!("                   have been made as one JSON object per line,");
+    println!("                   with \"type\": \"edit\".");
+    println!("  --quickstatements <file>");
+    println!("                   `papers`, `authors`, `ans`: don't edit Wikidata;");
+    println!("                   write the edits to <file> as QuickStatements V1.");
+    println!("  --output <format>");
+    println!("                   `papers`, `authors`, `ans`: `text` (default), or");
+    println!("                   `jsonl` for one JSON object per input line with");
+    println!("                   input, IDs, item, action, error and skip reason,");
+    println!("                   and \"type\": \"result\".");
+    println!("  --concurrency <n>");
+    println!("                   `papers`: process up to <n> inputs at a time");
+    println!("                   (default: 1). Results are printed as they");
+    println!("                   finish, so not in input order.");
+    println!("  --checkpoint <file>");
+    println!("                   `papers`: skip the inputs listed in <file>, and");
+    println!("                   add each input to it once it is done, so an");
+    println!("                   interrupted run can be resumed. Inputs that");
+    println!("                   failed are not added, and nothing is added");
+    println!("                   with --dry-run or --quickstatements.");
+    println!("  --format <format>");
+    println!("                   `papers`: `lines` (default) for one ID per line,");
+    println!("                   or a `bibtex` or `ris` reference list. Entries");
+    println!("                   without an ID are listed on stderr.");
+    println!("  --export-format <format>");
+    println!("                   `export`: `csl-json` (default) or `bibtex`.");
+    println!("  --sparql <query> `export`: export the items in the first item");
+    println!("                   column of the query result, instead of stdin.");
+    println!("  --duplicates <policy>");
+    println!("                   `papers`: what to do if a paper's IDs are on");
+    println!("                   several items; `refuse` (default) edits none of");
+    println!("                   them, `oldest` edits the lowest Q number, unless");
+    println!("                   one ID is on several items.");
+    println!("  --merge-candidates <file>");
+    println!("                   `papers`: write papers whose IDs are on several");
+    println!("                   items to <file>, as CSV if it ends in .csv, or");
+    println!("                   as one JSON object per line.");
+    println!("  --cache <file>   `papers`, `authors`, `ans`, `bot`: keep the items");
+    println!("                   found for IDs in <file>, and reuse them on the");
+    println!("                   next run. Can also be set as `cache_file` in the");
+    println!("                   [papers] section of the configuration file.");
+    println!("  --id-index <file>");
+    println!("                   `papers`, `authors`, `ans`, `bot`: look IDs up in");
+    println!("                   the index <file> built by `index`, and only search");
+    println!("                   Wikidata for those not in it. Can also be set as");
+    println!("                   `id_index` in the [papers] section of the");
+    println!("                   configuration file.");
+    println!("  --duplicates-log <file>");
+    println!("                   `papers`, `authors`, `ans`, `bot`: list IDs that");
+    println!("                   are on several items in <file>, one JSON object");
+    println!("                   per line. Such IDs are never used to pick an item.");
+    println!("  --discrepancies-json");
+    println!("                   `papers`: report source disagreements on stderr");
+    println!("                   as one JSON object per line instead of text.");
 }
 
 /// Outcome of one tick of the bot driver.
@@ -180,7 +765,7 @@ async fn run_bot(config: Arc<RwLock<SourceMD>>, cache: Arc<WikidataStringCache>)
     BotTick::Worked
 }
 
-async fn command_bot(ini_file: &str) {
+async fn command_bot(ini_file: &str, options: &Options) {
     tracing::info!("starting bot mode");
     let mut smd = SourceMD::new(ini_file).await.unwrap();
     if let Err(e) = smd.init(ini_file).await {
@@ -189,7 +774,7 @@ async fn command_bot(ini_file: &str) {
     }
     let smd = Arc::new(RwLock::new(smd));
     let mw_api = Arc::new(RwLock::new(SourceMD::create_mw_api(ini_file).await.unwrap()));
-    let cache = Arc::new(WikidataStringCache::new(mw_api));
+    let cache = string_cache(ini_file, mw_api, options);
     // Cross-tick state for rate-limiting DB-outage error logs: log on the
     // 1st, 2nd, 4th, 8th, … consecutive failure, plus an info! on recovery.
     let mut db_log =
@@ -216,6 +801,167 @@ async fn command_bot(ini_file: &str) {
 // For local testing:
 // ssh magnus@tools-login.wmflabs.org -L 3307:tools-db:3306 -N &
 
+/// The identifier cache, kept in `--cache <file>` or in the `cache_file`
+/// from the configuration file, reading `--id-index <file>` or the
+/// `id_index` from the configuration file, and writing to
+/// `--duplicates-log <file>`, if given; exits if one of those files can't be
+/// opened.
+fn string_cache(
+    ini_file: &str,
+    mw_api: Arc<RwLock<Api>>,
+    options: &Options,
+) -> Arc<WikidataStringCache> {
+    let mut cache = WikidataStringCache::new(mw_api);
+    match SourceMD::persistent_cache_from_ini(ini_file, options.cache_file.as_deref()) {
+        Ok(Some(store)) => cache = cache.with_store(Arc::new(store)),
+        Ok(None) => {},
+        Err(e) => {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        },
+    }
+    match SourceMD::identifier_index_from_ini(ini_file, options.id_index.as_deref()) {
+        Ok(Some(index)) => cache = cache.with_index(Arc::new(index)),
+        Ok(None) => {},
+        Err(e) => {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        },
+    }
+    if let Some(log) = &options.duplicates_log {
+        cache = cache.with_duplicates_log(log.clone());
+    }
+    Arc::new(cache)
+}
+
+/// `--dry-run` or `--quickstatements <file>`; exits if both are given, or
+/// the file can't be opened.
+fn edit_output(pargs: &mut Arguments) -> EditOutput {
+    let dry_run = pargs.contains("--dry-run");
+    let qs_file: Option<String> = pargs.opt_value_from_str("--quickstatements").unwrap_or(None);
+    match (dry_run, qs_file) {
+        (false, None) => EditOutput::Live,
+        (true, None) => EditOutput::DryRun,
+        (false, Some(file)) => match QuickStatementsWriter::open(&file) {
+            Ok(writer) => EditOutput::QuickStatements(writer),
+            Err(e) => {
+                eprintln!("Can't create {file}: {e:#}");
+                std::process::exit(1);
+            },
+        },
+        (true, Some(_)) => {
+            eprintln!("Use either --dry-run or --quickstatements, not both");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--output jsonl`, or the default `--output text`; exits on anything
+/// else.
+fn jsonl_output(pargs: &mut Arguments) -> bool {
+    let output: Option<String> = pargs.opt_value_from_str("--output").unwrap_or(None);
+    match output.as_deref() {
+        None | Some("text") => false,
+        Some("jsonl") => true,
+        Some(other) => {
+            eprintln!("Unknown output format '{other}'; use text or jsonl");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--concurrency <n>`, default 1; exits if it isn't a positive number.
+fn concurrency(pargs: &mut Arguments) -> usize {
+    match pargs.opt_value_from_str::<_, usize>("--concurrency") {
+        Ok(None) => 1,
+        Ok(Some(n)) if n > 0 => n,
+        _ => {
+            eprintln!("--concurrency needs a positive number");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--format bibtex|ris`, or `None` for the default `--format lines`; exits
+/// on anything else.
+fn reference_format(pargs: &mut Arguments) -> Option<ReferenceFormat> {
+    let format: Option<String> = pargs.opt_value_from_str("--format").unwrap_or(None);
+    match format.as_deref() {
+        None | Some("lines") => None,
+        Some(format) => match format.parse() {
+            Ok(format) => Some(format),
+            Err(e) => {
+                eprintln!("{e}; use lines, bibtex or ris");
+                std::process::exit(1);
+            },
+        },
+    }
+}
+
+/// `--export-format csl-json|bibtex`, default CSL-JSON; exits on anything
+/// else.
+fn export_format(pargs: &mut Arguments) -> CitationFormat {
+    let format: Option<String> = pargs.opt_value_from_str("--export-format").unwrap_or(None);
+    match format.map(|format| format.parse()) {
+        None => CitationFormat::default(),
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            eprintln!("{e}; use csl-json or bibtex");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--duplicates refuse|oldest`, or `None` to use the configuration file;
+/// exits on anything else.
+fn duplicate_policy(pargs: &mut Arguments) -> Option<DuplicatePolicy> {
+    let policy: Option<String> = pargs.opt_value_from_str("--duplicates").unwrap_or(None);
+    match policy.map(|policy| policy.parse()) {
+        None => None,
+        Some(Ok(policy)) => Some(policy),
+        Some(Err(e)) => {
+            eprintln!("{e}; use refuse or oldest");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--merge-candidates <file>`; exits if the file can't be opened.
+fn merge_candidates(pargs: &mut Arguments) -> Option<MergeCandidateWriter> {
+    let file: String = pargs.opt_value_from_str("--merge-candidates").unwrap_or(None)?;
+    match MergeCandidateWriter::open(&file) {
+        Ok(writer) => Some(writer),
+        Err(e) => {
+            eprintln!("Can't open {file}: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--duplicates-log <file>`; exits if the file can't be opened.
+fn duplicates_log(pargs: &mut Arguments) -> Option<DuplicatesLog> {
+    let file: String = pargs.opt_value_from_str("--duplicates-log").unwrap_or(None)?;
+    match DuplicatesLog::open(&file) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            eprintln!("Can't open {file}: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `--checkpoint <file>`; exits if the file can't be read or created.
+fn checkpoint(pargs: &mut Arguments) -> Option<Arc<Checkpoint>> {
+    let file: String = pargs.opt_value_from_str("--checkpoint").unwrap_or(None)?;
+    match Checkpoint::open(&file) {
+        Ok(checkpoint) => Some(Arc::new(checkpoint)),
+        Err(e) => {
+            eprintln!("Can't open checkpoint {file}: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
 fn init_tracing() {
     use tracing_subscriber::EnvFilter;
     // Honour RUST_LOG if set; otherwise default to INFO for our crate.
@@ -233,12 +979,37 @@ async fn main() {
         .opt_value_from_str("--config")
         .unwrap_or(None)
         .unwrap_or_else(|| INI_FILE.to_string());
+    let options = Options {
+        output: edit_output(&mut pargs),
+        discrepancies_json: pargs.contains("--discrepancies-json"),
+        jsonl: jsonl_output(&mut pargs),
+        concurrency: concurrency(&mut pargs),
+        checkpoint: checkpoint(&mut pargs),
+        format: reference_format(&mut pargs),
+        export_format: export_format(&mut pargs),
+        sparql: pargs.opt_value_from_str("--sparql").unwrap_or(None),
+        duplicates: duplicate_policy(&mut pargs),
+        merge_candidates: merge_candidates(&mut pargs),
+        cache_file: pargs.opt_value_from_str("--cache").unwrap_or(None),
+        id_index: pargs.opt_value_from_str("--id-index").unwrap_or(None),
+        duplicates_log: duplicates_log(&mut pargs),
+    };
 
     match pargs.subcommand().unwrap_or_default().as_deref() {
-        Some("papers") => command_papers(&config).await,
-        Some("authors") => command_authors(&config).await,
-        Some("bot") => command_bot(&config).await,
-        Some("ans") => command_ans(&config).await,
+        Some("papers") => command_papers(&config, &options).await,
+        Some("authors") => command_authors(&config, &options).await,
+        // The bot records command outcomes in the SourceMD database, so a
+        // dry run would mark commands done that were never run.
+        Some("bot") if !options.output.is_live() => {
+            eprintln!("--dry-run and --quickstatements can't be used with `bot`");
+            std::process::exit(1);
+        },
+        Some("bot") => command_bot(&config, &options).await,
+        Some("ans") => command_ans(&config, &options).await,
+        Some("lookup") => command_lookup().await,
+        Some("export") => command_export(&options).await,
+        Some("audit") => command_audit().await,
+        Some("index") => command_index(&options),
         _ => usage(&prog),
     }
 }
@@ -0,0 +1,116 @@
+//! Inputs a bulk run has finished, kept in a file so that an interrupted run
+//! can resume where it stopped.
+//!
+//! The file has one input per line. Lines are appended as soon as an input
+//! is done, in a single write each, so a crash loses at most the inputs
+//! that were still in flight.
+
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug)]
+pub struct Checkpoint {
+    done: Mutex<HashSet<String>>,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Reads the inputs done so far from `path`, which is created if it
+    /// doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut done = HashSet::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    done.insert(line.trim().to_string());
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { done: Mutex::new(done), file: Mutex::new(file) })
+    }
+
+    pub fn is_done(&self, input: &str) -> bool {
+        self.done.lock().map(|done| done.contains(input.trim())).unwrap_or(false)
+    }
+
+    /// Number of inputs done, including those of earlier runs.
+    pub fn len(&self) -> usize {
+        self.done.lock().map(|done| done.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn mark_done(&self, input: &str) -> Result<()> {
+        let input = input.trim();
+        if input.is_empty() || input.contains('\n') {
+            return Err(anyhow!("Can't checkpoint input '{input}'"));
+        }
+        let mut done = self.done.lock().map_err(|_| anyhow!("checkpoint poisoned"))?;
+        if !done.insert(input.to_string()) {
+            return Ok(());
+        }
+        let mut file = self.file.lock().map_err(|_| anyhow!("checkpoint poisoned"))?;
+        file.write_all(format!("{input}\n").as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = Checkpoint::open(dir.path().join("done.txt")).unwrap();
+        assert!(checkpoint.is_empty());
+        assert!(!checkpoint.is_done("10.1000/XYZ"));
+    }
+
+    #[test]
+    fn done_inputs_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("done.txt");
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        checkpoint.mark_done("10.1000/XYZ").unwrap();
+        checkpoint.mark_done(" 12345 ").unwrap();
+        assert!(checkpoint.is_done("12345"));
+        drop(checkpoint);
+
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        assert_eq!(checkpoint.len(), 2);
+        assert!(checkpoint.is_done("10.1000/XYZ"));
+        assert!(checkpoint.is_done("12345"));
+        assert!(!checkpoint.is_done("67890"));
+    }
+
+    #[test]
+    fn inputs_are_written_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("done.txt");
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        checkpoint.mark_done("Q42").unwrap();
+        checkpoint.mark_done("Q42").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Q42\n");
+    }
+
+    #[test]
+    fn empty_input_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = Checkpoint::open(dir.path().join("done.txt")).unwrap();
+        assert!(checkpoint.mark_done("  ").is_err());
+    }
+}
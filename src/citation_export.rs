@@ -0,0 +1,555 @@
+//! Publication items as citations, in CSL-JSON or BibTeX.
+//!
+//! This is the reverse of what the adapters do: title (P1476), date (P577),
+//! volume (P478), issue (P433), pages (P304), journal (P1433), DOI, PMID and
+//! ISBN are read from the item, and authors from P50 and P2093 in P1545
+//! order. P50 authors and the journal are named by their English labels, so
+//! those items have to be loaded too.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use wikibase::{entity_container::EntityContainer, mediawiki::api::Api};
+
+use crate::{
+    discrepancy_report::time_to_date, generic_author_info::GenericAuthorInfo, identifiers::is_qid,
+    *,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CitationFormat {
+    #[default]
+    CslJson,
+    BibTeX,
+}
+
+impl std::str::FromStr for CitationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim() {
+            "csl-json" | "csljson" | "csl" => Ok(CitationFormat::CslJson),
+            "bibtex" | "bib" => Ok(CitationFormat::BibTeX),
+            _ => Err(format!("Unknown citation format: {s}")),
+        }
+    }
+}
+
+impl CitationFormat {
+    /// All citations as one document: a CSL-JSON array, or BibTeX entries
+    /// separated by blank lines.
+    pub fn render(&self, citations: &[Citation]) -> String {
+        match self {
+            CitationFormat::CslJson => {
+                let items: Vec<serde_json::Value> =
+                    citations.iter().map(|c| c.to_csl_json()).collect();
+                serde_json::to_string_pretty(&items).unwrap_or_default()
+            },
+            CitationFormat::BibTeX => {
+                citations.iter().map(|c| c.to_bibtex()).collect::<Vec<_>>().join("\n")
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CitationType {
+    /// Has a journal (P1433)
+    JournalArticle,
+    /// Has an ISBN, but no journal
+    Book,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Citation {
+    pub q: String,
+    pub citation_type: CitationType,
+    pub title: Option<String>,
+    /// Author names in P1545 order
+    pub authors: Vec<String>,
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, depending on the date precision
+    pub date: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub pages: Option<String>,
+    pub journal: Option<String>,
+    pub doi: Option<String>,
+    pub pmid: Option<String>,
+    pub isbn: Option<String>,
+}
+
+impl Citation {
+    /// Reads a citation from a publication item. `labels` has the names of
+    /// the author and journal items, by Q-ID; see [`linked_items`].
+    pub fn from_item(item: &Entity, labels: &HashMap<String, String>) -> Self {
+        let journal_q = first_entity(item, "P1433");
+        let isbn = first_string(item, "P212").or_else(|| first_string(item, "P957"));
+        let citation_type = match (&journal_q, &isbn) {
+            (Some(_), _) => CitationType::JournalArticle,
+            (None, Some(_)) => CitationType::Book,
+            (None, None) => CitationType::Other,
+        };
+        Self {
+            q: item.id().to_string(),
+            citation_type,
+            title: title(item),
+            authors: authors(item, labels),
+            date: first_date(item, "P577"),
+            volume: first_string(item, "P478"),
+            issue: first_string(item, "P433"),
+            pages: first_string(item, "P304"),
+            journal: journal_q.and_then(|q| labels.get(&q).cloned()),
+            doi: first_string(item, "P356"),
+            pmid: first_string(item, "P698"),
+            isbn,
+        }
+    }
+
+    pub fn to_csl_json(&self) -> serde_json::Value {
+        let mut j = json!({
+            "id": self.q,
+            "type": match self.citation_type {
+                CitationType::JournalArticle => "article-journal",
+                CitationType::Book => "book",
+                CitationType::Other => "article",
+            },
+            "URL": format!("https://www.wikidata.org/wiki/{}", self.q),
+        });
+        let fields = [
+            ("title", &self.title),
+            ("volume", &self.volume),
+            ("issue", &self.issue),
+            ("page", &self.pages),
+            ("container-title", &self.journal),
+            ("DOI", &self.doi),
+            ("PMID", &self.pmid),
+            ("ISBN", &self.isbn),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                j[key] = json!(value);
+            }
+        }
+        if !self.authors.is_empty() {
+            j["author"] = self.authors.iter().map(|name| csl_name(name)).collect();
+        }
+        if let Some(date) = &self.date {
+            let parts: Vec<u32> = date.split('-').filter_map(|part| part.parse().ok()).collect();
+            j["issued"] = json!({"date-parts": [parts]});
+        }
+        j
+    }
+
+    /// One BibTeX entry, keyed by the Q-ID.
+    pub fn to_bibtex(&self) -> String {
+        let entry_type = match self.citation_type {
+            CitationType::JournalArticle => "article",
+            CitationType::Book => "book",
+            CitationType::Other => "misc",
+        };
+        let mut fields: Vec<(&str, String)> = vec![];
+        if !self.authors.is_empty() {
+            let authors: Vec<String> = self.authors.iter().map(|name| bibtex_name(name)).collect();
+            fields.push(("author", authors.join(" and ")));
+        }
+        if let Some(title) = &self.title {
+            // Double braces keep BibTeX styles from changing the case
+            fields.push(("title", format!("{{{}}}", bibtex_escape(title))));
+        }
+        if let Some(journal) = &self.journal {
+            fields.push(("journal", bibtex_escape(journal)));
+        }
+        let mut date_parts = self.date.iter().flat_map(|date| date.split('-'));
+        if let Some(year) = date_parts.next() {
+            fields.push(("year", year.to_string()));
+        }
+        if let Some(month) = date_parts.next() {
+            fields.push(("month", month.trim_start_matches('0').to_string()));
+        }
+        for (key, value) in [("volume", &self.volume), ("number", &self.issue)] {
+            if let Some(value) = value {
+                fields.push((key, bibtex_escape(value)));
+            }
+        }
+        // Identifiers are verbatim, like `url`: styles link them, and `\_` in
+        // a DOI would be a different DOI
+        for (key, value) in [("doi", &self.doi), ("pmid", &self.pmid), ("isbn", &self.isbn)] {
+            if let Some(value) = value {
+                fields.push((key, value.to_owned()));
+            }
+        }
+        if let Some(pages) = &self.pages {
+            fields.push(("pages", bibtex_escape(&pages.replace('-', "--"))));
+        }
+        fields.push(("url", format!("https://www.wikidata.org/wiki/{}", self.q)));
+
+        let mut ret = format!("@{entry_type}{{{},\n", self.q);
+        for (key, value) in fields {
+            ret += &format!("  {key} = {{{value}}},\n");
+        }
+        ret += "}\n";
+        ret
+    }
+}
+
+/// The author and journal items of `item`, whose labels
+/// [`Citation::from_item`] needs.
+pub fn linked_items(item: &Entity) -> Vec<String> {
+    let mut qs: Vec<String> = item
+        .claims()
+        .iter()
+        .filter(|statement| ["P50", "P1433"].contains(&statement.property()))
+        .filter_map(entity_value)
+        .collect();
+    qs.sort();
+    qs.dedup();
+    qs
+}
+
+/// Loads `qs`, and their authors and journals, and reads a citation from
+/// each of them. Items that can't be loaded are left out with a warning.
+pub async fn load_citations(api: &Api, qs: &[String]) -> Result<Vec<Citation>> {
+    let mut entities = EntityContainer::new();
+    entities.allow_special_entity_data(false);
+    let qs: Vec<String> = qs.iter().filter(|q| is_qid(q)).cloned().collect();
+    if qs.is_empty() {
+        return Ok(vec![]);
+    }
+    entities.load_entities(api, &qs).await.map_err(|e| anyhow!("load_entities: {e}"))?;
+    let items: Vec<Entity> = qs
+        .iter()
+        .filter_map(|q| {
+            let item = entities.get_entity(q.to_owned());
+            if item.is_none() {
+                tracing::warn!(%q, "could not load item");
+            }
+            item
+        })
+        .collect();
+
+    let mut linked: Vec<String> = items.iter().flat_map(linked_items).collect();
+    linked.sort();
+    linked.dedup();
+    if !linked.is_empty() {
+        entities.load_entities(api, &linked).await.map_err(|e| anyhow!("load_entities: {e}"))?;
+    }
+    let labels: HashMap<String, String> = linked
+        .into_iter()
+        .filter_map(|q| {
+            let entity = entities.get_entity(q.to_owned())?;
+            let label = entity.label_in_locale("en")?.to_string();
+            Some((q, label))
+        })
+        .collect();
+
+    Ok(items.iter().map(|item| Citation::from_item(item, &labels)).collect())
+}
+
+/// The items in the first column of a SPARQL result that has an item in
+/// each row, in result order.
+pub fn items_from_sparql_result(result: &serde_json::Value) -> Vec<String> {
+    let vars: Vec<&str> = result["head"]["vars"]
+        .as_array()
+        .map(|vars| vars.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let bindings = result["results"]["bindings"].as_array().cloned().unwrap_or_default();
+    let item_of = |binding: &serde_json::Value, var: &str| {
+        let uri = binding[var]["value"].as_str()?;
+        let q = uri.strip_prefix("http://www.wikidata.org/entity/")?;
+        is_qid(q).then(|| q.to_string())
+    };
+    let var = vars.into_iter().find(|var| {
+        !bindings.is_empty() && bindings.iter().all(|binding| item_of(binding, *var).is_some())
+    });
+    let Some(var) = var else {
+        return vec![];
+    };
+    let mut qs: Vec<String> = vec![];
+    for q in bindings.iter().filter_map(|binding| item_of(binding, var)) {
+        if !qs.contains(&q) {
+            qs.push(q);
+        }
+    }
+    qs
+}
+
+fn entity_value(statement: &Statement) -> Option<String> {
+    match statement.main_snak().data_value().as_ref()?.value() {
+        Value::Entity(entity) => Some(entity.id().to_string()),
+        _ => None,
+    }
+}
+
+fn first_entity(item: &Entity, property: &str) -> Option<String> {
+    item.claims().iter().filter(|s| s.property() == property).find_map(entity_value)
+}
+
+fn first_string(item: &Entity, property: &str) -> Option<String> {
+    item.claims().iter().filter(|s| s.property() == property).find_map(|statement| match statement
+        .main_snak()
+        .data_value()
+        .as_ref()?
+        .value()
+    {
+        Value::StringValue(s) => Some(s.to_string()),
+        _ => None,
+    })
+}
+
+fn first_date(item: &Entity, property: &str) -> Option<String> {
+    item.claims().iter().filter(|s| s.property() == property).find_map(|statement| match statement
+        .main_snak()
+        .data_value()
+        .as_ref()?
+        .value()
+    {
+        Value::Time(time) => time_to_date(time.time(), time.precision()),
+        _ => None,
+    })
+}
+
+/// P1476, or the English label for items without one.
+fn title(item: &Entity) -> Option<String> {
+    let p1476 = item.claims().iter().filter(|s| s.property() == "P1476").find_map(|statement| {
+        match statement.main_snak().data_value().as_ref()?.value() {
+            Value::MonoLingual(text) => Some(text.text().to_string()),
+            _ => None,
+        }
+    });
+    p1476.or_else(|| item.label_in_locale("en").map(|label| label.to_string()))
+}
+
+/// Authors sorted by P1545; those without one follow in statement order.
+/// A P50 author is named by the item label, or else by its "stated as"
+/// (P1932) name.
+fn authors(item: &Entity, labels: &HashMap<String, String>) -> Vec<String> {
+    let mut authors: Vec<(Option<u64>, String)> = item
+        .claims()
+        .iter()
+        .filter(|statement| ["P50", "P2093"].contains(&statement.property()))
+        .filter_map(GenericAuthorInfo::new_from_statement)
+        .filter_map(|author| {
+            let ordinal = author.list_number().and_then(|n| n.trim().parse().ok());
+            let name = author
+                .wikidata_item()
+                .and_then(|q| labels.get(q))
+                .map(|label| label.to_string())
+                .or_else(|| author.name().map(|name| name.to_string()))
+                .or_else(|| author.wikidata_item().map(|q| q.to_string()))?;
+            Some((ordinal, name))
+        })
+        .collect();
+    // Stable, so authors without an ordinal keep their order
+    authors.sort_by_key(|(ordinal, _)| (ordinal.is_none(), *ordinal));
+    authors.into_iter().map(|(_, name)| name).collect()
+}
+
+/// `Family, Given` or `Given Family` → CSL name; a single word becomes the
+/// family name.
+fn csl_name(name: &str) -> serde_json::Value {
+    let (family, given) = split_name(name);
+    match given {
+        Some(given) => json!({"family": family, "given": given}),
+        None => json!({"family": family}),
+    }
+}
+
+fn bibtex_name(name: &str) -> String {
+    match split_name(name) {
+        (family, Some(given)) => format!("{}, {}", bibtex_escape(family), bibtex_escape(given)),
+        (family, None) => bibtex_escape(family),
+    }
+}
+
+fn split_name(name: &str) -> (&str, Option<&str>) {
+    let name = name.trim();
+    if let Some((family, given)) = name.split_once(", ") {
+        return (family.trim(), Some(given.trim()));
+    }
+    match name.rsplit_once(' ') {
+        Some((given, family)) => (family.trim(), Some(given.trim())),
+        None => (name, None),
+    }
+}
+
+fn bibtex_escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                ret.push('\\');
+                ret.push(c);
+            },
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter_helpers::wb_time_from_partial;
+
+    fn string_statement(property: &str, value: &str) -> Statement {
+        Statement::new_normal(Snak::new_string(property, value), vec![], vec![])
+    }
+
+    fn author_statement(snak: Snak, ordinal: Option<&str>) -> Statement {
+        let qualifiers = ordinal.map(|n| Snak::new_string("P1545", n)).into_iter().collect();
+        Statement::new_normal(snak, qualifiers, vec![])
+    }
+
+    fn article() -> Entity {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(
+            Snak::new_monolingual_text("P1476", "A {study} of 5% of things", "en"),
+            vec![],
+            vec![],
+        ));
+        item.add_claim(wb_time_from_partial("P577", 2020, Some(3), None, vec![]));
+        item.add_claim(string_statement("P478", "12"));
+        item.add_claim(string_statement("P433", "3"));
+        item.add_claim(string_statement("P304", "100-110"));
+        item.add_claim(string_statement("P356", "10.1000/XYZ"));
+        item.add_claim(Statement::new_normal(Snak::new_item("P1433", "Q1000"), vec![], vec![]));
+        item.add_claim(author_statement(Snak::new_string("P2093", "Jane Doe"), Some("2")));
+        item.add_claim(author_statement(Snak::new_item("P50", "Q5"), Some("1")));
+        item.add_claim(author_statement(Snak::new_string("P2093", "Anon"), None));
+        item.add_claim(author_statement(Snak::new_item("P50", "Q6"), Some("10")));
+        item
+    }
+
+    /// The citation of `article()`, which as a new item has no Q-ID of its
+    /// own.
+    fn citation() -> Citation {
+        Citation { q: "Q123".to_string(), ..Citation::from_item(&article(), &labels()) }
+    }
+
+    fn labels() -> HashMap<String, String> {
+        [("Q5", "John Smith"), ("Q1000", "Journal of Things")]
+            .into_iter()
+            .map(|(q, label)| (q.to_string(), label.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn citation_format_from_str() {
+        assert_eq!("csl-json".parse::<CitationFormat>().unwrap(), CitationFormat::CslJson);
+        assert_eq!("BibTeX".parse::<CitationFormat>().unwrap(), CitationFormat::BibTeX);
+        assert!("ris".parse::<CitationFormat>().is_err());
+    }
+
+    #[test]
+    fn linked_items_are_authors_and_journal() {
+        assert_eq!(linked_items(&article()), vec!["Q1000", "Q5", "Q6"]);
+    }
+
+    #[test]
+    fn from_item_reads_fields() {
+        let c = Citation::from_item(&article(), &labels());
+        assert_eq!(c.citation_type, CitationType::JournalArticle);
+        assert_eq!(c.title.as_deref(), Some("A {study} of 5% of things"));
+        assert_eq!(c.date.as_deref(), Some("2020-03"));
+        assert_eq!(c.volume.as_deref(), Some("12"));
+        assert_eq!(c.issue.as_deref(), Some("3"));
+        assert_eq!(c.pages.as_deref(), Some("100-110"));
+        assert_eq!(c.journal.as_deref(), Some("Journal of Things"));
+        assert_eq!(c.doi.as_deref(), Some("10.1000/XYZ"));
+    }
+
+    #[test]
+    fn authors_are_in_ordinal_order() {
+        // Q6 has no label, so it is named by its Q-ID; "Anon" has no ordinal
+        let c = Citation::from_item(&article(), &labels());
+        assert_eq!(c.authors, vec!["John Smith", "Jane Doe", "Q6", "Anon"]);
+    }
+
+    #[test]
+    fn book_without_journal() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(string_statement("P212", "978-0-306-40615-7"));
+        let c = Citation::from_item(&item, &HashMap::new());
+        assert_eq!(c.citation_type, CitationType::Book);
+        assert_eq!(c.isbn.as_deref(), Some("978-0-306-40615-7"));
+        assert!(c.title.is_none());
+    }
+
+    #[test]
+    fn csl_json() {
+        let j = citation().to_csl_json();
+        assert_eq!(j["id"], "Q123");
+        assert_eq!(j["type"], "article-journal");
+        assert_eq!(j["container-title"], "Journal of Things");
+        assert_eq!(j["page"], "100-110");
+        assert_eq!(j["DOI"], "10.1000/XYZ");
+        assert_eq!(j["issued"], json!({"date-parts": [[2020, 3]]}));
+        assert_eq!(j["author"][0], json!({"family": "Smith", "given": "John"}));
+        assert_eq!(j["author"][2], json!({"family": "Q6"}));
+        assert!(j.get("PMID").is_none());
+    }
+
+    #[test]
+    fn bibtex() {
+        let b = citation().to_bibtex();
+        assert!(b.starts_with("@article{Q123,\n"));
+        assert!(b.contains("  author = {Smith, John and Doe, Jane and Q6 and Anon},\n"));
+        assert!(b.contains("  title = {{A \\{study\\} of 5\\% of things}},\n"));
+        assert!(b.contains("  journal = {Journal of Things},\n"));
+        assert!(b.contains("  year = {2020},\n  month = {3},\n"));
+        assert!(b.contains("  pages = {100--110},\n"));
+        assert!(b.contains("  doi = {10.1000/XYZ},\n"));
+        assert!(b.contains("  url = {https://www.wikidata.org/wiki/Q123},\n"));
+        assert!(b.ends_with("}\n"));
+    }
+
+    #[test]
+    fn bibtex_identifiers_are_verbatim() {
+        let mut c = citation();
+        c.doi = Some("10.1002/(SICI)1097-4636_3.0.CO;2-#".to_string());
+        let b = c.to_bibtex();
+        assert!(b.contains("  doi = {10.1002/(SICI)1097-4636_3.0.CO;2-#},\n"));
+    }
+
+    #[test]
+    fn render_bibtex_separates_entries() {
+        let c = citation();
+        let rendered = CitationFormat::BibTeX.render(&[c.clone(), c]);
+        assert_eq!(rendered.matches("@article{Q123,").count(), 2);
+        assert!(rendered.contains("}\n\n@article"));
+    }
+
+    #[test]
+    fn render_csl_json_is_an_array() {
+        let c = citation();
+        let rendered = CitationFormat::CslJson.render(&[c]);
+        let j: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(j.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn split_name_forms() {
+        assert_eq!(split_name("Doe, Jane"), ("Doe", Some("Jane")));
+        assert_eq!(split_name("Jane Q. Doe"), ("Doe", Some("Jane Q.")));
+        assert_eq!(split_name("Plato"), ("Plato", None));
+    }
+
+    #[test]
+    fn items_from_sparql_result_uses_item_column() {
+        let result = json!({
+            "head": {"vars": ["title", "work"]},
+            "results": {"bindings": [
+                {"title": {"type": "literal", "value": "A"},
+                 "work": {"type": "uri", "value": "http://www.wikidata.org/entity/Q2"}},
+                {"title": {"type": "literal", "value": "B"},
+                 "work": {"type": "uri", "value": "http://www.wikidata.org/entity/Q1"}},
+                {"title": {"type": "literal", "value": "A again"},
+                 "work": {"type": "uri", "value": "http://www.wikidata.org/entity/Q2"}},
+            ]},
+        });
+        assert_eq!(items_from_sparql_result(&result), vec!["Q2", "Q1"]);
+        assert!(items_from_sparql_result(&json!({})).is_empty());
+    }
+}
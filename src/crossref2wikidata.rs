@@ -1,12 +1,12 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
-use chrono::prelude::*;
 use crossref::{response::work::PartialDate, Crossref};
 
 use self::identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp};
 use crate::{
-    adapter_helpers::{get_external_identifier_from_item, wb_time_from_partial},
+    adapter_helpers::{get_external_identifier_from_item, titles_are_equal, UNDETERMINED_LANGUAGE},
+    journal_info::JournalInfo,
     scientific_publication_adapter::{crossref_work_type_to_q, ScientificPublicationAdapter},
     *,
 };
@@ -80,12 +80,35 @@ fn parse_crossref_date(issued: &PartialDate) -> Option<(u32, Option<u8>, Option<
     Some((year, month, day))
 }
 
+/// Crossref titles carry no language. `title` is usually English (or an
+/// English translation when there is an `original-title`); the original
+/// title is kept with an undetermined language, so it becomes P1476 but
+/// not a label.
+fn crossref_titles(title: &[String], original_title: &[String]) -> Vec<LocaleString> {
+    let mut ret: Vec<LocaleString> = title.iter().map(|t| LocaleString::new("en", t)).collect();
+    for original in original_title {
+        if !title.iter().any(|t| titles_are_equal(t, original)) {
+            ret.push(LocaleString::new(UNDETERMINED_LANGUAGE, original));
+        }
+    }
+    ret
+}
+
 #[async_trait(?Send)]
 impl ScientificPublicationAdapter for Crossref2Wikidata {
     fn name(&self) -> &str {
         "Crossref2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q5188229") // Crossref
+    }
+
+    /// Works are looked up by DOI
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        Some((IdProp::DOI.as_str().to_string(), publication_id.to_string()))
+    }
+
     fn get_work_type(&self, publication_id: &str) -> Option<String> {
         let work = self.get_cached_publication_from_id(publication_id)?;
         crossref_work_type_to_q(&work.type_).map(|s| s.to_string())
@@ -98,6 +121,21 @@ impl ScientificPublicationAdapter for Crossref2Wikidata {
             .and_then(|a| a.first().cloned())
     }
 
+    fn get_work_issns(&self, publication_id: &str) -> Vec<String> {
+        self.get_cached_publication_from_id(publication_id)
+            .and_then(|work| work.issn.clone())
+            .unwrap_or_default()
+    }
+
+    fn get_journal_info(&self, publication_id: &str) -> Option<JournalInfo> {
+        let work = self.get_cached_publication_from_id(publication_id)?;
+        Some(JournalInfo {
+            title: work.container_title.as_ref().and_then(|titles| titles.first().cloned()),
+            issns: work.issn.clone().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -167,14 +205,11 @@ impl ScientificPublicationAdapter for Crossref2Wikidata {
         Some(publication_id)
     }
 
-    fn reference(&self) -> Vec<Reference> {
-        let now = Utc::now().format("+%Y-%m-%dT00:00:00Z").to_string();
-        vec![Reference::new(vec![Snak::new_time("P813", &now, 11)])]
-    }
-
     fn get_work_titles(&self, publication_id: &str) -> Vec<LocaleString> {
         match self.get_cached_publication_from_id(publication_id) {
-            Some(work) => work.title.iter().map(|t| LocaleString::new("en", t)).collect(),
+            Some(work) => {
+                crossref_titles(&work.title, work.original_title.as_deref().unwrap_or_default())
+            },
             None => vec![],
         }
     }
@@ -184,47 +219,19 @@ impl ScientificPublicationAdapter for Crossref2Wikidata {
         parse_crossref_date(&work.issued)
     }
 
-    async fn update_statements_for_publication_id(&self, publication_id: &str, item: &mut Entity) {
-        let work = match self.get_cached_publication_from_id(publication_id) {
-            Some(w) => w,
-            None => return,
-        };
-
-        // Date
-        if !item.has_claims_with_property("P577") {
-            if let Some((year, month, day)) = self.get_publication_date(publication_id) {
-                let statement = wb_time_from_partial("P577", year, month, day, self.reference());
-                item.add_claim(statement);
-            }
-        }
-
-        // Issue/volume/page
-        let string_options =
-            vec![("P433", &work.issue), ("P478", &work.volume), ("P304", &work.page)];
-        for option in string_options {
-            if !item.has_claims_with_property(option.0) {
-                if let Some(v) = option.1 {
-                    if self.should_add_string(v) {
-                        item.add_claim(Statement::new_normal(
-                            Snak::new_string(option.0, v),
-                            vec![],
-                            self.reference(),
-                        ));
-                    }
-                }
-            }
-        }
+    fn get_volume(&self, publication_id: &str) -> Option<String> {
+        let volume = self.get_cached_publication_from_id(publication_id)?.volume.as_ref()?;
+        self.should_add_string(volume).then(|| volume.to_owned())
+    }
 
-        if let Some(subjects) = &work.subject {
-            for _subject in subjects {
-                // println!("Subject:{}", &subject);
-                // TODO
-            }
-        }
+    fn get_issue(&self, publication_id: &str) -> Option<String> {
+        let issue = self.get_cached_publication_from_id(publication_id)?.issue.as_ref()?;
+        self.should_add_string(issue).then(|| issue.to_owned())
+    }
 
-        // TODO journal (already done via ISSN?)
-        // TODO ISBN
-        // TODO authors
+    fn get_pages(&self, publication_id: &str) -> Option<String> {
+        let page = self.get_cached_publication_from_id(publication_id)?.page.as_ref()?;
+        self.should_add_string(page).then(|| page.to_owned())
     }
 }
 
@@ -323,6 +330,26 @@ mod tests {
         assert_eq!(crossref_work_type_to_q("unknown-type"), None);
     }
 
+    // === crossref_titles ===
+
+    #[test]
+    fn crossref_titles_keeps_original_title_without_language() {
+        let titles = crossref_titles(
+            &["Treatment of migraine".to_string()],
+            &["Migränebehandlung".to_string()],
+        );
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[0].language(), "en");
+        assert_eq!(titles[1].value(), "Migränebehandlung");
+        assert_eq!(titles[1].language(), UNDETERMINED_LANGUAGE);
+    }
+
+    #[test]
+    fn crossref_titles_skips_original_title_equal_to_title() {
+        let titles = crossref_titles(&["A title".to_string()], &["A title.".to_string()]);
+        assert_eq!(titles.len(), 1);
+    }
+
     // === should_add_string ===
 
     #[test]
@@ -5,7 +5,10 @@ use async_trait::async_trait;
 
 use self::identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp};
 use crate::{
-    adapter_helpers::{fetch_doi_json, get_external_identifier_from_item},
+    adapter_helpers::{
+        fetch_doi_json, get_external_identifier_from_item, wikidata_language_code,
+        UNDETERMINED_LANGUAGE,
+    },
     generic_author_info::GenericAuthorInfo,
     http_client::{HttpJsonFetcher, JsonFetcher},
     scientific_publication_adapter::ScientificPublicationAdapter,
@@ -77,6 +80,15 @@ impl ScientificPublicationAdapter for DataCite2Wikidata {
         "DataCite2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q821542") // DataCite
+    }
+
+    /// Works are looked up by DOI
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        Some((IdProp::DOI.as_str().to_string(), publication_id.to_string()))
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -127,17 +139,29 @@ impl ScientificPublicationAdapter for DataCite2Wikidata {
             Some(a) => a,
             None => return vec![],
         };
-        match attrs["titles"].as_array() {
-            Some(titles) if !titles.is_empty() => {
-                if let Some(title) = titles[0]["title"].as_str() {
-                    if !title.is_empty() {
-                        return vec![LocaleString::new("en", title)];
-                    }
-                }
-                vec![]
-            },
-            _ => vec![],
+        // Main and translated titles, in the language given by `lang`. An
+        // untagged main title is assumed to be English, as before DataCite
+        // had `lang`; other untagged titles get an undetermined language.
+        let mut ret: Vec<LocaleString> = vec![];
+        for title in attrs["titles"].as_array().into_iter().flatten() {
+            let value = match title["title"].as_str().map(str::trim) {
+                Some(value) if !value.is_empty() => value,
+                _ => continue,
+            };
+            let is_main = match title["titleType"].as_str() {
+                None => true,
+                Some("TranslatedTitle") => false,
+                Some(_) => continue,
+            };
+            let language = title["lang"].as_str().and_then(wikidata_language_code);
+            let language = match language {
+                Some(language) => language,
+                None if is_main && ret.is_empty() => "en".to_string(),
+                None => UNDETERMINED_LANGUAGE.to_string(),
+            };
+            ret.push(LocaleString::new(language, value));
         }
+        ret
     }
 
     fn get_work_type(&self, publication_id: &str) -> Option<String> {
@@ -211,14 +235,6 @@ impl ScientificPublicationAdapter for DataCite2Wikidata {
             })
             .collect()
     }
-
-    async fn update_statements_for_publication_id(
-        &self,
-        _publication_id: &str,
-        _item: &mut Entity,
-    ) {
-        // No extra statements beyond the defaults
-    }
 }
 
 #[cfg(test)]
@@ -298,6 +314,29 @@ mod tests {
         assert!(adapter.get_work_titles("10.5281/ZENODO.1234567").is_empty());
     }
 
+    #[test]
+    fn test_get_work_titles_multilingual() {
+        let mut adapter = DataCite2Wikidata::default();
+        let mut work = make_datacite_work();
+        work["data"]["attributes"]["titles"] = json!([
+            {"title": "Messdaten zur Bodenfeuchte", "lang": "de"},
+            {"title": "Soil moisture measurements", "lang": "en-GB", "titleType": "TranslatedTitle"},
+            {"title": "Version 2", "titleType": "Subtitle"},
+            {"title": "Données", "titleType": "TranslatedTitle"}
+        ]);
+        adapter.work_cache.insert("10.5281/ZENODO.1234567".to_string(), work);
+        let titles = adapter.get_work_titles("10.5281/ZENODO.1234567");
+        let titles: Vec<(&str, &str)> = titles.iter().map(|t| (t.language(), t.value())).collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("de", "Messdaten zur Bodenfeuchte"),
+                ("en", "Soil moisture measurements"),
+                (UNDETERMINED_LANGUAGE, "Données"),
+            ]
+        );
+    }
+
     #[test]
     fn test_get_work_type_dataset() {
         let mut adapter = DataCite2Wikidata::default();
@@ -0,0 +1,323 @@
+//! Where the adapters disagree on a work's core metadata, and where the item
+//! contradicts all of them.
+//!
+//! [`PublicationRecord::apply_to_item`] writes the value that wins by
+//! [`Precedence`]; a [`DiscrepancyReport`] lists what it chose over, so
+//! curators can check it. Titles are left out, since sources differ in
+//! capitalisation and punctuation far more often than in substance.
+
+use std::fmt;
+
+use crate::{
+    publication_record::{Precedence, PublicationRecord, RecordField},
+    *,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscrepancyKind {
+    /// Sources report values that can't all be right
+    SourcesDisagree,
+    /// The item has a value that no source reports
+    ItemContradictsSources,
+}
+
+impl DiscrepancyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiscrepancyKind::SourcesDisagree => "sources_disagree",
+            DiscrepancyKind::ItemContradictsSources => "item_contradicts_sources",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub kind: DiscrepancyKind,
+    pub field: RecordField,
+    /// Value per source, best first by precedence
+    pub values: Vec<(String, String)>,
+    /// The value that won by precedence
+    pub chosen: String,
+    /// Values on the item before the update
+    pub existing: Vec<String>,
+}
+
+impl Discrepancy {
+    fn to_json(&self) -> serde_json::Value {
+        let values: Vec<serde_json::Value> = self
+            .values
+            .iter()
+            .map(|(source, value)| json!({"source": source, "value": value}))
+            .collect();
+        json!({
+            "kind": self.kind.as_str(),
+            "field": self.field.as_str(),
+            "property": self.field.property(),
+            "values": values,
+            "chosen": self.chosen,
+            "existing": self.existing,
+        })
+    }
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.field.property() {
+            Some(property) => format!("{} ({property})", self.field.as_str()),
+            None => self.field.as_str().to_string(),
+        };
+        let values: Vec<String> =
+            self.values.iter().map(|(source, value)| format!("{source}={value}")).collect();
+        match self.kind {
+            DiscrepancyKind::SourcesDisagree => {
+                write!(f, "{field}: {}, chose {}", values.join(", "), self.chosen)
+            },
+            DiscrepancyKind::ItemContradictsSources => write!(
+                f,
+                "{field}: item has {}, sources say {}",
+                self.existing.join(" / "),
+                values.join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiscrepancyReport {
+    discrepancies: Vec<Discrepancy>,
+}
+
+impl DiscrepancyReport {
+    /// Compares the candidate values in `record` with each other and with
+    /// `item`, which should be the item before the record is applied.
+    pub fn new(record: &PublicationRecord, item: &Entity, precedence: &Precedence) -> Self {
+        let mut discrepancies = vec![];
+        for (field, candidates) in record.single_valued_fields() {
+            let values: Vec<(String, String)> = precedence
+                .sorted(field, &candidates)
+                .into_iter()
+                .map(|c| (c.source.to_owned(), c.value.to_owned()))
+                .collect();
+            let chosen = match values.first() {
+                Some((_, chosen)) => chosen.to_owned(),
+                None => continue,
+            };
+            let existing = field.property().map(|p| item_values(item, p)).unwrap_or_default();
+            let disagree = values
+                .iter()
+                .enumerate()
+                .any(|(i, (_, a))| values[i + 1..].iter().any(|(_, b)| !compatible(field, a, b)));
+            let contradicts = !existing.is_empty()
+                && existing.iter().all(|e| values.iter().all(|(_, v)| !compatible(field, e, v)));
+            let kinds = [
+                (disagree, DiscrepancyKind::SourcesDisagree),
+                (contradicts, DiscrepancyKind::ItemContradictsSources),
+            ];
+            for (_, kind) in kinds.into_iter().filter(|(found, _)| *found) {
+                discrepancies.push(Discrepancy {
+                    kind,
+                    field,
+                    values: values.clone(),
+                    chosen: chosen.clone(),
+                    existing: existing.clone(),
+                });
+            }
+        }
+        Self { discrepancies }
+    }
+
+    pub fn discrepancies(&self) -> &[Discrepancy] {
+        &self.discrepancies
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!(self.discrepancies.iter().map(|d| d.to_json()).collect::<Vec<_>>())
+    }
+}
+
+impl fmt::Display for DiscrepancyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.discrepancies.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// Two values are compatible if they are equal; dates also if one is just
+/// less precise than the other.
+pub(crate) fn compatible(field: RecordField, a: &str, b: &str) -> bool {
+    match field {
+        RecordField::PublicationDate => a.starts_with(b) || b.starts_with(a),
+        _ => a.trim() == b.trim(),
+    }
+}
+
+/// Values of `property` on `item`, rendered like the record values.
+pub(crate) fn item_values(item: &Entity, property: &str) -> Vec<String> {
+    item.claims()
+        .iter()
+        .filter(|statement| statement.property() == property)
+        .filter_map(|statement| match statement.main_snak().data_value().as_ref()?.value() {
+            Value::StringValue(s) => Some(s.to_string()),
+            Value::Entity(entity) => Some(entity.id().to_string()),
+            Value::Time(time) => time_to_date(time.time(), time.precision()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `+2020-03-05T00:00:00Z` at month precision → `2020-03`. `None` for
+/// precisions coarser than a year.
+pub(crate) fn time_to_date(time: &str, precision: u64) -> Option<String> {
+    let date = time.trim_start_matches('+').split('T').next()?;
+    let parts: Vec<&str> = date.split('-').collect();
+    let count = match precision {
+        9 => 1,
+        10 => 2,
+        p if p >= 11 => 3,
+        _ => return None,
+    };
+    Some(parts[..count.min(parts.len())].join("-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{adapter_helpers::wb_time_from_partial, publication_record::Sourced};
+
+    fn sourced<T>(value: T, source: &str) -> Sourced<T> {
+        Sourced::new(value, source, vec![])
+    }
+
+    fn item_with(statements: Vec<Statement>) -> Entity {
+        let mut item = Entity::new_empty_item();
+        statements.into_iter().for_each(|s| item.add_claim(s));
+        item
+    }
+
+    fn string_statement(property: &str, value: &str) -> Statement {
+        Statement::new_normal(Snak::new_string(property, value), vec![], vec![])
+    }
+
+    #[test]
+    fn sources_disagree_on_volume() {
+        let record = PublicationRecord {
+            volumes: vec![
+                sourced("12".to_string(), "Pubmed2Wikidata"),
+                sourced("13".to_string(), "Crossref2Wikidata"),
+            ],
+            ..Default::default()
+        };
+        let report = DiscrepancyReport::new(&record, &item_with(vec![]), &Precedence::default());
+        assert_eq!(report.discrepancies().len(), 1);
+        let d = &report.discrepancies()[0];
+        assert_eq!(d.kind, DiscrepancyKind::SourcesDisagree);
+        assert_eq!(d.field, RecordField::Volume);
+        assert_eq!(d.chosen, "12");
+        assert_eq!(
+            report.to_string(),
+            "volume (P478): Pubmed2Wikidata=12, Crossref2Wikidata=13, chose 12"
+        );
+    }
+
+    #[test]
+    fn agreeing_sources_are_not_reported() {
+        let record = PublicationRecord {
+            volumes: vec![sourced("12".to_string(), "a"), sourced("12".to_string(), "b")],
+            issues: vec![sourced("3".to_string(), "a")],
+            ..Default::default()
+        };
+        let item = item_with(vec![string_statement("P478", "12")]);
+        assert!(DiscrepancyReport::new(&record, &item, &Precedence::default()).is_empty());
+    }
+
+    #[test]
+    fn less_precise_dates_are_compatible() {
+        let record = PublicationRecord {
+            publication_dates: vec![
+                sourced((2020, Some(3), None), "a"),
+                sourced((2020, Some(3), Some(5)), "b"),
+                sourced((2020, None, None), "c"),
+            ],
+            ..Default::default()
+        };
+        let item = item_with(vec![wb_time_from_partial("P577", 2020, None, None, vec![])]);
+        assert!(DiscrepancyReport::new(&record, &item, &Precedence::default()).is_empty());
+    }
+
+    #[test]
+    fn dates_a_year_apart_disagree() {
+        let record = PublicationRecord {
+            publication_dates: vec![
+                sourced((2020, Some(3), None), "a"),
+                sourced((2019, Some(12), Some(1)), "b"),
+            ],
+            ..Default::default()
+        };
+        let report = DiscrepancyReport::new(&record, &item_with(vec![]), &Precedence::default());
+        assert_eq!(report.discrepancies().len(), 1);
+        assert_eq!(report.discrepancies()[0].values[1].1, "2019-12-01");
+    }
+
+    #[test]
+    fn item_contradicting_every_source_is_flagged() {
+        let record = PublicationRecord {
+            volumes: vec![sourced("12".to_string(), "a"), sourced("12".to_string(), "b")],
+            publication_dates: vec![sourced((2020, Some(3), Some(5)), "a")],
+            ..Default::default()
+        };
+        let item = item_with(vec![
+            string_statement("P478", "21"),
+            wb_time_from_partial("P577", 2002, None, None, vec![]),
+        ]);
+        let report = DiscrepancyReport::new(&record, &item, &Precedence::default());
+        let kinds: Vec<(RecordField, DiscrepancyKind)> =
+            report.discrepancies().iter().map(|d| (d.field, d.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (RecordField::PublicationDate, DiscrepancyKind::ItemContradictsSources),
+                (RecordField::Volume, DiscrepancyKind::ItemContradictsSources),
+            ]
+        );
+        assert_eq!(report.discrepancies()[0].existing, vec!["2002"]);
+    }
+
+    #[test]
+    fn item_matching_one_source_is_not_flagged() {
+        let record = PublicationRecord {
+            volumes: vec![sourced("12".to_string(), "a"), sourced("13".to_string(), "b")],
+            ..Default::default()
+        };
+        let item = item_with(vec![string_statement("P478", "13")]);
+        let report = DiscrepancyReport::new(&record, &item, &Precedence::default());
+        assert_eq!(report.discrepancies().len(), 1);
+        assert_eq!(report.discrepancies()[0].kind, DiscrepancyKind::SourcesDisagree);
+    }
+
+    #[test]
+    fn to_json_lists_values_per_source() {
+        let record = PublicationRecord {
+            languages: vec![sourced("Q1860".to_string(), "a"), sourced("Q188".to_string(), "b")],
+            ..Default::default()
+        };
+        let report = DiscrepancyReport::new(&record, &item_with(vec![]), &Precedence::default());
+        let j = report.to_json();
+        assert_eq!(j[0]["kind"], "sources_disagree");
+        assert_eq!(j[0]["property"], "P407");
+        assert_eq!(j[0]["values"][1]["source"], "b");
+        assert_eq!(j[0]["values"][1]["value"], "Q188");
+        assert_eq!(j[0]["chosen"], "Q1860");
+    }
+
+    #[test]
+    fn time_to_date_respects_precision() {
+        assert_eq!(time_to_date("+2020-03-05T00:00:00Z", 11).as_deref(), Some("2020-03-05"));
+        assert_eq!(time_to_date("+2020-03-01T00:00:00Z", 10).as_deref(), Some("2020-03"));
+        assert_eq!(time_to_date("+2020-01-01T00:00:00Z", 9).as_deref(), Some("2020"));
+        assert_eq!(time_to_date("+2000-01-01T00:00:00Z", 7), None);
+    }
+}
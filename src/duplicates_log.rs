@@ -0,0 +1,73 @@
+//! A log of identifiers that were found on more than one item.
+//!
+//! `WikidataStringCache` refuses to pick one of several items for a DOI,
+//! ORCID etc., so those identifiers are left alone. The log lists them, one
+//! JSON object per line (`{"property", "key", "items"}`), so the items can be
+//! merged or fixed by hand.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::append_writer::AppendWriter;
+
+/// Appends ambiguous identifiers to a log file (or any writer), each one
+/// once, shared between all clones.
+#[derive(Debug, Clone)]
+pub struct DuplicatesLog {
+    out: AppendWriter,
+    logged: Arc<Mutex<HashSet<(String, String)>>>,
+}
+
+impl DuplicatesLog {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self::with_writer(AppendWriter::new(out))
+    }
+
+    /// Appends to the log at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::with_writer(AppendWriter::open(path)?))
+    }
+
+    fn with_writer(out: AppendWriter) -> Self {
+        Self { out, logged: Arc::new(Mutex::new(HashSet::new())) }
+    }
+
+    /// Writes the items for `property`/`key`, unless that key was logged
+    /// before.
+    pub fn write(&self, property: &str, key: &str, items: &[String]) -> Result<()> {
+        let entry = (property.to_string(), key.to_string());
+        let mut logged = self.logged.lock().map_err(|_| anyhow!("duplicates log poisoned"))?;
+        if !logged.insert(entry) {
+            return Ok(());
+        }
+        let line = json!({"property": property, "key": key, "items": items});
+        self.out.write_lines(&[line.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::SharedBuffer;
+
+    #[test]
+    fn keys_are_logged_once() {
+        let buffer = SharedBuffer::default();
+        let log = DuplicatesLog::new(buffer.clone());
+        let items = vec!["Q1".to_string(), "Q2".to_string()];
+        log.write("P356", "10.1/x", &items).unwrap();
+        log.clone().write("P356", "10.1/x", &items).unwrap();
+        log.write("P496", "10.1/x", &items).unwrap();
+        let text = buffer.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let j: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(j, json!({"property": "P356", "key": "10.1/x", "items": ["Q1", "Q2"]}));
+    }
+}
@@ -0,0 +1,109 @@
+//! Where edits go: written to Wikidata, or only reported (as JSON, or as
+//! QuickStatements for a curator to run).
+//!
+//! Every write goes through [`WikidataPapers::apply_diff_for_item`],
+//! [`GenericAuthorInfo::update_author_item`] or
+//! [`WikidataInteraction::create_item`], and each of them checks the
+//! [`EditOutput`] before touching the write API.
+//!
+//! [`WikidataPapers::apply_diff_for_item`]: crate::wikidata_papers::WikidataPapers::apply_diff_for_item
+//! [`GenericAuthorInfo::update_author_item`]: crate::generic_author_info::GenericAuthorInfo::update_author_item
+//! [`WikidataInteraction::create_item`]: crate::wikidata_interaction::WikidataInteraction::create_item
+
+use wikibase::entity_diff::EntityDiff;
+
+use crate::{quickstatements::QuickStatementsWriter, *};
+
+#[derive(Debug, Clone, Default)]
+pub enum EditOutput {
+    /// Edits are written to Wikidata
+    #[default]
+    Live,
+    /// Edits are printed to stdout as one JSON object per line, and nothing
+    /// is written. Each has `"type": "edit"`, to tell it apart from the
+    /// results of `--output jsonl` on the same stream.
+    DryRun,
+    /// Edits are appended to a QuickStatements V1 file, and nothing is
+    /// written. New items can't be referenced before QuickStatements creates
+    /// them, so new authors stay author name strings.
+    QuickStatements(QuickStatementsWriter),
+}
+
+impl EditOutput {
+    pub fn is_live(&self) -> bool {
+        matches!(self, EditOutput::Live)
+    }
+
+    /// Reports an edit that was not written. `original` is the item that
+    /// would have been edited, without an ID for a new item.
+    pub fn report(&self, original: &Entity, summary: Option<&str>, diff: &EntityDiff) {
+        match self {
+            EditOutput::Live => {},
+            EditOutput::DryRun => {
+                let id = original.id();
+                let entity = (!id.is_empty()).then_some(id.as_str());
+                println!("{}", dry_run_json(entity, summary, diff))
+            },
+            EditOutput::QuickStatements(writer) => {
+                if let Err(error) = writer.write(original, summary, diff.actions()) {
+                    tracing::error!(%error, "could not write QuickStatements");
+                }
+            },
+        }
+    }
+}
+
+fn dry_run_json(
+    entity: Option<&str>,
+    summary: Option<&str>,
+    diff: &EntityDiff,
+) -> serde_json::Value {
+    json!({
+        "type": "edit",
+        "action": if entity.is_some() { "edit" } else { "create" },
+        "entity": entity,
+        "summary": summary,
+        "data": diff.actions(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wikibase::entity_diff::EntityDiffParams;
+
+    use super::*;
+
+    fn diff_adding_title() -> EntityDiff {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(
+            Snak::new_monolingual_text("P1476", "A title", "en"),
+            vec![],
+            vec![],
+        ));
+        EntityDiff::new(&Entity::new_empty_item(), &item, &EntityDiffParams::all())
+    }
+
+    #[test]
+    fn default_is_live() {
+        assert!(EditOutput::default().is_live());
+        assert!(!EditOutput::DryRun.is_live());
+    }
+
+    #[test]
+    fn dry_run_json_for_new_item() {
+        let j = dry_run_json(None, Some("summary"), &diff_adding_title());
+        assert_eq!(j["type"], "edit");
+        assert_eq!(j["action"], "create");
+        assert!(j["entity"].is_null());
+        assert_eq!(j["summary"], "summary");
+        assert!(j["data"]["claims"].to_string().contains("A title"));
+    }
+
+    #[test]
+    fn dry_run_json_for_existing_item() {
+        let j = dry_run_json(Some("Q42"), None, &diff_adding_title());
+        assert_eq!(j["action"], "edit");
+        assert_eq!(j["entity"], "Q42");
+        assert!(j["summary"].is_null());
+    }
+}
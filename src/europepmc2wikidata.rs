@@ -71,6 +71,15 @@ impl ScientificPublicationAdapter for EuropePMC2Wikidata {
         "EuropePMC2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q5412157") // Europe PMC
+    }
+
+    /// Works are looked up by DOI
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        Some((IdProp::DOI.as_str().to_string(), publication_id.to_string()))
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -188,6 +197,19 @@ impl ScientificPublicationAdapter for EuropePMC2Wikidata {
             .map(|s| s.to_string())
     }
 
+    /// Print ISSN first, then the electronic one (`essn`)
+    fn get_work_issns(&self, publication_id: &str) -> Vec<String> {
+        let journal = match self.get_cached_publication_from_id(publication_id) {
+            Some(work) => &work["journalInfo"]["journal"],
+            None => return vec![],
+        };
+        ["issn", "essn"]
+            .iter()
+            .filter_map(|key| journal[*key].as_str())
+            .map(|s| s.to_string())
+            .collect()
+    }
+
     async fn get_author_list(&mut self, publication_id: &str) -> Vec<GenericAuthorInfo> {
         let work = match self.get_cached_publication_from_id(publication_id) {
             Some(w) => w.clone(),
@@ -203,14 +225,6 @@ impl ScientificPublicationAdapter for EuropePMC2Wikidata {
             None => vec![],
         }
     }
-
-    async fn update_statements_for_publication_id(
-        &self,
-        _publication_id: &str,
-        _item: &mut Entity,
-    ) {
-        // No extra statements beyond the defaults
-    }
 }
 
 #[cfg(test)]
@@ -304,6 +318,15 @@ mod tests {
         assert_eq!(adapter.get_work_issn("10.1234/TEST"), Some("1234-5678".to_string()));
     }
 
+    #[test]
+    fn test_get_work_issns_includes_essn() {
+        let mut adapter = EuropePMC2Wikidata::default();
+        let mut work = make_epmc_work();
+        work["journalInfo"]["journal"]["essn"] = json!("8765-4321");
+        adapter.work_cache.insert("10.1234/TEST".to_string(), work);
+        assert_eq!(adapter.get_work_issns("10.1234/TEST"), vec!["1234-5678", "8765-4321"]);
+    }
+
     #[tokio::test]
     async fn test_get_author_list() {
         let mut adapter = EuropePMC2Wikidata::default();
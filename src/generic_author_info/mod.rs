@@ -7,6 +7,7 @@ use tokio::sync::RwLock;
 use wikibase::mediawiki::api::Api;
 
 use crate::{
+    edit_output::EditOutput, key_normalization::normalize_value,
     wikidata_interaction::WikidataInteraction, wikidata_string_cache::WikidataStringCache, *,
 };
 
@@ -253,25 +254,39 @@ impl GenericAuthorInfo {
 
         // External IDs
         for (prop, id) in &self.prop2id {
-            let existing = item.values_for_property(prop);
-            let to_check = Value::StringValue(id.to_string());
-            if existing.contains(&to_check) {
+            if item_has_id(item, prop, id) {
                 continue;
             }
-            let statement = Statement::new_normal(
-                Snak::new_external_id(prop.to_string(), id.to_string()),
-                vec![],
-                vec![],
-            );
+            let id = normalize_value(prop, id);
+            let statement =
+                Statement::new_normal(Snak::new_external_id(prop.to_string(), id), vec![], vec![]);
             item.add_claim(statement);
         }
     }
 
+    /// External IDs that disagree with `item`: the item already has a
+    /// value for the property, and it isn't ours. Values are compared in
+    /// canonical form, so a different spelling of the same ID agrees.
+    /// Sorted by property.
+    pub fn conflicting_ids(&self, item: &Entity) -> Vec<(String, String)> {
+        let mut ret: Vec<(String, String)> = self
+            .prop2id
+            .iter()
+            .filter(|(prop, id)| {
+                !item.values_for_property(prop).is_empty() && !item_has_id(item, prop, id)
+            })
+            .map(|(prop, id)| (prop.to_owned(), id.to_owned()))
+            .collect();
+        ret.sort();
+        ret
+    }
+
     pub async fn get_or_create_author_item(
         &self,
         mw_api: Arc<RwLock<Api>>,
         cache: Arc<WikidataStringCache>,
         allow_no_external_ids: bool,
+        output: &EditOutput,
     ) -> GenericAuthorInfo {
         let mut ret = self.clone();
         // Already has item?
@@ -283,11 +298,19 @@ impl GenericAuthorInfo {
             return ret;
         }
 
-        // Use search
+        // Use search. An ID on several items could be any of them, and
+        // creating yet another item would not help either.
         for (prop, id) in &ret.prop2id {
-            if let Some(q) = cache.get(prop, id).await {
-                ret.wikidata_item = Some(q);
-                return ret;
+            match cache.get_unique(prop, id).await {
+                Ok(Some(q)) => {
+                    ret.wikidata_item = Some(q);
+                    return ret;
+                },
+                Ok(None) => {},
+                Err(error) => {
+                    tracing::warn!(prop, id, %error, "author ID can't be used, skipping");
+                    return ret;
+                },
             }
         }
 
@@ -298,7 +321,7 @@ impl GenericAuthorInfo {
         // Create new item and use its ID. On API error we degrade to
         // `None` so the outer best-effort flow can still proceed; the
         // caller's "no item ID" branch will pick it up.
-        ret.wikidata_item = match self.create_item(&item, mw_api).await {
+        ret.wikidata_item = match self.create_item(&item, mw_api, output).await {
             Ok(opt) => opt,
             Err(e) => {
                 tracing::warn!(error = %e, "create_item failed for author");
@@ -306,7 +329,10 @@ impl GenericAuthorInfo {
             },
         };
 
-        // Update external IDs cache
+        // Update external IDs cache; a dry run has no item to remember
+        if !output.is_live() {
+            return ret;
+        }
         for (prop, id) in &ret.prop2id {
             cache.set(prop, id, ret.wikidata_item.clone()).await;
         }
@@ -593,12 +619,13 @@ impl GenericAuthorInfo {
         &self,
         entities: &mut wikibase::entity_container::EntityContainer,
         mw_api: Arc<RwLock<Api>>,
+        output: &EditOutput,
     ) {
         let q = match &self.wikidata_item {
             Some(q) => q.to_string(),
             None => return,
         };
-        let original_item = match entities.get_entity(q) {
+        let original_item = match entities.get_entity(q.to_owned()) {
             Some(i) => i.clone(),
             None => return,
         };
@@ -609,11 +636,16 @@ impl GenericAuthorInfo {
         params.labels.add = EntityDiffParamState::All;
         params.aliases.add = EntityDiffParamState::All;
         params.claims.add = EntityDiffParamState::All;
+        let summary = "(automated edit by SourceMD)";
         let mut diff = EntityDiff::new(&original_item, &item, &params);
-        diff.set_edit_summary(Some("(automated edit by SourceMD)".to_string()));
+        diff.set_edit_summary(Some(summary.to_string()));
         if diff.is_empty() {
             return;
         }
+        if !output.is_live() {
+            output.report(&original_item, Some(summary), &diff);
+            return;
+        }
 
         // Write lock for the upstream-bounded reason described in
         // wikidata_papers::apply_diff_for_item — `EntityContainer::apply_diff`
@@ -629,5 +661,15 @@ impl GenericAuthorInfo {
     }
 }
 
+/// Whether `item` has `id` for `property`, comparing canonical forms, so
+/// that e.g. an ORCID URL matches the bare ORCID on the item.
+pub fn item_has_id(item: &Entity, property: &str, id: &str) -> bool {
+    let id = normalize_value(property, id);
+    item.values_for_property(property).into_iter().any(|value| match value {
+        Value::StringValue(s) => normalize_value(property, &s) == id,
+        _ => false,
+    })
+}
+
 #[cfg(test)]
 mod tests;
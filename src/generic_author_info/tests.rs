@@ -132,6 +132,41 @@ fn amend_author_item() {
     );
 }
 
+#[test]
+fn conflicting_ids() {
+    let mut ga = GenericAuthorInfo::new();
+    ga.prop2id.insert("P496".to_string(), "1234-5678-1234-5678".to_string());
+    ga.prop2id.insert("P1053".to_string(), "ABC-1234".to_string());
+    ga.prop2id.insert("P1153".to_string(), "55555".to_string());
+    let mut item = Entity::new_empty_item();
+    item.add_claim(Statement::new_normal(
+        Snak::new_external_id("P496", "1234-5678-1234-5678"),
+        vec![],
+        vec![],
+    ));
+    item.add_claim(Statement::new_normal(
+        Snak::new_external_id("P1053", "XYZ-9999"),
+        vec![],
+        vec![],
+    ));
+    // P496 agrees, P1153 is absent from the item, P1053 disagrees
+    assert_eq!(ga.conflicting_ids(&item), vec![("P1053".to_string(), "ABC-1234".to_string())]);
+    assert!(ga.conflicting_ids(&Entity::new_empty_item()).is_empty());
+}
+
+#[test]
+fn conflicting_ids_ignore_spelling() {
+    let mut ga = GenericAuthorInfo::new();
+    ga.prop2id.insert("P496".to_string(), "https://orcid.org/0000-0002-1825-009x".to_string());
+    let mut item = Entity::new_empty_item();
+    item.add_claim(Statement::new_normal(
+        Snak::new_external_id("P496", "0000-0002-1825-009X"),
+        vec![],
+        vec![],
+    ));
+    assert!(ga.conflicting_ids(&item).is_empty());
+}
+
 #[test]
 fn find_best_match() {
     let mut ga_main = GenericAuthorInfo::new();
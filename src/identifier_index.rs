@@ -0,0 +1,289 @@
+//! A local index of identifier→item mappings, built from a Wikidata JSON
+//! dump, so that large backfills can find existing items without searching
+//! live Wikidata.
+//!
+//! The index is a text file with one `property<TAB>key<TAB>item` line per
+//! statement, sorted bytewise. Keys are in the canonical form from
+//! `key_normalization`, like the keys of `WikidataStringCache`. Lookups
+//! binary-search the file, so it is never loaded into memory.
+//!
+//! Building the index sorts the entries in chunks of `SORT_CHUNK_BYTES`,
+//! spilled to temporary files and merged, so memory use doesn't grow with
+//! the size of the dump.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use flate2::bufread::MultiGzDecoder;
+
+use crate::key_normalization::normalize_value;
+
+/// DOI, PubMed ID, PMCID, arXiv ID, ORCID iD and ISSN
+pub const INDEXED_PROPERTIES: [&str; 6] = ["P356", "P698", "P932", "P818", "P496", "P236"];
+
+/// The `(property, key, item)` entries for one line of a JSON dump, which
+/// has one entity per line. Lines that aren't an item are skipped.
+pub fn entries_from_dump_line(line: &str) -> Vec<(String, String, String)> {
+    // Parsing every entity is slow; most have none of the properties
+    if !INDEXED_PROPERTIES.iter().any(|property| line.contains(&format!("\"{property}\""))) {
+        return vec![];
+    }
+    let line = line.trim().trim_end_matches(',');
+    let Ok(entity) = serde_json::from_str::<serde_json::Value>(line) else {
+        return vec![];
+    };
+    let Some(q) = entity["id"].as_str().filter(|id| id.starts_with('Q')) else {
+        return vec![];
+    };
+    let mut entries = vec![];
+    for property in INDEXED_PROPERTIES {
+        let statements = entity["claims"][property].as_array().into_iter().flatten();
+        for statement in statements {
+            let Some(value) = statement["mainsnak"]["datavalue"]["value"].as_str() else {
+                continue;
+            };
+            let key = normalize_value(property, value);
+            if !key.is_empty() && !key.contains(['\t', '\n', '\r']) {
+                entries.push((property.to_string(), key, q.to_string()));
+            }
+        }
+    }
+    entries
+}
+
+/// The size of the entries that are sorted in memory at a time.
+const SORT_CHUNK_BYTES: usize = 256 * 1024 * 1024;
+
+/// Reads a JSON dump, uncompressed or gzipped (`latest-all.json.gz`), and
+/// writes the index. Returns the number of entries.
+pub fn build_index(dump: impl BufRead, out: impl Write) -> Result<usize> {
+    build_index_in_chunks(dump, out, SORT_CHUNK_BYTES)
+}
+
+fn build_index_in_chunks(
+    mut dump: impl BufRead,
+    out: impl Write,
+    chunk_bytes: usize,
+) -> Result<usize> {
+    if dump.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        let dump = BufReader::new(MultiGzDecoder::new(dump));
+        return sort_entries(dump, out, chunk_bytes);
+    }
+    sort_entries(dump, out, chunk_bytes)
+}
+
+fn sort_entries(dump: impl BufRead, out: impl Write, chunk_bytes: usize) -> Result<usize> {
+    let mut chunk: Vec<String> = vec![];
+    let mut chunk_size = 0;
+    let mut spilled: Vec<File> = vec![];
+    for line in dump.lines() {
+        for (property, key, q) in entries_from_dump_line(&line?) {
+            let entry = format!("{property}\t{key}\t{q}");
+            chunk_size += entry.len();
+            chunk.push(entry);
+        }
+        if chunk_size >= chunk_bytes {
+            spilled.push(spill(&mut chunk)?);
+            chunk_size = 0;
+        }
+    }
+    chunk.sort_unstable();
+    chunk.dedup();
+    if spilled.is_empty() {
+        return write_sorted(chunk.into_iter().map(Ok), out);
+    }
+    spilled.push(spill(&mut chunk)?);
+    merge(spilled, out)
+}
+
+/// Sorts `chunk` into a temporary file, and empties it.
+fn spill(chunk: &mut Vec<String>) -> Result<File> {
+    chunk.sort_unstable();
+    chunk.dedup();
+    let mut file = BufWriter::new(tempfile::tempfile()?);
+    for line in chunk.drain(..) {
+        writeln!(file, "{line}")?;
+    }
+    let mut file = file.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Merges the sorted `files` into `out`.
+fn merge(files: Vec<File>, out: impl Write) -> Result<usize> {
+    let mut readers: Vec<_> = files.into_iter().map(|file| BufReader::new(file).lines()).collect();
+    let mut heap = BinaryHeap::new();
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some(line) = reader.next() {
+            heap.push(Reverse((line?, i)));
+        }
+    }
+    let merged = std::iter::from_fn(move || -> Option<Result<String>> {
+        let Reverse((line, i)) = heap.pop()?;
+        match readers[i].next() {
+            Some(Ok(next)) => heap.push(Reverse((next, i))),
+            Some(Err(error)) => return Some(Err(error.into())),
+            None => {},
+        }
+        Some(Ok(line))
+    });
+    write_sorted(merged, out)
+}
+
+/// Writes sorted `lines`, without duplicates, and returns how many.
+fn write_sorted(lines: impl Iterator<Item = Result<String>>, mut out: impl Write) -> Result<usize> {
+    let mut last: Option<String> = None;
+    let mut count = 0;
+    for line in lines {
+        let line = line?;
+        if last.as_ref() == Some(&line) {
+            continue;
+        }
+        writeln!(out, "{line}")?;
+        count += 1;
+        last = Some(line);
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+#[derive(Debug)]
+pub struct IdentifierIndex {
+    file: Mutex<BufReader<File>>,
+    len: u64,
+}
+
+impl IdentifierIndex {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self { file: Mutex::new(BufReader::new(file)), len })
+    }
+
+    /// The items that had `key` as a `property` value when the dump was
+    /// made, sorted.
+    pub fn lookup(&self, property: &str, key: &str) -> Result<Vec<String>> {
+        let prefix = format!("{property}\t{}\t", normalize_value(property, key));
+        let mut file = self.file.lock().map_err(|_| anyhow!("identifier index poisoned"))?;
+
+        // The first position whose next line is not before `prefix`
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match Self::line_from(&mut file, mid)? {
+                Some(line) if line.as_str() < prefix.as_str() => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+
+        let mut items = vec![];
+        let mut line = Self::line_from(&mut file, low)?;
+        while let Some(q) = line.as_deref().and_then(|line| line.strip_prefix(&prefix)) {
+            items.push(q.to_string());
+            line = Self::next_line(&mut file)?;
+        }
+        Ok(items)
+    }
+
+    /// The first complete line that starts at or after `pos`.
+    fn line_from(file: &mut BufReader<File>, pos: u64) -> Result<Option<String>> {
+        if pos == 0 {
+            file.seek(SeekFrom::Start(0))?;
+        } else {
+            file.seek(SeekFrom::Start(pos - 1))?;
+            let mut skipped = vec![];
+            file.read_until(b'\n', &mut skipped)?;
+        }
+        Self::next_line(file)
+    }
+
+    fn next_line(file: &mut BufReader<File>) -> Result<Option<String>> {
+        let mut line = String::new();
+        if file.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = r#"[
+{"type":"item","id":"Q1","claims":{"P356":[{"mainsnak":{"snaktype":"value","property":"P356","datavalue":{"value":"10.1000/XYZ","type":"string"}}}],"P698":[{"mainsnak":{"snaktype":"value","property":"P698","datavalue":{"value":"123","type":"string"}}}]}},
+{"type":"item","id":"Q2","claims":{"P31":[{"mainsnak":{"snaktype":"value","property":"P31","datavalue":{"value":{"id":"Q5"},"type":"wikibase-entityid"}}}]}},
+{"type":"item","id":"Q3","claims":{"P356":[{"mainsnak":{"snaktype":"value","property":"P356","datavalue":{"value":"10.1000/xyz","type":"string"}}},{"mainsnak":{"snaktype":"novalue","property":"P356"}}]}},
+{"type":"property","id":"P356","claims":{"P356":[{"mainsnak":{"snaktype":"value","property":"P356","datavalue":{"value":"10.1000/prop","type":"string"}}}]}},
+{"type":"item","id":"Q4","claims":{"P496":[{"mainsnak":{"snaktype":"value","property":"P496","datavalue":{"value":"0000-0002-1825-0097","type":"string"}}}]}}
+]"#;
+
+    fn built_index() -> (tempfile::TempDir, IdentifierIndex) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.index");
+        let count = build_index(DUMP.as_bytes(), File::create(&path).unwrap()).unwrap();
+        assert_eq!(count, 4);
+        let index = IdentifierIndex::open(&path).unwrap();
+        (dir, index)
+    }
+
+    fn index_text(dump: &[u8], chunk_bytes: usize) -> String {
+        let mut out = vec![];
+        build_index_in_chunks(dump, &mut out, chunk_bytes).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn chunks_are_merged_in_order() {
+        let in_memory = index_text(DUMP.as_bytes(), SORT_CHUNK_BYTES);
+        assert_eq!(in_memory.lines().count(), 4);
+        // One entry per chunk, so every line is spilled and merged
+        assert_eq!(index_text(DUMP.as_bytes(), 1), in_memory);
+    }
+
+    #[test]
+    fn gzipped_dump() {
+        use flate2::{write::GzEncoder, Compression};
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(DUMP.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(index_text(&gzipped, 1), index_text(DUMP.as_bytes(), SORT_CHUNK_BYTES));
+    }
+
+    #[test]
+    fn entries_skip_non_items_and_novalue() {
+        let lines: Vec<&str> = DUMP.lines().collect();
+        assert_eq!(entries_from_dump_line(lines[0]), vec![]);
+        assert_eq!(entries_from_dump_line(lines[2]), vec![]);
+        assert_eq!(
+            entries_from_dump_line(lines[3]),
+            vec![("P356".to_string(), "10.1000/XYZ".to_string(), "Q3".to_string())]
+        );
+        assert_eq!(entries_from_dump_line(lines[4]), vec![]);
+    }
+
+    #[test]
+    fn lookup_finds_all_items() {
+        let (_dir, index) = built_index();
+        assert_eq!(index.lookup("P356", "10.1000/XYZ").unwrap(), vec!["Q1", "Q3"]);
+        assert_eq!(index.lookup("P698", "123").unwrap(), vec!["Q1"]);
+        assert_eq!(index.lookup("P496", "0000-0002-1825-0097").unwrap(), vec!["Q4"]);
+    }
+
+    #[test]
+    fn lookup_misses() {
+        let (_dir, index) = built_index();
+        assert!(index.lookup("P356", "10.1000/xy").unwrap().is_empty());
+        assert!(index.lookup("P698", "12").unwrap().is_empty());
+        assert!(index.lookup("P932", "123").unwrap().is_empty());
+        assert!(index.lookup("P1", "x").unwrap().is_empty());
+        assert!(index.lookup("P999", "x").unwrap().is_empty());
+    }
+}
@@ -2,11 +2,15 @@ use std::str::FromStr;
 
 use regex::Regex;
 
+use crate::{isbn::Isbn, key_normalization::normalize_value};
+
 const PROP_PMID: &str = "P698";
 const PROP_PMCID: &str = "P932";
 const PROP_DOI: &str = "P356";
 const PROP_ARXIV: &str = "P818";
 const PROP_SEMANTIC_SCHOLAR: &str = "P4011";
+const PROP_ISBN10: &str = "P957";
+const PROP_ISBN13: &str = "P212";
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum IdProp {
@@ -15,6 +19,8 @@ pub enum IdProp {
     DOI,
     ARXIV,
     SemanticScholar,
+    ISBN10,
+    ISBN13,
 }
 
 impl FromStr for IdProp {
@@ -27,6 +33,8 @@ impl FromStr for IdProp {
             PROP_DOI => Ok(IdProp::DOI),
             PROP_ARXIV => Ok(IdProp::ARXIV),
             PROP_SEMANTIC_SCHOLAR => Ok(IdProp::SemanticScholar),
+            PROP_ISBN10 => Ok(IdProp::ISBN10),
+            PROP_ISBN13 => Ok(IdProp::ISBN13),
             _ => Err(format!("Invalid ID property: {s}")),
         }
     }
@@ -40,6 +48,8 @@ impl std::fmt::Display for IdProp {
             IdProp::DOI => PROP_DOI,
             IdProp::ARXIV => PROP_ARXIV,
             IdProp::SemanticScholar => PROP_SEMANTIC_SCHOLAR,
+            IdProp::ISBN10 => PROP_ISBN10,
+            IdProp::ISBN13 => PROP_ISBN13,
         })
     }
 }
@@ -52,6 +62,8 @@ impl IdProp {
             IdProp::DOI => PROP_DOI,
             IdProp::ARXIV => PROP_ARXIV,
             IdProp::SemanticScholar => PROP_SEMANTIC_SCHOLAR,
+            IdProp::ISBN10 => PROP_ISBN10,
+            IdProp::ISBN13 => PROP_ISBN13,
         }
     }
 }
@@ -78,12 +90,12 @@ pub struct GenericWorkIdentifier {
 }
 
 impl GenericWorkIdentifier {
+    /// The ID in the canonical form for `prop`, except that PMCIDs keep
+    /// their `PMC` prefix, which the adapters look them up with.
     pub fn new_prop(prop: IdProp, id: &str) -> Self {
         let id = match &prop {
-            IdProp::DOI => id.to_uppercase(), // DOIs are always uppercase
-            IdProp::SemanticScholar => id.to_lowercase(), /* Semantic Scholar IDs are always
-                                                            * lowercase */
-            _other => id.to_string(),
+            IdProp::PMCID => id.trim().to_string(),
+            prop => normalize_value(prop.as_str(), id),
         };
         Self {
             work_type: GenericWorkType::Property(prop),
@@ -91,12 +103,27 @@ impl GenericWorkIdentifier {
         }
     }
 
+    /// Both ISBN identifiers for a book; the ISBN-10 only if one exists.
+    pub fn new_isbn(isbn: &Isbn) -> Vec<Self> {
+        let mut ret = vec![Self::new_prop(IdProp::ISBN13, isbn.isbn13())];
+        if let Some(isbn10) = isbn.isbn10() {
+            ret.push(Self::new_prop(IdProp::ISBN10, &isbn10));
+        }
+        ret
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
 
     pub fn is_legit(&self) -> bool {
-        !self.id.is_empty() && self.id != "0"
+        match &self.work_type {
+            GenericWorkType::Property(IdProp::ISBN13) => Isbn::parse(&self.id).is_some(),
+            GenericWorkType::Property(IdProp::ISBN10) => {
+                Isbn::parse(&self.id).and_then(|isbn| isbn.isbn10()).is_some()
+            },
+            _ => !self.id.is_empty() && self.id != "0",
+        }
     }
 
     pub fn work_type(&self) -> &GenericWorkType {
@@ -104,15 +131,20 @@ impl GenericWorkIdentifier {
     }
 
     /// Parses a free-form identifier string into zero or more
-    /// `GenericWorkIdentifier`s. Recognises DOIs (`xx/yy`), PubMed IDs
-    /// (digits only) and PMC IDs (`PMCnnn`). Q-items are intentionally
-    /// excluded; callers handle those separately.
+    /// `GenericWorkIdentifier`s. Recognises ISBNs (with or without hyphens,
+    /// checksum-validated), DOIs (`xx/yy`), PubMed IDs (digits only) and
+    /// PMC IDs (`PMCnnn`). A string that is a valid ISBN is not also
+    /// treated as a PubMed ID. Q-items are intentionally excluded; callers
+    /// handle those separately.
     pub fn parse_ids_from_str(s: &str) -> Vec<Self> {
         lazy_static::lazy_static! {
             static ref RE_DOI:   Regex = Regex::new(r#"^(.+/.+)$"#).expect("RE_DOI");
             static ref RE_PMID:  Regex = Regex::new(r#"^(\d+)$"#).expect("RE_PMID");
             static ref RE_PMCID: Regex = Regex::new(r#"^(PMC\d+)$"#).expect("RE_PMCID");
         }
+        if let Some(isbn) = Isbn::parse(s) {
+            return Self::new_isbn(&isbn);
+        }
         let mut ids = vec![];
         if let Some(x) = RE_DOI.captures(s).and_then(|c| c.get(1)) {
             ids.push(Self::new_prop(IdProp::DOI, x.as_str()));
@@ -136,7 +168,7 @@ pub fn is_pubmed_id(id: &str) -> bool {
 lazy_static::lazy_static! {
     static ref RE_QID:   Regex = Regex::new(r"^Q\d+$").expect("RE_QID");
     static ref RE_PMCID: Regex = Regex::new(r"^PMC\d+$").expect("RE_PMCID");
-    static ref RE_ORCID: Regex = Regex::new(r"^\d{4}-\d{4}-\d{4}-\d{4}$").expect("RE_ORCID");
+    static ref RE_ORCID: Regex = Regex::new(r"^\d{4}-\d{4}-\d{4}-\d{3}[\dX]$").expect("RE_ORCID");
 }
 
 /// Returns `true` if `s` is a Wikidata Q-ID like `"Q12345"`.
@@ -149,7 +181,8 @@ pub fn is_pmcid(s: &str) -> bool {
     RE_PMCID.is_match(s)
 }
 
-/// Returns `true` if `s` is an ORCID like `"0000-0001-2345-6789"`.
+/// Returns `true` if `s` is an ORCID like `"0000-0001-2345-6789"` or
+/// `"0000-0002-1825-009X"`.
 pub fn is_orcid(s: &str) -> bool {
     RE_ORCID.is_match(s)
 }
@@ -165,6 +198,8 @@ mod tests {
         assert_eq!(IdProp::from_str(PROP_DOI).unwrap(), IdProp::DOI);
         assert_eq!(IdProp::from_str(PROP_ARXIV).unwrap(), IdProp::ARXIV);
         assert_eq!(IdProp::from_str(PROP_SEMANTIC_SCHOLAR).unwrap(), IdProp::SemanticScholar);
+        assert_eq!(IdProp::from_str(PROP_ISBN10).unwrap(), IdProp::ISBN10);
+        assert_eq!(IdProp::from_str(PROP_ISBN13).unwrap(), IdProp::ISBN13);
         assert!(IdProp::from_str("P123").is_err());
     }
 
@@ -175,6 +210,8 @@ mod tests {
         assert_eq!(IdProp::DOI.to_string(), PROP_DOI);
         assert_eq!(IdProp::ARXIV.to_string(), PROP_ARXIV);
         assert_eq!(IdProp::SemanticScholar.to_string(), PROP_SEMANTIC_SCHOLAR);
+        assert_eq!(IdProp::ISBN10.to_string(), PROP_ISBN10);
+        assert_eq!(IdProp::ISBN13.to_string(), PROP_ISBN13);
     }
 
     #[test]
@@ -216,6 +253,7 @@ mod tests {
         assert!(is_orcid("0000-0001-2345-6789"));
         assert!(is_orcid("0000-0000-0000-0000"));
         assert!(is_orcid("9999-9999-9999-9999"));
+        assert!(is_orcid("0000-0002-1825-009X"));
     }
 
     #[test]
@@ -249,6 +287,38 @@ mod tests {
         assert!(!gwi.is_legit());
     }
 
+    #[test]
+    fn test_genericworkidentifier_new_prop_isbn_is_hyphenated() {
+        let gwi = GenericWorkIdentifier::new_prop(IdProp::ISBN13, "9780306406157");
+        assert_eq!(gwi.id(), "978-0-306-40615-7");
+        let gwi = GenericWorkIdentifier::new_prop(IdProp::ISBN10, "ISBN 0306406152");
+        assert_eq!(gwi.id(), "0-306-40615-2");
+        // Unknown registration group: plain digits
+        let gwi = GenericWorkIdentifier::new_prop(IdProp::ISBN13, "978-99921-58-10-4");
+        assert_eq!(gwi.id(), "9789992158104");
+    }
+
+    #[test]
+    fn test_genericworkidentifier_is_legit_isbn() {
+        assert!(GenericWorkIdentifier::new_prop(IdProp::ISBN13, "9780306406157").is_legit());
+        assert!(!GenericWorkIdentifier::new_prop(IdProp::ISBN13, "9780306406158").is_legit());
+        assert!(GenericWorkIdentifier::new_prop(IdProp::ISBN10, "0-8044-2957-X").is_legit());
+        // 979- ISBNs have no ISBN-10 form
+        assert!(!GenericWorkIdentifier::new_prop(IdProp::ISBN10, "9791090636071").is_legit());
+    }
+
+    #[test]
+    fn test_genericworkidentifier_new_isbn() {
+        let ids = GenericWorkIdentifier::new_isbn(&Isbn::parse("0306406152").unwrap());
+        assert_eq!(ids, vec![
+            GenericWorkIdentifier::new_prop(IdProp::ISBN13, "978-0-306-40615-7"),
+            GenericWorkIdentifier::new_prop(IdProp::ISBN10, "0-306-40615-2"),
+        ]);
+        let ids = GenericWorkIdentifier::new_isbn(&Isbn::parse("979-10-90636-07-1").unwrap());
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids[0].work_type(), &GenericWorkType::Property(IdProp::ISBN13));
+    }
+
     #[test]
     fn test_genericworkidentifier_work_type() {
         let prop = IdProp::DOI;
@@ -272,6 +342,8 @@ mod tests {
         assert_eq!(IdProp::DOI.as_str(), PROP_DOI);
         assert_eq!(IdProp::ARXIV.as_str(), PROP_ARXIV);
         assert_eq!(IdProp::SemanticScholar.as_str(), PROP_SEMANTIC_SCHOLAR);
+        assert_eq!(IdProp::ISBN10.as_str(), PROP_ISBN10);
+        assert_eq!(IdProp::ISBN13.as_str(), PROP_ISBN13);
     }
 
     // === parse_ids_from_str ===
@@ -300,6 +372,32 @@ mod tests {
         assert_eq!(ids[0].work_type(), &GenericWorkType::Property(IdProp::PMCID));
     }
 
+    #[test]
+    fn parse_ids_from_str_isbn13() {
+        let ids = GenericWorkIdentifier::parse_ids_from_str("978-3-16-148410-0");
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[0].id(), "978-3-16-148410-0");
+        assert_eq!(ids[0].work_type(), &GenericWorkType::Property(IdProp::ISBN13));
+        assert_eq!(ids[1].id(), "3-16-148410-0");
+        assert_eq!(ids[1].work_type(), &GenericWorkType::Property(IdProp::ISBN10));
+    }
+
+    #[test]
+    fn parse_ids_from_str_isbn10_digits_only_is_not_pmid() {
+        let ids = GenericWorkIdentifier::parse_ids_from_str("0306406152");
+        assert!(ids.iter().all(|id| id.work_type() != &GenericWorkType::Property(IdProp::PMID)));
+        assert!(ids.iter().any(|id| id.id() == "978-0-306-40615-7"));
+    }
+
+    #[test]
+    fn parse_ids_from_str_bad_isbn_checksum_is_not_isbn() {
+        let ids = GenericWorkIdentifier::parse_ids_from_str("9783161484101");
+        assert!(ids.iter().all(|id| {
+            id.work_type() != &GenericWorkType::Property(IdProp::ISBN13)
+                && id.work_type() != &GenericWorkType::Property(IdProp::ISBN10)
+        }));
+    }
+
     #[test]
     fn parse_ids_from_str_empty_returns_nothing() {
         let ids = GenericWorkIdentifier::parse_ids_from_str("");
@@ -0,0 +1,343 @@
+//! ISBN parsing, validation and formatting.
+//!
+//! Wikidata stores books under two properties: P212 (ISBN-13) and P957
+//! (ISBN-10), both in their hyphenated form (`978-3-16-148410-0`). Input
+//! from batches arrives in every shape imaginable — with or without
+//! hyphens, with an `ISBN:` prefix, with a lowercase `x` check digit — so
+//! [`Isbn::parse`] normalises all of them to a 13-digit canonical form and
+//! rejects anything whose check digit doesn't add up.
+//!
+//! Hyphenation needs the registrant ranges from the ISBN International
+//! range message. Only the registration groups SourceMD sees in practice
+//! are tabulated in [`RANGES`]; for other groups the `hyphenated*`
+//! methods return `None` and callers fall back to the plain digits.
+
+use regex::Regex;
+
+/// `(prefix, registration group, [(range start, range end, registrant length)])`.
+///
+/// Range bounds are the seven digits that follow the registration group,
+/// exactly as in the range message.
+type GroupRanges = (&'static str, &'static str, &'static [(u32, u32, usize)]);
+
+const RANGES: &[GroupRanges] = &[
+    // English language
+    (
+        "978",
+        "0",
+        &[
+            (0, 1999999, 2),
+            (2000000, 6999999, 3),
+            (7000000, 8499999, 4),
+            (8500000, 8999999, 5),
+            (9000000, 9499999, 6),
+            (9500000, 9999999, 7),
+        ],
+    ),
+    (
+        "978",
+        "1",
+        &[
+            (0, 999999, 2),
+            (1000000, 3999999, 3),
+            (4000000, 5499999, 4),
+            (5500000, 8697999, 5),
+            (8698000, 9989999, 6),
+            (9990000, 9999999, 7),
+        ],
+    ),
+    // French language
+    (
+        "978",
+        "2",
+        &[
+            (0, 1999999, 2),
+            (2000000, 3499999, 3),
+            (3500000, 3999999, 5),
+            (4000000, 6999999, 3),
+            (7000000, 8399999, 4),
+            (8400000, 8999999, 5),
+            (9000000, 9499999, 6),
+            (9500000, 9999999, 7),
+        ],
+    ),
+    // German language
+    (
+        "978",
+        "3",
+        &[
+            (0, 299999, 2),
+            (300000, 339999, 3),
+            (340000, 369999, 4),
+            (370000, 399999, 5),
+            (400000, 1999999, 2),
+            (2000000, 6999999, 3),
+            (7000000, 8499999, 4),
+            (8500000, 8999999, 5),
+            (9000000, 9499999, 6),
+            (9500000, 9539999, 7),
+            (9540000, 9699999, 5),
+            (9700000, 9849999, 7),
+            (9850000, 9999999, 5),
+        ],
+    ),
+    // Japan
+    (
+        "978",
+        "4",
+        &[
+            (0, 1999999, 2),
+            (2000000, 6999999, 3),
+            (7000000, 8499999, 4),
+            (8500000, 8999999, 5),
+            (9000000, 9499999, 6),
+            (9500000, 9999999, 7),
+        ],
+    ),
+    // China
+    (
+        "978",
+        "7",
+        &[
+            (0, 999999, 2),
+            (1000000, 4999999, 3),
+            (5000000, 7999999, 4),
+            (8000000, 8999999, 5),
+            (9000000, 9999999, 6),
+        ],
+    ),
+    // France (979 prefix)
+    (
+        "979",
+        "10",
+        &[
+            (0, 1999999, 2),
+            (2000000, 6999999, 3),
+            (7000000, 8999999, 4),
+            (9000000, 9759999, 5),
+            (9760000, 9999999, 6),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Isbn {
+    /// Canonical 13-digit form, no hyphens.
+    digits: String,
+}
+
+impl Isbn {
+    /// Parses an ISBN-10 or ISBN-13, ignoring hyphens, spaces and an
+    /// optional `ISBN`/`ISBN-10`/`ISBN-13` prefix. Returns `None` if the
+    /// string isn't a well-formed ISBN or the check digit is wrong.
+    pub fn parse(s: &str) -> Option<Self> {
+        lazy_static! {
+            static ref RE_PREFIX: Regex =
+                Regex::new(r"(?i)^\s*ISBN(?:-1[03])?:?\s*").expect("Isbn::parse: RE_PREFIX");
+        }
+        let s = RE_PREFIX.replace(s, "");
+        let compact: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != ' ')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        match compact.len() {
+            10 => {
+                let (body, check) = compact.split_at(9);
+                if !body.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                if check != Self::isbn10_check_digit(body).to_string() {
+                    return None;
+                }
+                let body13 = format!("978{body}");
+                let check13 = Self::isbn13_check_digit(&body13);
+                Some(Self { digits: format!("{body13}{check13}") })
+            },
+            13 => {
+                if !compact.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                if !compact.starts_with("978") && !compact.starts_with("979") {
+                    return None;
+                }
+                let (body, check) = compact.split_at(12);
+                if check != Self::isbn13_check_digit(body).to_string() {
+                    return None;
+                }
+                Some(Self { digits: compact })
+            },
+            _ => None,
+        }
+    }
+
+    /// The 13 digits, without hyphens.
+    pub fn isbn13(&self) -> &str {
+        &self.digits
+    }
+
+    /// The equivalent ISBN-10, without hyphens. Only `978-` ISBNs have
+    /// one; `979-` ISBNs return `None`.
+    pub fn isbn10(&self) -> Option<String> {
+        let body = self.digits.strip_prefix("978")?.get(..9)?;
+        Some(format!("{body}{}", Self::isbn10_check_digit(body)))
+    }
+
+    /// ISBN-13 with hyphens between prefix, group, registrant, publication
+    /// and check digit, as Wikidata expects for P212. `None` if the
+    /// registration group isn't in the range table.
+    pub fn hyphenated13(&self) -> Option<String> {
+        let (prefix, group, registrant, publication) = self.split()?;
+        Some(format!("{prefix}-{group}-{registrant}-{publication}-{}", &self.digits[12..]))
+    }
+
+    /// ISBN-10 with hyphens, as Wikidata expects for P957. `None` for
+    /// `979-` ISBNs or if the registration group isn't in the range table.
+    pub fn hyphenated10(&self) -> Option<String> {
+        let isbn10 = self.isbn10()?;
+        let (_prefix, group, registrant, publication) = self.split()?;
+        Some(format!("{group}-{registrant}-{publication}-{}", &isbn10[9..]))
+    }
+
+    /// Splits the first twelve digits into prefix, registration group,
+    /// registrant and publication element.
+    fn split(&self) -> Option<(&str, &str, &str, &str)> {
+        let (prefix, rest) = self.digits.split_at(3);
+        let (group, ranges) = RANGES
+            .iter()
+            .find(|(p, g, _)| *p == prefix && rest.starts_with(g))
+            .map(|(_, g, r)| (*g, *r))?;
+        let after_group = &rest[group.len()..rest.len() - 1];
+        let key: u32 =
+            format!("{:0<7}", after_group.get(..7).unwrap_or(after_group)).parse().ok()?;
+        let len = ranges.iter().find(|(lo, hi, _)| key >= *lo && key <= *hi).map(|(_, _, l)| *l)?;
+        if len >= after_group.len() {
+            return None;
+        }
+        let (registrant, publication) = after_group.split_at(len);
+        Some((prefix, group, registrant, publication))
+    }
+
+    fn isbn10_check_digit(body: &str) -> char {
+        let sum: u32 = body
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .enumerate()
+            .map(|(i, d)| d * (10 - i as u32))
+            .sum();
+        match (11 - sum % 11) % 11 {
+            10 => 'X',
+            d => char::from_digit(d, 10).unwrap_or('0'),
+        }
+    }
+
+    fn isbn13_check_digit(body: &str) -> char {
+        let sum: u32 = body
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .enumerate()
+            .map(|(i, d)| if i % 2 == 0 { d } else { d * 3 })
+            .sum();
+        char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+    }
+}
+
+impl std::fmt::Display for Isbn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.hyphenated13() {
+            Some(s) => write!(f, "{s}"),
+            None => write!(f, "{}", self.digits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_isbn13_with_and_without_hyphens() {
+        let a = Isbn::parse("978-3-16-148410-0").unwrap();
+        let b = Isbn::parse("9783161484100").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.isbn13(), "9783161484100");
+    }
+
+    #[test]
+    fn parse_accepts_prefix_and_spaces() {
+        assert!(Isbn::parse("ISBN 978 3 16 148410 0").is_some());
+        assert!(Isbn::parse("ISBN-13: 978-3-16-148410-0").is_some());
+        assert!(Isbn::parse("isbn:0306406152").is_some());
+    }
+
+    #[test]
+    fn parse_isbn10_converts_to_13() {
+        let isbn = Isbn::parse("0-306-40615-2").unwrap();
+        assert_eq!(isbn.isbn13(), "9780306406157");
+        assert_eq!(isbn.isbn10(), Some("0306406152".to_string()));
+    }
+
+    #[test]
+    fn parse_isbn10_with_x_check_digit() {
+        let upper = Isbn::parse("0-8044-2957-X").unwrap();
+        let lower = Isbn::parse("080442957x").unwrap();
+        assert_eq!(upper, lower);
+        assert_eq!(upper.isbn13(), "9780804429573");
+        assert_eq!(upper.isbn10(), Some("080442957X".to_string()));
+    }
+
+    #[test]
+    fn parse_rejects_bad_check_digits() {
+        assert!(Isbn::parse("978-3-16-148410-1").is_none());
+        assert!(Isbn::parse("0-306-40615-3").is_none());
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(Isbn::parse("").is_none());
+        assert!(Isbn::parse("12345").is_none());
+        assert!(Isbn::parse("10.1234/abc").is_none());
+        assert!(Isbn::parse("97831614841X0").is_none());
+        // Valid EAN-13 check digit, but not a Bookland prefix.
+        assert!(Isbn::parse("4006381333931").is_none());
+    }
+
+    #[test]
+    fn isbn10_is_none_for_979() {
+        let isbn = Isbn::parse("979-10-90636-07-1").unwrap();
+        assert_eq!(isbn.isbn10(), None);
+        assert_eq!(isbn.hyphenated10(), None);
+    }
+
+    #[test]
+    fn hyphenation_matches_range_table() {
+        let isbn = Isbn::parse("9783161484100").unwrap();
+        assert_eq!(isbn.hyphenated13(), Some("978-3-16-148410-0".to_string()));
+        assert_eq!(isbn.hyphenated10(), Some("3-16-148410-0".to_string()));
+
+        let isbn = Isbn::parse("0306406152").unwrap();
+        assert_eq!(isbn.hyphenated13(), Some("978-0-306-40615-7".to_string()));
+        assert_eq!(isbn.hyphenated10(), Some("0-306-40615-2".to_string()));
+
+        let isbn = Isbn::parse("080442957X").unwrap();
+        assert_eq!(isbn.hyphenated13(), Some("978-0-8044-2957-3".to_string()));
+        assert_eq!(isbn.hyphenated10(), Some("0-8044-2957-X".to_string()));
+
+        let isbn = Isbn::parse("9791090636071").unwrap();
+        assert_eq!(isbn.hyphenated13(), Some("979-10-90636-07-1".to_string()));
+    }
+
+    #[test]
+    fn hyphenation_unknown_group_is_none() {
+        // 978-99... is a five-digit registration group we don't tabulate.
+        let isbn = Isbn::parse("9789992158104").unwrap();
+        assert_eq!(isbn.hyphenated13(), None);
+        assert_eq!(isbn.to_string(), "9789992158104");
+    }
+
+    #[test]
+    fn display_uses_hyphenated_form() {
+        assert_eq!(Isbn::parse("9783161484100").unwrap().to_string(), "978-3-16-148410-0");
+    }
+}
@@ -0,0 +1,256 @@
+//! Journal metadata as reported by publication sources, and the rules for
+//! creating a journal item from it.
+//!
+//! Papers are linked to their journal via P1433 (published in), which
+//! [`WikidataStringCache::issn2q`](crate::wikidata_string_cache::WikidataStringCache::issn2q)
+//! resolves by ISSN. If no item has the ISSN yet, `WikidataPapers` can
+//! create the journal, but only when `create_journals` is switched on.
+//! Sources occasionally report a wrong ISSN, so a new journal is built from
+//! [`JournalInfo::corroborated`] metadata only: a title, and an ISSN with a
+//! valid check digit that at least [`MIN_SOURCES_PER_ISSN`] sources agree on.
+
+use crate::{key_normalization::normalize_value, *};
+
+/// Number of sources that must report an ISSN before a journal is created
+/// for it.
+pub const MIN_SOURCES_PER_ISSN: usize = 2;
+
+/// Q-item for "scientific journal"
+const SCIENTIFIC_JOURNAL: &str = "Q5633421";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JournalInfo {
+    pub title: Option<String>,
+    /// Linking ISSN (P7363)
+    pub issn_l: Option<String>,
+    /// Print and electronic ISSNs (P236)
+    pub issns: Vec<String>,
+    /// OpenAlex ID of the publisher, resolved to an item via P10283
+    pub publisher_openalex_id: Option<String>,
+    /// NLM Unique ID (P1055)
+    pub nlm_id: Option<String>,
+}
+
+impl JournalInfo {
+    /// All valid ISSNs in normalised form, ISSN-L first.
+    pub fn all_issns(&self) -> Vec<String> {
+        let mut ret: Vec<String> = vec![];
+        for issn in self.issn_l.iter().chain(self.issns.iter()) {
+            match normalize_issn(issn) {
+                Some(issn) if issn_is_valid(&issn) && !ret.contains(&issn) => ret.push(issn),
+                _ => {},
+            }
+        }
+        ret
+    }
+
+    /// Merges what several sources report about the same journal. Returns
+    /// `None` unless there is enough evidence to create an item; ISSNs that
+    /// too few sources know about are dropped. Title, publisher and NLM ID
+    /// come from the first source that has them.
+    pub fn corroborated(infos: &[JournalInfo]) -> Option<JournalInfo> {
+        let per_source: Vec<Vec<String>> = infos.iter().map(|info| info.all_issns()).collect();
+        let mut issns: Vec<String> = vec![];
+        for issn in per_source.iter().flatten() {
+            let sources = per_source.iter().filter(|issns| issns.contains(issn)).count();
+            if sources >= MIN_SOURCES_PER_ISSN && !issns.contains(issn) {
+                issns.push(issn.to_owned());
+            }
+        }
+        if issns.is_empty() {
+            return None;
+        }
+        let title = infos
+            .iter()
+            .filter_map(|info| info.title.as_deref())
+            .map(str::trim)
+            .find(|t| !t.is_empty())?;
+        let issn_l = infos
+            .iter()
+            .filter_map(|info| info.issn_l.as_deref())
+            .filter_map(normalize_issn)
+            .find(|issn| issns.contains(issn));
+        Some(JournalInfo {
+            title: Some(title.to_string()),
+            issn_l,
+            issns,
+            publisher_openalex_id: infos.iter().find_map(|info| info.publisher_openalex_id.clone()),
+            nlm_id: infos.iter().find_map(|info| info.nlm_id.clone()),
+        })
+    }
+
+    /// A new journal item. `publisher` is the item of the publisher, if known.
+    pub fn new_item(&self, publisher: Option<&str>) -> Entity {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(
+            Snak::new_item("P31", SCIENTIFIC_JOURNAL),
+            vec![],
+            vec![],
+        ));
+        if let Some(title) = &self.title {
+            item.set_label(LocaleString::new("en", title));
+            item.add_claim(Statement::new_normal(
+                Snak::new_monolingual_text("P1476", title, "en"),
+                vec![],
+                vec![],
+            ));
+        }
+        let mut ids: Vec<(&str, &String)> = self.issns.iter().map(|issn| ("P236", issn)).collect();
+        ids.extend(self.issn_l.iter().map(|issn| ("P7363", issn)));
+        ids.extend(self.nlm_id.iter().map(|nlm_id| ("P1055", nlm_id)));
+        for (property, id) in ids {
+            item.add_claim(Statement::new_normal(
+                Snak::new_external_id(property, &normalize_value(property, id)),
+                vec![],
+                vec![],
+            ));
+        }
+        if let Some(publisher) = publisher {
+            item.add_claim(Statement::new_normal(
+                Snak::new_item("P123", publisher),
+                vec![],
+                vec![],
+            ));
+        }
+        item
+    }
+}
+
+/// Brings an ISSN into the `1234-567X` form Wikidata uses; `None` if it
+/// doesn't look like an ISSN at all. The check digit is not verified.
+pub fn normalize_issn(issn: &str) -> Option<String> {
+    let chars: Vec<char> = issn
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let valid = chars.len() == 8
+        && chars[..7].iter().all(|c| c.is_ascii_digit())
+        && (chars[7].is_ascii_digit() || chars[7] == 'X');
+    if !valid {
+        return None;
+    }
+    let issn: String = chars.into_iter().collect();
+    Some(format!("{}-{}", &issn[..4], &issn[4..]))
+}
+
+/// Checks the ISSN check digit (mod 11, `X` standing for 10).
+pub fn issn_is_valid(issn: &str) -> bool {
+    let issn = match normalize_issn(issn) {
+        Some(issn) => issn.replace('-', ""),
+        None => return false,
+    };
+    let sum: u32 = issn
+        .chars()
+        .take(7)
+        .zip((2..=8).rev())
+        .map(|(c, weight)| c.to_digit(10).unwrap_or(0) * weight)
+        .sum();
+    let check = match (11 - sum % 11) % 11 {
+        10 => 'X',
+        n => char::from_digit(n, 10).unwrap_or('?'),
+    };
+    issn.ends_with(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(title: Option<&str>, issns: &[&str]) -> JournalInfo {
+        JournalInfo {
+            title: title.map(|t| t.to_string()),
+            issns: issns.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize_issn() {
+        assert_eq!(super::normalize_issn("1351-5101"), Some("1351-5101".to_string()));
+        assert_eq!(super::normalize_issn(" 0317847x "), Some("0317-847X".to_string()));
+        assert_eq!(super::normalize_issn("nope-di-dope"), None);
+        assert_eq!(super::normalize_issn("1351-510"), None);
+        assert_eq!(super::normalize_issn(""), None);
+    }
+
+    #[test]
+    fn issn_is_valid() {
+        assert!(super::issn_is_valid("0028-0836")); // Nature
+        assert!(super::issn_is_valid("1553-734x")); // PLoS Pathogens
+        assert!(super::issn_is_valid("20493630"));
+        assert!(!super::issn_is_valid("0028-0837"));
+        assert!(!super::issn_is_valid("nope"));
+    }
+
+    #[test]
+    fn all_issns_drops_invalid_and_duplicates() {
+        let mut journal = info(None, &["00280836", "0028-0837", "1476-4687"]);
+        journal.issn_l = Some("0028-0836".to_string());
+        assert_eq!(journal.all_issns(), vec!["0028-0836", "1476-4687"]);
+    }
+
+    #[test]
+    fn corroborated_needs_two_sources() {
+        assert_eq!(JournalInfo::corroborated(&[info(Some("Nature"), &["0028-0836"])]), None);
+        let merged = JournalInfo::corroborated(&[
+            info(Some("Nature"), &["0028-0836", "1476-4687"]),
+            info(None, &["0028-0836"]),
+        ])
+        .unwrap();
+        assert_eq!(merged.title.as_deref(), Some("Nature"));
+        assert_eq!(merged.issns, vec!["0028-0836"]);
+    }
+
+    #[test]
+    fn corroborated_ignores_typo_issn() {
+        // The typo still has a valid check digit, but no other source backs it
+        let infos = [info(Some("Nature"), &["0028-0836"]), info(Some("Nature"), &["0028-0860"])];
+        assert_eq!(JournalInfo::corroborated(&infos), None);
+    }
+
+    #[test]
+    fn corroborated_needs_title() {
+        let infos = [info(None, &["0028-0836"]), info(Some(" "), &["0028-0836"])];
+        assert_eq!(JournalInfo::corroborated(&infos), None);
+    }
+
+    #[test]
+    fn corroborated_keeps_agreed_issn_l_and_ids() {
+        let mut openalex = info(Some("Nature"), &["0028-0836", "1476-4687"]);
+        openalex.issn_l = Some("0028-0836".to_string());
+        openalex.publisher_openalex_id = Some("P4310319908".to_string());
+        let mut pubmed = info(Some("Nature"), &["1476-4687"]);
+        pubmed.issn_l = Some("0028-0836".to_string());
+        pubmed.nlm_id = Some("0410462".to_string());
+        let merged = JournalInfo::corroborated(&[openalex, pubmed]).unwrap();
+        assert_eq!(merged.issn_l.as_deref(), Some("0028-0836"));
+        assert_eq!(merged.issns, vec!["0028-0836", "1476-4687"]);
+        assert_eq!(merged.publisher_openalex_id.as_deref(), Some("P4310319908"));
+        assert_eq!(merged.nlm_id.as_deref(), Some("0410462"));
+    }
+
+    #[test]
+    fn new_item() {
+        let mut journal = info(Some("Nature"), &["0028-0836", "1476-4687"]);
+        journal.issn_l = Some("0028-0836".to_string());
+        journal.nlm_id = Some("0410462".to_string());
+        let item = journal.new_item(Some("Q176916"));
+        assert!(item.has_target_entity("P31", SCIENTIFIC_JOURNAL));
+        assert!(item.has_target_entity("P123", "Q176916"));
+        assert_eq!(item.label_in_locale("en"), Some("Nature"));
+        let count =
+            |property: &str| item.claims().iter().filter(|s| s.property() == property).count();
+        assert_eq!(count("P1476"), 1);
+        assert_eq!(count("P236"), 2);
+        assert_eq!(count("P7363"), 1);
+        assert_eq!(count("P1055"), 1);
+    }
+
+    #[test]
+    fn new_item_without_publisher() {
+        let item = info(Some("Nature"), &["0028-0836"]).new_item(None);
+        assert!(!item.has_claims_with_property("P123"));
+        assert!(!item.has_claims_with_property("P1055"));
+    }
+}
@@ -0,0 +1,181 @@
+//! How identifier values are written, per property.
+//!
+//! The same DOI or ORCID comes from sources, input files and Wikidata in
+//! different spellings. `KeyNormalization::for_property` picks one form per
+//! property, and cache keys, searches, the identifier index and new
+//! statements all use it, so a lookup and an edit always agree on the value.
+
+use std::fmt;
+
+use crate::{isbn::Isbn, journal_info::normalize_issn};
+
+/// The canonical form of the values of a property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyNormalization {
+    /// Upper case (P356). DOIs are case-insensitive, and Wikidata has them
+    /// in upper case.
+    Doi,
+    /// `0000-0002-1825-009X`, without the `https://orcid.org/` prefix, and
+    /// with an upper-case `X` check digit (P496)
+    Orcid,
+    /// `1234-567X` (P236, P7363)
+    Issn,
+    /// Digits only, without the `PMC` prefix (P932)
+    Pmcid,
+    /// Hyphenated if the registration group is known (P212)
+    Isbn13,
+    /// Hyphenated if the registration group is known (P957)
+    Isbn10,
+    /// Lower case (P4011, Semantic Scholar paper IDs)
+    Lowercase,
+    /// Surrounding whitespace removed; all other properties
+    Trim,
+}
+
+impl KeyNormalization {
+    /// The normalization for the values of `property`.
+    pub fn for_property(property: &str) -> Self {
+        match property {
+            "P356" => KeyNormalization::Doi,
+            "P496" => KeyNormalization::Orcid,
+            "P236" | "P7363" => KeyNormalization::Issn,
+            "P932" => KeyNormalization::Pmcid,
+            "P212" => KeyNormalization::Isbn13,
+            "P957" => KeyNormalization::Isbn10,
+            "P4011" => KeyNormalization::Lowercase,
+            _ => KeyNormalization::Trim,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyNormalization::Doi => "doi",
+            KeyNormalization::Orcid => "orcid",
+            KeyNormalization::Issn => "issn",
+            KeyNormalization::Pmcid => "pmcid",
+            KeyNormalization::Isbn13 => "isbn13",
+            KeyNormalization::Isbn10 => "isbn10",
+            KeyNormalization::Lowercase => "lowercase",
+            KeyNormalization::Trim => "trim",
+        }
+    }
+
+    /// `value` in canonical form. Values that don't parse (an ISSN with
+    /// the wrong length, say) are only trimmed.
+    pub fn normalize(&self, value: &str) -> String {
+        let value = value.trim();
+        let normalized = match self {
+            KeyNormalization::Doi => Some(value.to_uppercase()),
+            KeyNormalization::Orcid => normalize_orcid(value),
+            KeyNormalization::Issn => normalize_issn(value),
+            KeyNormalization::Pmcid => Some(strip_prefix_ignore_case(value, "PMC").to_string()),
+            KeyNormalization::Isbn13 => Isbn::parse(value)
+                .map(|isbn| isbn.hyphenated13().unwrap_or_else(|| isbn.isbn13().to_string())),
+            KeyNormalization::Isbn10 => {
+                Isbn::parse(value).and_then(|isbn| isbn.hyphenated10().or_else(|| isbn.isbn10()))
+            },
+            KeyNormalization::Lowercase => Some(value.to_lowercase()),
+            KeyNormalization::Trim => None,
+        };
+        normalized.unwrap_or_else(|| value.to_string())
+    }
+
+    /// The spellings to search Wikidata for, canonical form first. Only
+    /// ISBNs have a second one, since many are stored without hyphens.
+    pub fn spellings(&self, value: &str) -> Vec<String> {
+        let mut spellings = vec![self.normalize(value)];
+        let plain = match self {
+            KeyNormalization::Isbn13 => Isbn::parse(value).map(|isbn| isbn.isbn13().to_string()),
+            KeyNormalization::Isbn10 => Isbn::parse(value).and_then(|isbn| isbn.isbn10()),
+            _ => None,
+        };
+        spellings.extend(plain.filter(|plain| !spellings.contains(plain)));
+        spellings
+    }
+}
+
+impl fmt::Display for KeyNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `value` in the canonical form for `property`.
+pub fn normalize_value(property: &str, value: &str) -> String {
+    KeyNormalization::for_property(property).normalize(value)
+}
+
+fn normalize_orcid(orcid: &str) -> Option<String> {
+    let orcid = strip_prefix_ignore_case(orcid, "https://orcid.org/");
+    let orcid = strip_prefix_ignore_case(orcid, "http://orcid.org/");
+    let chars: Vec<char> = orcid
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let valid = chars.len() == 16
+        && chars[..15].iter().all(|c| c.is_ascii_digit())
+        && (chars[15].is_ascii_digit() || chars[15] == 'X');
+    if !valid {
+        return None;
+    }
+    let groups: Vec<String> = chars.chunks(4).map(|group| group.iter().collect()).collect();
+    Some(groups.join("-"))
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> &'a str {
+    match value.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => &value[prefix.len()..],
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dois_are_upper_case() {
+        assert_eq!(normalize_value("P356", " 10.1000/abc.De "), "10.1000/ABC.DE");
+    }
+
+    #[test]
+    fn orcids_are_hyphenated() {
+        assert_eq!(normalize_value("P496", "000000021825009x"), "0000-0002-1825-009X");
+        assert_eq!(
+            normalize_value("P496", "https://orcid.org/0000-0002-1825-0097"),
+            "0000-0002-1825-0097"
+        );
+        assert_eq!(normalize_value("P496", "0000-0002-1825"), "0000-0002-1825");
+    }
+
+    #[test]
+    fn issns_and_pmcids() {
+        assert_eq!(normalize_value("P236", "0028083x"), "0028-083X");
+        assert_eq!(normalize_value("P7363", "1351-5101"), "1351-5101");
+        assert_eq!(normalize_value("P932", "PMC1234567"), "1234567");
+        assert_eq!(normalize_value("P932", "pmc1234567"), "1234567");
+        assert_eq!(normalize_value("P932", "1234567"), "1234567");
+    }
+
+    #[test]
+    fn isbns_are_hyphenated() {
+        assert_eq!(normalize_value("P212", "9780306406157"), "978-0-306-40615-7");
+        assert_eq!(normalize_value("P957", "0306406152"), "0-306-40615-2");
+        assert_eq!(normalize_value("P212", "not an isbn"), "not an isbn");
+    }
+
+    #[test]
+    fn other_properties_are_trimmed() {
+        assert_eq!(normalize_value("P4011", "ABCdef"), "abcdef");
+        assert_eq!(normalize_value("P698", " 123 "), "123");
+        assert_eq!(normalize_value("P818", "hep-TH/9901001"), "hep-TH/9901001");
+    }
+
+    #[test]
+    fn isbns_are_searched_without_hyphens_too() {
+        let spellings = KeyNormalization::Isbn13.spellings("978-0-306-40615-7");
+        assert_eq!(spellings, vec!["978-0-306-40615-7", "9780306406157"]);
+        assert_eq!(KeyNormalization::Doi.spellings("10.1/x"), vec!["10.1/X"]);
+    }
+}
@@ -6,20 +6,37 @@ extern crate lazy_static;
 use wikibase::{entity_diff::*, *};
 
 pub mod adapter_helpers;
+pub mod append_writer;
 pub mod arxiv2wikidata;
+pub mod audit_report;
 pub mod author_name_string;
+pub mod checkpoint;
+pub mod citation_export;
 pub mod crossref2wikidata;
 pub mod datacite2wikidata;
+pub mod discrepancy_report;
+pub mod duplicates_log;
+pub mod edit_output;
 pub mod europepmc2wikidata;
 pub mod generic_author_info;
 pub mod http_client;
+pub mod identifier_index;
 pub mod identifiers;
+pub mod isbn;
+pub mod journal_info;
+pub mod key_normalization;
 pub mod language_cache;
+pub mod merge_candidate;
 pub mod openalex2wikidata;
+pub mod openlibrary;
 pub mod orcid2wikidata;
+pub mod persistent_cache;
 pub mod pmc2wikidata;
+pub mod publication_record;
 pub mod pubmed2wikidata;
+pub mod quickstatements;
 pub mod rate_limit_log;
+pub mod reference_import;
 pub mod scientific_publication_adapter;
 #[cfg(test)]
 pub(crate) mod test_helpers;
@@ -0,0 +1,330 @@
+//! Papers whose identifiers lead to more than one item.
+//!
+//! When the DOI of a paper is on one item and its PMID on another, the two
+//! items are most likely duplicates. Editing only one of them would leave
+//! the other half-updated, so the items are reported as a merge candidate
+//! instead, and only edited if a `DuplicatePolicy` picks one of them. A
+//! single identifier that is on several items says nothing about which of
+//! them is the paper, so no policy picks one then.
+
+use std::{fmt, io::Write, path::Path, str::FromStr};
+
+use anyhow::Result;
+
+use crate::{append_writer::AppendWriter, identifiers::GenericWorkIdentifier};
+
+/// What to do with a paper whose identifiers are on several items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Edit none of the items
+    #[default]
+    Refuse,
+    /// Edit the item with the lowest Q number, if each identifier is on
+    /// only one item
+    Oldest,
+}
+
+impl DuplicatePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Refuse => "refuse",
+            DuplicatePolicy::Oldest => "oldest",
+        }
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "refuse" => Ok(DuplicatePolicy::Refuse),
+            "oldest" => Ok(DuplicatePolicy::Oldest),
+            other => Err(format!("Unknown duplicate policy '{other}'")),
+        }
+    }
+}
+
+impl fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The items a paper's identifiers were found on, and which identifier led
+/// to which item.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeCandidate {
+    hits: Vec<(GenericWorkIdentifier, String)>,
+}
+
+impl MergeCandidate {
+    pub fn new(hits: Vec<(GenericWorkIdentifier, String)>) -> Self {
+        Self { hits }
+    }
+
+    pub fn hits(&self) -> &[(GenericWorkIdentifier, String)] {
+        &self.hits
+    }
+
+    /// The distinct items, oldest (lowest Q number) first.
+    pub fn items(&self) -> Vec<String> {
+        let mut items: Vec<String> = self.hits.iter().map(|(_, q)| q.to_owned()).collect();
+        items.sort_by_key(|q| (q_number(q), q.to_owned()));
+        items.dedup();
+        items
+    }
+
+    /// More than one item was found.
+    pub fn is_duplicate(&self) -> bool {
+        self.items().len() > 1
+    }
+
+    /// An identifier was found on more than one item.
+    pub fn has_ambiguous_id(&self) -> bool {
+        self.hits.iter().any(|(id, q)| self.hits.iter().any(|(other, hit)| other == id && hit != q))
+    }
+
+    /// The item `policy` picks for editing, if any. Never one of several
+    /// items that share an identifier.
+    pub fn winner(&self, policy: DuplicatePolicy) -> Option<String> {
+        match policy {
+            DuplicatePolicy::Refuse if self.is_duplicate() => None,
+            DuplicatePolicy::Oldest if self.has_ambiguous_id() => None,
+            _ => self.items().into_iter().next(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let hits: Vec<serde_json::Value> = self
+            .hits
+            .iter()
+            .map(|(id, q)| json!({"property": id.work_type().to_string(), "id": id.id(), "q": q}))
+            .collect();
+        json!({"items": self.items(), "hits": hits})
+    }
+
+    /// `q` and the identifiers that led to it, for each item.
+    fn item_ids(&self) -> Vec<(String, Vec<String>)> {
+        self.items()
+            .into_iter()
+            .map(|q| {
+                let ids = self
+                    .hits
+                    .iter()
+                    .filter(|(_, hit)| *hit == q)
+                    .map(|(id, _)| format!("{}:{}", id.work_type(), id.id()))
+                    .collect();
+                (q, ids)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for MergeCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .item_ids()
+            .into_iter()
+            .map(|(q, ids)| if ids.is_empty() { q } else { format!("{q} ({})", ids.join(", ")) })
+            .collect();
+        f.write_str(&parts.join("; "))
+    }
+}
+
+fn q_number(q: &str) -> u64 {
+    q.trim_start_matches(['Q', 'q']).parse().unwrap_or(u64::MAX)
+}
+
+/// How `MergeCandidateWriter` writes its report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// One JSON object per line
+    JsonLines,
+    /// `input,items,edited,hits` with a header line
+    Csv,
+}
+
+impl ReportFormat {
+    /// CSV for a `.csv` file, JSON lines otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ReportFormat::Csv,
+            _ => ReportFormat::JsonLines,
+        }
+    }
+}
+
+/// Appends merge candidates to a report file (or any writer), shared
+/// between all clones.
+#[derive(Debug, Clone)]
+pub struct MergeCandidateWriter {
+    format: ReportFormat,
+    out: AppendWriter,
+}
+
+impl MergeCandidateWriter {
+    pub fn new(format: ReportFormat, out: impl Write + Send + 'static) -> Result<Self> {
+        Self::with_writer(format, AppendWriter::new(out))
+    }
+
+    /// Appends to the report at `path`, as CSV if it ends in `.csv`. The
+    /// CSV header is only written to a new file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        Self::with_writer(ReportFormat::for_path(path), AppendWriter::open(path)?)
+    }
+
+    fn with_writer(format: ReportFormat, out: AppendWriter) -> Result<Self> {
+        if format == ReportFormat::Csv && out.is_new() {
+            out.write_lines(&["input,items,edited,hits"])?;
+        }
+        Ok(Self { format, out })
+    }
+
+    /// Writes the candidate for `input`, with the item that was edited
+    /// instead, if any.
+    pub fn write(
+        &self,
+        input: &str,
+        candidate: &MergeCandidate,
+        edited: Option<&str>,
+    ) -> Result<()> {
+        let line = match self.format {
+            ReportFormat::JsonLines => {
+                let mut j = candidate.to_json();
+                j["input"] = json!(input);
+                j["edited"] = json!(edited);
+                j.to_string()
+            },
+            ReportFormat::Csv => {
+                let hits: Vec<String> = candidate
+                    .hits
+                    .iter()
+                    .map(|(id, q)| format!("{}:{}={q}", id.work_type(), id.id()))
+                    .collect();
+                [input, &candidate.items().join(" "), edited.unwrap_or_default(), &hits.join(" ")]
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            },
+        };
+        self.out.write_lines(&[line])
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{identifiers::IdProp, test_helpers::SharedBuffer};
+
+    fn candidate() -> MergeCandidate {
+        MergeCandidate::new(vec![
+            (GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1/x"), "Q20".to_string()),
+            (GenericWorkIdentifier::new_prop(IdProp::PMID, "123"), "Q3".to_string()),
+            (GenericWorkIdentifier::new_prop(IdProp::PMCID, "456"), "Q20".to_string()),
+        ])
+    }
+
+    #[test]
+    fn items_are_sorted_by_q_number() {
+        assert_eq!(candidate().items(), vec!["Q3", "Q20"]);
+        assert!(candidate().is_duplicate());
+    }
+
+    #[test]
+    fn single_item_is_no_duplicate() {
+        let candidate = MergeCandidate::new(vec![
+            (GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1/x"), "Q20".to_string()),
+            (GenericWorkIdentifier::new_prop(IdProp::PMID, "123"), "Q20".to_string()),
+        ]);
+        assert!(!candidate.is_duplicate());
+        assert_eq!(candidate.winner(DuplicatePolicy::Refuse), Some("Q20".to_string()));
+    }
+
+    #[test]
+    fn winner_depends_on_policy() {
+        assert_eq!(candidate().winner(DuplicatePolicy::Refuse), None);
+        assert_eq!(candidate().winner(DuplicatePolicy::Oldest), Some("Q3".to_string()));
+        assert_eq!(MergeCandidate::default().winner(DuplicatePolicy::Oldest), None);
+    }
+
+    #[test]
+    fn ambiguous_id_has_no_winner() {
+        let doi = GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1/x");
+        let candidate = MergeCandidate::new(vec![
+            (doi.clone(), "Q20".to_string()),
+            (doi, "Q3".to_string()),
+            (GenericWorkIdentifier::new_prop(IdProp::PMID, "123"), "Q3".to_string()),
+        ]);
+        assert!(candidate.has_ambiguous_id());
+        assert!(!self::candidate().has_ambiguous_id());
+        assert_eq!(candidate.winner(DuplicatePolicy::Oldest), None);
+    }
+
+    #[test]
+    fn policy_round_trips() {
+        assert_eq!("Oldest".parse::<DuplicatePolicy>(), Ok(DuplicatePolicy::Oldest));
+        assert_eq!(DuplicatePolicy::Refuse.to_string().parse(), Ok(DuplicatePolicy::Refuse));
+        assert!("newest".parse::<DuplicatePolicy>().is_err());
+    }
+
+    #[test]
+    fn display_lists_ids_per_item() {
+        assert_eq!(candidate().to_string(), "Q3 (P698:123); Q20 (P356:10.1/X, P932:456)");
+    }
+
+    #[test]
+    fn json_report_has_input_and_edited_item() {
+        let buffer = SharedBuffer::default();
+        let writer = MergeCandidateWriter::new(ReportFormat::JsonLines, buffer.clone()).unwrap();
+        writer.write("10.1/x", &candidate(), Some("Q3")).unwrap();
+        let j: serde_json::Value = serde_json::from_str(buffer.text().trim()).unwrap();
+        assert_eq!(j["input"], "10.1/x");
+        assert_eq!(j["edited"], "Q3");
+        assert_eq!(j["items"], json!(["Q3", "Q20"]));
+        assert_eq!(j["hits"][1], json!({"property": "P698", "id": "123", "q": "Q3"}));
+    }
+
+    #[test]
+    fn csv_report_quotes_fields() {
+        let buffer = SharedBuffer::default();
+        let writer = MergeCandidateWriter::new(ReportFormat::Csv, buffer.clone()).unwrap();
+        writer.write("Smith, \"A title\"", &candidate(), None).unwrap();
+        let text = buffer.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "input,items,edited,hits");
+        assert_eq!(
+            lines[1],
+            "\"Smith, \"\"A title\"\"\",Q3 Q20,,P356:10.1/X=Q20 P698:123=Q3 P932:456=Q20"
+        );
+    }
+
+    #[test]
+    fn csv_header_is_written_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("duplicates.csv");
+        MergeCandidateWriter::open(&path).unwrap().write("a", &candidate(), None).unwrap();
+        MergeCandidateWriter::open(&path).unwrap().write("b", &candidate(), None).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "input,items,edited,hits");
+        assert!(lines[2].starts_with("b,"));
+    }
+
+    #[test]
+    fn report_format_follows_extension() {
+        assert_eq!(ReportFormat::for_path(Path::new("dups.CSV")), ReportFormat::Csv);
+        assert_eq!(ReportFormat::for_path(Path::new("dups.jsonl")), ReportFormat::JsonLines);
+    }
+}
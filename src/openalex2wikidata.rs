@@ -8,6 +8,7 @@ use crate::{
     adapter_helpers::{fetch_doi_json, get_external_identifier_from_item},
     generic_author_info::GenericAuthorInfo,
     http_client::{HttpJsonFetcher, JsonFetcher},
+    journal_info::JournalInfo,
     scientific_publication_adapter::{crossref_work_type_to_q, ScientificPublicationAdapter},
     *,
 };
@@ -60,6 +61,22 @@ impl ScientificPublicationAdapter for OpenAlex2Wikidata {
         "OpenAlex2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q107507571") // OpenAlex
+    }
+
+    /// The OpenAlex work ID (P10283), or the DOI the work was looked up by
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        let work_id = self
+            .get_cached_publication_from_id(publication_id)
+            .and_then(|work| work["id"].as_str())
+            .map(|id| id.trim_start_matches("https://openalex.org/").to_string());
+        Some(match work_id {
+            Some(work_id) => ("P10283".to_string(), work_id),
+            None => (IdProp::DOI.as_str().to_string(), publication_id.to_string()),
+        })
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -183,6 +200,41 @@ impl ScientificPublicationAdapter for OpenAlex2Wikidata {
         work["primary_location"]["source"]["issn_l"].as_str().map(|s| s.to_string())
     }
 
+    fn get_work_issns(&self, publication_id: &str) -> Vec<String> {
+        let work = match self.get_cached_publication_from_id(publication_id) {
+            Some(work) => work,
+            None => return vec![],
+        };
+        // ISSN-L first; it is usually also one of the `issn` values
+        let source = &work["primary_location"]["source"];
+        let issns = source["issn"].as_array().map(|a| a.iter().collect()).unwrap_or(vec![]);
+        let mut ret: Vec<String> = vec![];
+        for issn in std::iter::once(&source["issn_l"]).chain(issns).filter_map(|v| v.as_str()) {
+            if !ret.iter().any(|x| x == issn) {
+                ret.push(issn.to_string());
+            }
+        }
+        ret
+    }
+
+    fn get_journal_info(&self, publication_id: &str) -> Option<JournalInfo> {
+        let work = self.get_cached_publication_from_id(publication_id)?;
+        let source = &work["primary_location"]["source"];
+        // Repositories and conference series are sources too
+        if source["type"].as_str().is_some_and(|t| t != "journal") {
+            return None;
+        }
+        Some(JournalInfo {
+            title: source["display_name"].as_str().map(|s| s.to_string()),
+            issn_l: source["issn_l"].as_str().map(|s| s.to_string()),
+            issns: self.get_work_issns(publication_id),
+            publisher_openalex_id: source["host_organization"]
+                .as_str()
+                .map(|s| s.trim_start_matches("https://openalex.org/").to_string()),
+            nlm_id: None,
+        })
+    }
+
     async fn get_author_list(&mut self, publication_id: &str) -> Vec<GenericAuthorInfo> {
         let work = match self.get_cached_publication_from_id(publication_id) {
             Some(w) => w.clone(),
@@ -211,14 +263,6 @@ impl ScientificPublicationAdapter for OpenAlex2Wikidata {
             })
             .collect()
     }
-
-    async fn update_statements_for_publication_id(
-        &self,
-        _publication_id: &str,
-        _item: &mut Entity,
-    ) {
-        // No extra statements beyond the defaults
-    }
 }
 
 #[cfg(test)]
@@ -337,6 +381,44 @@ mod tests {
         assert_eq!(adapter.get_work_issn("10.1234/TEST"), Some("1234-5678".to_string()));
     }
 
+    #[test]
+    fn test_get_work_issns_issn_l_first() {
+        let mut adapter = OpenAlex2Wikidata::default();
+        let mut work = make_work();
+        work["primary_location"]["source"]["issn"] = json!(["8765-4321", "1234-5678"]);
+        adapter.work_cache.insert("10.1234/TEST".to_string(), work);
+        assert_eq!(adapter.get_work_issns("10.1234/TEST"), vec!["1234-5678", "8765-4321"]);
+        assert!(adapter.get_work_issns("10.1234/NOPE").is_empty());
+    }
+
+    #[test]
+    fn test_get_journal_info() {
+        let mut adapter = OpenAlex2Wikidata::default();
+        let mut work = make_work();
+        work["primary_location"]["source"] = json!({
+            "display_name": "Test Journal",
+            "type": "journal",
+            "issn_l": "1234-5678",
+            "issn": ["1234-5678", "8765-4321"],
+            "host_organization": "https://openalex.org/P4310320990"
+        });
+        adapter.work_cache.insert("10.1234/TEST".to_string(), work);
+        let journal = adapter.get_journal_info("10.1234/TEST").unwrap();
+        assert_eq!(journal.title.as_deref(), Some("Test Journal"));
+        assert_eq!(journal.issn_l.as_deref(), Some("1234-5678"));
+        assert_eq!(journal.issns, vec!["1234-5678", "8765-4321"]);
+        assert_eq!(journal.publisher_openalex_id.as_deref(), Some("P4310320990"));
+    }
+
+    #[test]
+    fn test_get_journal_info_skips_repositories() {
+        let mut adapter = OpenAlex2Wikidata::default();
+        let mut work = make_work();
+        work["primary_location"]["source"]["type"] = json!("repository");
+        adapter.work_cache.insert("10.1234/TEST".to_string(), work);
+        assert_eq!(adapter.get_journal_info("10.1234/TEST"), None);
+    }
+
     #[tokio::test]
     async fn test_get_author_list() {
         let mut adapter = OpenAlex2Wikidata::default();
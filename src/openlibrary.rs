@@ -0,0 +1,311 @@
+//! Book metadata from the Open Library "books" API, used by the
+//! `CREATE_BOOK_FROM_ISBN` bot command.
+//!
+//! Open Library isn't a [`ScientificPublicationAdapter`] — books are
+//! keyed by ISBN, not by any of the paper identifiers the adapter
+//! pipeline resolves — so this module only fetches one record per ISBN
+//! and knows how to write it into a book item.
+//!
+//! [`ScientificPublicationAdapter`]: crate::scientific_publication_adapter::ScientificPublicationAdapter
+
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::{
+    adapter_helpers::{retrieved_today, strip_html_tags, wb_time_from_partial},
+    generic_author_info::GenericAuthorInfo,
+    http_client::{HttpJsonFetcher, JsonFetcher},
+    isbn::Isbn,
+    scientific_publication_adapter::WorkType,
+    *,
+};
+
+/// Q-item for Open Library
+const OPEN_LIBRARY: &str = "Q1201876";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookRecord {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub authors: Vec<String>,
+    pub publishers: Vec<String>,
+    pub publish_date: Option<String>,
+    pub number_of_pages: Option<u64>,
+    /// Open Library edition ID (P648), e.g. `OL7353617M`
+    pub edition_id: Option<String>,
+}
+
+impl BookRecord {
+    /// Builds a record from one entry of the `jscmd=data` response.
+    pub fn from_json(j: &serde_json::Value) -> Option<Self> {
+        let names = |key: &str| -> Vec<String> {
+            j[key]
+                .as_array()
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|x| x["name"].as_str())
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let ret = Self {
+            title: j["title"].as_str().map(strip_html_tags).filter(|s| !s.is_empty()),
+            subtitle: j["subtitle"].as_str().map(strip_html_tags).filter(|s| !s.is_empty()),
+            authors: names("authors"),
+            publishers: names("publishers"),
+            publish_date: j["publish_date"].as_str().map(|s| s.to_string()),
+            number_of_pages: j["number_of_pages"].as_u64(),
+            edition_id: j["key"]
+                .as_str()
+                .map(|s| s.trim_start_matches("/books/").to_string())
+                .filter(|s| !s.is_empty()),
+        };
+        if ret == Self::default() {
+            return None;
+        }
+        Some(ret)
+    }
+
+    /// Full title, with the subtitle appended after a colon.
+    pub fn full_title(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        match &self.subtitle {
+            Some(subtitle) => Some(format!("{title}: {subtitle}")),
+            None => Some(title.to_owned()),
+        }
+    }
+
+    /// Parses `publish_date`, which Open Library stores as free text
+    /// ("1994", "March 1994", "Mar 05, 1994", "1994-03-05").
+    pub fn publication_date(&self) -> Option<(u32, Option<u8>, Option<u8>)> {
+        lazy_static! {
+            static ref RE_YEAR: Regex = Regex::new(r"\b(\d{4})\b").expect("RE_YEAR");
+            static ref RE_DAY: Regex = Regex::new(r"\b(\d{1,2})\b").expect("RE_DAY");
+            static ref RE_WORD: Regex = Regex::new(r"[A-Za-z]{3,}").expect("RE_WORD");
+        }
+        const MONTHS: [&str; 12] =
+            ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+        let s = self.publish_date.as_ref()?.trim();
+        if let Some((year, month, day)) = adapter_helpers::parse_date(s) {
+            if s.contains('-') {
+                return Some((year, month, day));
+            }
+        }
+        let year: u32 = RE_YEAR.captures(s)?.get(1)?.as_str().parse().ok()?;
+        let month = RE_WORD.find_iter(s).find_map(|m| {
+            let prefix = m.as_str().to_lowercase();
+            MONTHS.iter().position(|name| prefix.starts_with(name)).map(|i| i as u8 + 1)
+        });
+        let day = match month {
+            Some(_) => RE_DAY
+                .captures(s)
+                .and_then(|c| c.get(1)?.as_str().parse::<u8>().ok())
+                .filter(|d| (1..=31).contains(d)),
+            None => None,
+        };
+        Some((year, month, day))
+    }
+
+    /// Reference for statements taken from this record: stated in (P248)
+    /// Open Library, the edition ID, and retrieved (P813) today.
+    pub fn reference(&self) -> Vec<Reference> {
+        let mut snaks = vec![Snak::new_item("P248", OPEN_LIBRARY)];
+        if let Some(id) = &self.edition_id {
+            snaks.push(Snak::new_external_id("P648", id));
+        }
+        snaks.push(retrieved_today());
+        vec![Reference::new(snaks)]
+    }
+
+    /// Adds whatever the item doesn't have yet: P31 (book), label and
+    /// P1476, authors as P2093 with P1545 ordinals, P577 and P1104.
+    /// Publisher (P123) needs a Wikidata lookup and is handled by the
+    /// caller.
+    pub fn amend_book_item(&self, item: &mut Entity) {
+        if !item.has_claims_with_property("P31") {
+            item.add_claim(Statement::new_normal(
+                Snak::new_item("P31", WorkType::Book.as_q()),
+                vec![],
+                vec![],
+            ));
+        }
+
+        if let Some(title) = self.full_title() {
+            if item.label_in_locale("en").is_none() {
+                item.set_label(LocaleString::new("en", &title));
+            }
+            if !item.has_claims_with_property("P1476") {
+                item.add_claim(Statement::new_normal(
+                    Snak::new_monolingual_text("P1476", &title, "en"),
+                    vec![],
+                    self.reference(),
+                ));
+            }
+        }
+
+        if !item.has_claims_with_property("P50") && !item.has_claims_with_property("P2093") {
+            for (num, name) in self.authors.iter().enumerate() {
+                GenericAuthorInfo::new_from_name_num(name, num + 1)
+                    .create_author_statement_in_paper_item(item);
+            }
+        }
+
+        if !item.has_claims_with_property("P577") {
+            if let Some((year, month, day)) = self.publication_date() {
+                item.add_claim(wb_time_from_partial("P577", year, month, day, self.reference()));
+            }
+        }
+
+        if !item.has_claims_with_property("P1104") {
+            if let Some(pages) = self.number_of_pages.filter(|p| *p > 0) {
+                item.add_claim(Statement::new_normal(
+                    Snak::new_quantity("P1104", pages as f64),
+                    vec![],
+                    self.reference(),
+                ));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenLibrary {
+    fetcher: Arc<dyn JsonFetcher>,
+}
+
+impl Default for OpenLibrary {
+    fn default() -> Self {
+        Self::new(Arc::new(HttpJsonFetcher::default()))
+    }
+}
+
+impl OpenLibrary {
+    pub fn new(fetcher: Arc<dyn JsonFetcher>) -> Self {
+        Self { fetcher }
+    }
+
+    /// Fetches the record for `isbn`. Returns `None` if Open Library
+    /// doesn't know the book or the request fails.
+    pub async fn get_book(&self, isbn: &Isbn) -> Option<BookRecord> {
+        let key = format!("ISBN:{}", isbn.isbn13());
+        let url = format!("https://openlibrary.org/api/books?bibkeys={key}&format=json&jscmd=data");
+        let j = self.fetcher.fetch_json(&url).await?;
+        BookRecord::from_json(&j[&key])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::MockJsonFetcher;
+
+    const URL: &str =
+        "https://openlibrary.org/api/books?bibkeys=ISBN:9780306406157&format=json&jscmd=data";
+
+    fn make_record() -> serde_json::Value {
+        json!({
+            "title": "Fundamentals of Testing",
+            "subtitle": "A Primer",
+            "authors": [{"name": "Alice Smith"}, {"name": "Bob Jones"}],
+            "publishers": [{"name": "Plenum Press"}],
+            "publish_date": "March 5, 1994",
+            "number_of_pages": 318,
+            "key": "/books/OL7353617M"
+        })
+    }
+
+    fn record_with_date(date: &str) -> BookRecord {
+        BookRecord { publish_date: Some(date.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn from_json_reads_fields() {
+        let r = BookRecord::from_json(&make_record()).unwrap();
+        assert_eq!(r.title.as_deref(), Some("Fundamentals of Testing"));
+        assert_eq!(r.full_title().as_deref(), Some("Fundamentals of Testing: A Primer"));
+        assert_eq!(r.authors, vec!["Alice Smith", "Bob Jones"]);
+        assert_eq!(r.publishers, vec!["Plenum Press"]);
+        assert_eq!(r.number_of_pages, Some(318));
+        assert_eq!(r.edition_id.as_deref(), Some("OL7353617M"));
+    }
+
+    #[test]
+    fn from_json_empty_is_none() {
+        assert!(BookRecord::from_json(&json!(null)).is_none());
+        assert!(BookRecord::from_json(&json!({})).is_none());
+    }
+
+    #[test]
+    fn publication_date_formats() {
+        assert_eq!(record_with_date("1994").publication_date(), Some((1994, None, None)));
+        assert_eq!(record_with_date("March 1994").publication_date(), Some((1994, Some(3), None)));
+        assert_eq!(
+            record_with_date("Mar 05, 1994").publication_date(),
+            Some((1994, Some(3), Some(5)))
+        );
+        assert_eq!(
+            record_with_date("1994-03-05").publication_date(),
+            Some((1994, Some(3), Some(5)))
+        );
+        assert_eq!(record_with_date("unknown").publication_date(), None);
+    }
+
+    #[test]
+    fn amend_book_item_adds_statements() {
+        let r = BookRecord::from_json(&make_record()).unwrap();
+        let mut item = Entity::new_empty_item();
+        r.amend_book_item(&mut item);
+        assert!(item.has_target_entity("P31", "Q571"));
+        assert_eq!(item.label_in_locale("en"), Some("Fundamentals of Testing: A Primer"));
+        assert!(item.has_claims_with_property("P1476"));
+        assert_eq!(item.claims().iter().filter(|s| s.property() == "P2093").count(), 2);
+        assert!(item.has_claims_with_property("P577"));
+        assert!(item.has_claims_with_property("P1104"));
+    }
+
+    #[test]
+    fn amend_book_item_adds_references() {
+        let r = BookRecord::from_json(&make_record()).unwrap();
+        let mut item = Entity::new_empty_item();
+        r.amend_book_item(&mut item);
+        let claim = |property: &str| item.claims().iter().find(|s| s.property() == property);
+        let snaks = claim("P1104").unwrap().references()[0].snaks();
+        let properties: Vec<&str> = snaks.iter().map(|snak| snak.property()).collect();
+        assert_eq!(properties, vec!["P248", "P648", "P813"]);
+        assert!(claim("P31").unwrap().references().is_empty());
+    }
+
+    #[test]
+    fn amend_book_item_keeps_existing_authors_and_type() {
+        let r = BookRecord::from_json(&make_record()).unwrap();
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(Snak::new_item("P31", "Q7725634"), vec![], vec![]));
+        item.add_claim(Statement::new_normal(Snak::new_item("P50", "Q42"), vec![], vec![]));
+        r.amend_book_item(&mut item);
+        assert!(!item.has_target_entity("P31", "Q571"));
+        assert!(!item.has_claims_with_property("P2093"));
+    }
+
+    #[tokio::test]
+    async fn get_book_hits_expected_url() {
+        let fetcher = Arc::new(MockJsonFetcher::new());
+        fetcher.add_response(URL, json!({"ISBN:9780306406157": make_record()}));
+        let ol = OpenLibrary::new(fetcher.clone());
+        let isbn = Isbn::parse("0-306-40615-2").unwrap();
+        let record = ol.get_book(&isbn).await.unwrap();
+        assert_eq!(record.title.as_deref(), Some("Fundamentals of Testing"));
+        assert_eq!(fetcher.captured_urls(), vec![URL.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn get_book_unknown_isbn_is_none() {
+        let fetcher = Arc::new(MockJsonFetcher::new());
+        fetcher.add_response(URL, json!({}));
+        let ol = OpenLibrary::new(fetcher);
+        let isbn = Isbn::parse("0-306-40615-2").unwrap();
+        assert!(ol.get_book(&isbn).await.is_none());
+    }
+}
@@ -67,7 +67,9 @@ impl Orcid2Wikidata {
         self.author_data.lock().await.get(orcid_author_id).and_then(|r| r.clone())
     }
 
-    async fn get_author_data(
+    /// Name and external IDs for an ORCID author, ready to be matched
+    /// against a paper's authors or written to an author item.
+    pub async fn get_author_data(
         &self,
         orcid_author_id: &str,
         author_property: &str,
@@ -140,6 +142,15 @@ impl ScientificPublicationAdapter for Orcid2Wikidata {
         "Orcid2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q51044") // ORCID
+    }
+
+    /// Works are looked up by DOI
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        Some((IdProp::DOI.as_str().to_string(), publication_id.to_string()))
+    }
+
     fn author_property(&self) -> Option<String> {
         Some("P496".to_string())
     }
@@ -163,13 +174,6 @@ impl ScientificPublicationAdapter for Orcid2Wikidata {
         Some(publication_id)
     }
 
-    async fn update_statements_for_publication_id(&self, publication_id: &str, _item: &mut Entity) {
-        let _work = match self.get_cached_publication_from_id(publication_id) {
-            Some(w) => w,
-            None => return,
-        };
-    }
-
     async fn get_author_list(&mut self, publication_id: &str) -> Vec<GenericAuthorInfo> {
         let work = match self.get_cached_publication_from_id(publication_id) {
             Some(w) => w.clone(),
@@ -269,16 +273,6 @@ mod tests {
         assert!(authors.is_empty());
     }
 
-    #[tokio::test]
-    async fn update_statements_for_unknown_publication_is_a_noop() {
-        let o = Orcid2Wikidata::new();
-        let mut item = wikibase::Entity::new_empty_item();
-        let before = item.claims().len();
-        // Calling on an unknown publication ID should not mutate the item
-        o.update_statements_for_publication_id("nonexistent", &mut item).await;
-        assert_eq!(item.claims().len(), before);
-    }
-
     // === P2-10b: SDK DI via base_url ======================================
 
     use wiremock::matchers::method as wm_method;
@@ -0,0 +1,201 @@
+//! On-disk backing for `WikidataStringCache`, so that identifier lookups
+//! are kept between runs instead of being searched for again.
+//!
+//! The file has one JSON object per line: `{"property", "key", "q", "time"}`
+//! for a lookup (`q` is `null` if nothing was found, `time` is in seconds
+//! since the epoch), or `{"property", "key", "removed": true}` once an entry
+//! is cleared. Later lines win. Expired entries are dropped, and the file is
+//! rewritten without them, when it is opened.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+
+/// How long an item found for an identifier is trusted
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 24 * 3600);
+/// How long "no item for this identifier" is trusted. Shorter than
+/// `DEFAULT_TTL`, since items for new papers are created all the time.
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoredValue {
+    q: Option<String>,
+    time: u64,
+}
+
+#[derive(Debug)]
+pub struct PersistentCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    entries: Mutex<HashMap<(String, String), StoredValue>>,
+    file: Mutex<File>,
+}
+
+impl PersistentCache {
+    /// Reads the entries in `path` that haven't expired, and compacts the
+    /// file. The file is created if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration, negative_ttl: Duration) -> Result<Self> {
+        let path = path.as_ref();
+        let mut entries = HashMap::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                // A run that was killed mid-write can leave a partial last line
+                let Ok(j) = serde_json::from_str::<serde_json::Value>(&line?) else {
+                    continue;
+                };
+                let (Some(property), Some(key)) = (j["property"].as_str(), j["key"].as_str())
+                else {
+                    continue;
+                };
+                let entry = (property.to_string(), key.to_string());
+                if j["removed"].as_bool() == Some(true) {
+                    entries.remove(&entry);
+                } else {
+                    let q = j["q"].as_str().map(|q| q.to_string());
+                    entries.insert(entry, StoredValue { q, time: j["time"].as_u64().unwrap_or(0) });
+                }
+            }
+        }
+
+        let now = now();
+        entries.retain(|_, value| !is_expired(value, now, ttl, negative_ttl));
+        let tmp_path = path.with_extension("tmp");
+        let mut tmp = BufWriter::new(File::create(&tmp_path)?);
+        for ((property, key), value) in &entries {
+            writeln!(tmp, "{}", entry_json(property, key, value))?;
+        }
+        tmp.flush()?;
+        drop(tmp);
+        std::fs::rename(&tmp_path, path)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self { ttl, negative_ttl, entries: Mutex::new(entries), file: Mutex::new(file) })
+    }
+
+    /// The item stored for `property`/`key`: `None` if there is no entry or
+    /// it has expired, `Some(None)` if no item was found last time.
+    pub fn get(&self, property: &str, key: &str) -> Option<Option<String>> {
+        let entries = self.entries.lock().ok()?;
+        let value = entries.get(&(property.to_string(), key.to_string()))?;
+        if is_expired(value, now(), self.ttl, self.negative_ttl) {
+            return None;
+        }
+        Some(value.q.to_owned())
+    }
+
+    /// Stores the result of a lookup, as of now.
+    pub fn set(&self, property: &str, key: &str, q: Option<String>) -> Result<()> {
+        let value = StoredValue { q, time: now() };
+        self.append(&entry_json(property, key, &value))?;
+        self.entries_mut()?.insert((property.to_string(), key.to_string()), value);
+        Ok(())
+    }
+
+    /// Forgets the lookup for `property`/`key`, if there is one.
+    pub fn remove(&self, property: &str, key: &str) -> Result<()> {
+        let entry = (property.to_string(), key.to_string());
+        if self.entries_mut()?.remove(&entry).is_some() {
+            self.append(&json!({"property": property, "key": key, "removed": true}))?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn entries_mut(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<(String, String), StoredValue>>> {
+        self.entries.lock().map_err(|_| anyhow!("persistent cache poisoned"))
+    }
+
+    /// Appends one line in a single write, so concurrent writers don't
+    /// interleave.
+    fn append(&self, j: &serde_json::Value) -> Result<()> {
+        let mut file = self.file.lock().map_err(|_| anyhow!("persistent cache file poisoned"))?;
+        file.write_all(format!("{j}\n").as_bytes())?;
+        Ok(())
+    }
+}
+
+fn entry_json(property: &str, key: &str, value: &StoredValue) -> serde_json::Value {
+    json!({"property": property, "key": key, "q": value.q, "time": value.time})
+}
+
+fn is_expired(value: &StoredValue, now: u64, ttl: Duration, negative_ttl: Duration) -> bool {
+    let ttl = match value.q {
+        Some(_) => ttl,
+        None => negative_ttl,
+    };
+    now.saturating_sub(value.time) >= ttl.as_secs()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn entries_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let cache = PersistentCache::open(&path, HOUR, HOUR).unwrap();
+        assert_eq!(cache.get("P356", "10.1/x"), None);
+        cache.set("P356", "10.1/x", Some("Q1".to_string())).unwrap();
+        cache.set("P698", "123", None).unwrap();
+        cache.set("P698", "456", Some("Q2".to_string())).unwrap();
+        cache.remove("P698", "456").unwrap();
+        drop(cache);
+
+        let cache = PersistentCache::open(&path, HOUR, HOUR).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("P356", "10.1/x"), Some(Some("Q1".to_string())));
+        assert_eq!(cache.get("P698", "123"), Some(None));
+        assert_eq!(cache.get("P698", "456"), None);
+    }
+
+    #[test]
+    fn negative_results_expire_separately() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let cache = PersistentCache::open(&path, HOUR, Duration::ZERO).unwrap();
+        cache.set("P356", "10.1/x", Some("Q1".to_string())).unwrap();
+        cache.set("P698", "123", None).unwrap();
+        assert_eq!(cache.get("P356", "10.1/x"), Some(Some("Q1".to_string())));
+        assert_eq!(cache.get("P698", "123"), None);
+    }
+
+    #[test]
+    fn open_drops_expired_and_broken_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let old = now() - 2 * HOUR.as_secs();
+        let lines = [
+            format!(r#"{{"property":"P356","key":"10.1/old","q":"Q1","time":{old}}}"#),
+            format!(r#"{{"property":"P356","key":"10.1/new","q":"Q2","time":{}}}"#, now()),
+            r#"{"property":"P356","key":"10.1/tor"#.to_string(),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let cache = PersistentCache::open(&path, HOUR, HOUR).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("P356", "10.1/new"), Some(Some("Q2".to_string())));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
     adapter_helpers::get_external_identifier_from_item,
     generic_author_info::GenericAuthorInfo,
     http_client::{HttpJsonFetcher, JsonFetcher},
+    key_normalization::normalize_value,
     scientific_publication_adapter::ScientificPublicationAdapter,
     *,
 };
@@ -111,6 +112,10 @@ impl ScientificPublicationAdapter for PMC2Wikidata {
         "PMC2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q229883") // PubMed Central
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -153,32 +158,24 @@ impl ScientificPublicationAdapter for PMC2Wikidata {
 
     fn publication_id_for_statement(&self, id: &str) -> Option<String> {
         if self.is_pmcid(id) {
-            Some(id.replace("PMC", "").to_string())
+            Some(normalize_value(IdProp::PMCID.as_str(), id))
         } else {
             None
         }
     }
 
     // Overriding default function
-    fn update_work_item_with_property(&self, publication_id: &str, item: &mut Entity) {
+    fn publication_identifier(&self, publication_id: &str) -> Option<(String, String)> {
         // The original guard was `publication_id[0..4] == "PMID_"`, which
         // panicked on inputs shorter than 4 bytes and never matched anyway
         // (4-byte slice vs. 5-byte literal). `starts_with` is the intended
         // check.
         if publication_id.starts_with("PMID_") {
-            return;
-        }
-        if let Some(prop) = self.publication_property() {
-            if !item.has_claims_with_property(prop.as_str()) {
-                if let Some(id) = self.publication_id_for_statement(publication_id) {
-                    item.add_claim(Statement::new_normal(
-                        Snak::new_external_id(prop.as_str(), &id),
-                        vec![],
-                        self.reference(),
-                    ))
-                }
-            }
+            return None;
         }
+        let prop = self.publication_property()?;
+        let id = self.publication_id_for_statement(publication_id)?;
+        Some((prop.as_str().to_string(), id))
     }
 
     async fn publication_id_from_item(&mut self, item: &Entity) -> Option<String> {
@@ -224,6 +221,19 @@ impl ScientificPublicationAdapter for PMC2Wikidata {
             .map(|s| s.to_string())
     }
 
+    /// Print ISSN first, then the electronic one (`essn`)
+    fn get_work_issns(&self, publication_id: &str) -> Vec<String> {
+        let journal = match self.get_cached_publication_from_id(publication_id) {
+            Some(work) => &work["journalInfo"]["journal"],
+            None => return vec![],
+        };
+        ["issn", "essn"]
+            .iter()
+            .filter_map(|key| journal[*key].as_str())
+            .map(|s| s.to_string())
+            .collect()
+    }
+
     fn get_publication_date(&self, publication_id: &str) -> Option<(u32, Option<u8>, Option<u8>)> {
         let journal_info = &self.get_cached_publication_from_id(publication_id)?["journalInfo"];
         let year = journal_info["yearOfPublication"].as_u64()? as u32;
@@ -266,13 +276,6 @@ impl ScientificPublicationAdapter for PMC2Wikidata {
         ret
     }
 
-    async fn update_statements_for_publication_id(&self, publication_id: &str, _item: &mut Entity) {
-        let _work = match self.get_cached_publication_from_id(publication_id) {
-            Some(w) => w,
-            None => return,
-        };
-    }
-
     // Not sure what this does
     async fn do_cache_work(&mut self, _publication_id: &str) -> Option<String> {
         None
@@ -443,6 +446,16 @@ mod tests {
         assert_eq!(pmc.get_work_issn("PMC1"), None);
     }
 
+    #[test]
+    fn get_work_issns_print_before_electronic() {
+        let pmc = make_pmc("PMC1", json!({"journalInfo": {"journal": {"essn": "8765-4321"}}}));
+        assert_eq!(pmc.get_work_issns("PMC1"), vec!["8765-4321"]);
+        let journal =
+            json!({"journalInfo": {"journal": {"essn": "8765-4321", "issn": "1234-5678"}}});
+        let pmc = make_pmc("PMC1", journal);
+        assert_eq!(pmc.get_work_issns("PMC1"), vec!["1234-5678", "8765-4321"]);
+    }
+
     // === get_publication_date ===
 
     #[test]
@@ -499,44 +512,37 @@ mod tests {
         assert_eq!(pmc.publication_property(), Some(IdProp::PMCID));
     }
 
-    // === update_work_item_with_property — regression tests for the
+    // === publication_identifier — regression tests for the
     //     `publication_id[0..4] == "PMID_"` slice bug (would panic on
     //     inputs shorter than 4 bytes; the guard was also dead code
     //     because "PMID_" is 5 bytes).
 
     #[test]
-    fn update_work_item_with_property_does_not_panic_on_short_input() {
+    fn publication_identifier_does_not_panic_on_short_input() {
         let pmc = PMC2Wikidata::default();
-        let mut item = wikibase::Entity::new_empty_item();
         // "PMC" is 3 bytes — pre-fix this slice indexing panics.
-        pmc.update_work_item_with_property("PMC", &mut item);
-        // Not a valid PMCID, so no claim should be added either.
-        assert!(!item.has_claims_with_property("P932"));
+        // Not a valid PMCID, so there is no identifier either.
+        assert_eq!(pmc.publication_identifier("PMC"), None);
     }
 
     #[test]
-    fn update_work_item_with_property_does_not_panic_on_empty_input() {
+    fn publication_identifier_does_not_panic_on_empty_input() {
         let pmc = PMC2Wikidata::default();
-        let mut item = wikibase::Entity::new_empty_item();
-        pmc.update_work_item_with_property("", &mut item);
-        assert!(!item.has_claims_with_property("P932"));
+        assert_eq!(pmc.publication_identifier(""), None);
     }
 
     #[test]
-    fn update_work_item_with_property_adds_pmcid_claim() {
+    fn publication_identifier_is_the_pmcid() {
         let pmc = PMC2Wikidata::default();
-        let mut item = wikibase::Entity::new_empty_item();
-        pmc.update_work_item_with_property("PMC12345", &mut item);
-        assert!(item.has_claims_with_property("P932"));
+        let expected = Some(("P932".to_string(), "12345".to_string()));
+        assert_eq!(pmc.publication_identifier("PMC12345"), expected);
     }
 
     #[test]
-    fn update_work_item_with_property_skips_pmid_prefixed_id() {
+    fn publication_identifier_skips_pmid_prefixed_id() {
         let pmc = PMC2Wikidata::default();
-        let mut item = wikibase::Entity::new_empty_item();
         // The guard's intent: pass-through for PubMed IDs handled elsewhere.
-        pmc.update_work_item_with_property("PMID_999", &mut item);
-        assert!(!item.has_claims_with_property("P932"));
+        assert_eq!(pmc.publication_identifier("PMID_999"), None);
     }
 
     // === HTTP-injected tests (P2-10) =======================================
@@ -0,0 +1,818 @@
+//! Publication metadata collected from all adapters before it is written to
+//! an item.
+//!
+//! Each adapter contributes a [`PublicationRecord`] in which every value is
+//! [`Sourced`]: tagged with the adapter that reported it and the references
+//! for statements made from it. The records of all adapters are merged into
+//! one, which then holds every candidate value per field. A [`Precedence`]
+//! decides which candidate wins; candidates that agree with the winner add
+//! their references to the statement, the others are logged as conflicts.
+
+use std::collections::HashMap;
+
+use crate::{
+    adapter_helpers::{
+        add_or_merge_monolingual_statement, add_or_merge_statement, strip_html_tags,
+        titles_are_equal, wb_time_from_partial, UNDETERMINED_LANGUAGE,
+    },
+    generic_author_info::GenericAuthorInfo,
+    wikidata_string_cache::WikidataStringCache,
+    *,
+};
+
+/// Year, month and day, as far as known.
+pub type WorkDate = (u32, Option<u8>, Option<u8>);
+
+/// Adapter names in the order `WikidataPapers::with_default_adapters`
+/// registers them; the fallback order for every field.
+pub const DEFAULT_SOURCE_ORDER: &[&str] = &[
+    "PMC2Wikidata",
+    "Pubmed2Wikidata",
+    "Crossref2Wikidata",
+    "Semanticscholar2Wikidata",
+    "Orcid2Wikidata",
+    "Arxiv2Wikidata",
+    "OpenAlex2Wikidata",
+    "DataCite2Wikidata",
+    "EuropePMC2Wikidata",
+];
+
+/// A value as reported by one adapter.
+#[derive(Debug, Clone)]
+pub struct Sourced<T> {
+    pub value: T,
+    /// Name of the adapter that reported the value
+    pub source: String,
+    /// References for statements made from the value
+    pub references: Vec<Reference>,
+}
+
+impl<T> Sourced<T> {
+    pub fn new(value: T, source: &str, references: Vec<Reference>) -> Self {
+        Self { value, source: source.to_string(), references }
+    }
+
+    /// All `values`, from the same source.
+    pub fn all(
+        values: impl IntoIterator<Item = T>,
+        source: &str,
+        references: &[Reference],
+    ) -> Vec<Self> {
+        values.into_iter().map(|value| Self::new(value, source, references.to_vec())).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordField {
+    Identifier,
+    Title,
+    PublicationDate,
+    Volume,
+    Issue,
+    Pages,
+    WorkType,
+    Issn,
+    Language,
+    Authors,
+}
+
+impl RecordField {
+    pub const ALL: [RecordField; 10] = [
+        RecordField::Identifier,
+        RecordField::Title,
+        RecordField::PublicationDate,
+        RecordField::Volume,
+        RecordField::Issue,
+        RecordField::Pages,
+        RecordField::WorkType,
+        RecordField::Issn,
+        RecordField::Language,
+        RecordField::Authors,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordField::Identifier => "identifier",
+            RecordField::Title => "title",
+            RecordField::PublicationDate => "publication_date",
+            RecordField::Volume => "volume",
+            RecordField::Issue => "issue",
+            RecordField::Pages => "pages",
+            RecordField::WorkType => "work_type",
+            RecordField::Issn => "issn",
+            RecordField::Language => "language",
+            RecordField::Authors => "authors",
+        }
+    }
+
+    /// The property the field is written to, if it maps to one directly.
+    pub fn property(&self) -> Option<&'static str> {
+        match self {
+            RecordField::Title => Some("P1476"),
+            RecordField::PublicationDate => Some("P577"),
+            RecordField::Volume => Some("P478"),
+            RecordField::Issue => Some("P433"),
+            RecordField::Pages => Some("P304"),
+            RecordField::WorkType => Some("P31"),
+            RecordField::Language => Some("P407"),
+            RecordField::Identifier | RecordField::Issn | RecordField::Authors => None,
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.as_str() == s)
+    }
+}
+
+/// Which source wins, per field. Sources that are not listed for a field
+/// rank after the listed ones, in the order their values were collected.
+#[derive(Debug, Clone)]
+pub struct Precedence {
+    order: HashMap<RecordField, Vec<String>>,
+}
+
+impl Default for Precedence {
+    /// [`DEFAULT_SOURCE_ORDER`] for every field, except that the work type is
+    /// taken from Crossref and DataCite first (they have the most specific
+    /// types), and the language from PubMed.
+    fn default() -> Self {
+        let mut ret = Self { order: HashMap::new() };
+        for field in RecordField::ALL {
+            ret.set_order(field, DEFAULT_SOURCE_ORDER);
+        }
+        ret.set_order(RecordField::WorkType, &["Crossref2Wikidata", "DataCite2Wikidata"]);
+        ret.set_order(RecordField::Language, &["Pubmed2Wikidata"]);
+        ret
+    }
+}
+
+impl Precedence {
+    /// Sets the sources for `field`, most trusted first.
+    pub fn set_order(&mut self, field: RecordField, sources: &[&str]) {
+        self.order.insert(field, sources.iter().map(|s| s.to_string()).collect());
+    }
+
+    pub fn with_order(mut self, field: RecordField, sources: &[&str]) -> Self {
+        self.set_order(field, sources);
+        self
+    }
+
+    pub fn order(&self, field: RecordField) -> &[String] {
+        self.order.get(&field).map(|v| v.as_slice()).unwrap_or_default()
+    }
+
+    fn rank(&self, field: RecordField, source: &str) -> usize {
+        let order = self.order(field);
+        order.iter().position(|s| s == source).unwrap_or(order.len())
+    }
+
+    /// `candidates` sorted by precedence, best first.
+    pub fn sorted<'a, T>(
+        &self,
+        field: RecordField,
+        candidates: &'a [Sourced<T>],
+    ) -> Vec<&'a Sourced<T>> {
+        let mut ret: Vec<&Sourced<T>> = candidates.iter().collect();
+        ret.sort_by_key(|c| self.rank(field, &c.source)); // Stable
+        ret
+    }
+}
+
+/// What one or more adapters report about a publication.
+#[derive(Debug, Clone, Default)]
+pub struct PublicationRecord {
+    /// Property and normalized value of the record each source was read
+    /// from, e.g. `("P698", "123")`
+    pub identifiers: Vec<Sourced<(String, String)>>,
+    pub titles: Vec<Sourced<LocaleString>>,
+    pub publication_dates: Vec<Sourced<WorkDate>>,
+    pub volumes: Vec<Sourced<String>>,
+    pub issues: Vec<Sourced<String>>,
+    pub pages: Vec<Sourced<String>>,
+    /// Q-items for P31
+    pub work_types: Vec<Sourced<String>>,
+    /// All ISSNs one source knows for the journal, ISSN-L first
+    pub issns: Vec<Sourced<Vec<String>>>,
+    /// Q-items for P407
+    pub languages: Vec<Sourced<String>>,
+    pub authors: Vec<Sourced<Vec<GenericAuthorInfo>>>,
+}
+
+impl PublicationRecord {
+    /// Adds all candidate values from `other`.
+    pub fn merge(&mut self, other: PublicationRecord) {
+        self.identifiers.extend(other.identifiers);
+        self.titles.extend(other.titles);
+        self.publication_dates.extend(other.publication_dates);
+        self.volumes.extend(other.volumes);
+        self.issues.extend(other.issues);
+        self.pages.extend(other.pages);
+        self.work_types.extend(other.work_types);
+        self.issns.extend(other.issns);
+        self.languages.extend(other.languages);
+        self.authors.extend(other.authors);
+    }
+
+    /// The winning value for a single-valued field, with the references of
+    /// every candidate that reports the same value.
+    pub fn agreed<'a, T: PartialEq + std::fmt::Debug>(
+        field: RecordField,
+        candidates: &'a [Sourced<T>],
+        precedence: &Precedence,
+    ) -> Option<(&'a T, Vec<Reference>)> {
+        let sorted = precedence.sorted(field, candidates);
+        let best = sorted.first()?;
+        let mut references = vec![];
+        for candidate in &sorted {
+            if candidate.value == best.value {
+                references.extend(candidate.references.iter().cloned());
+            } else {
+                tracing::info!(
+                    field = field.as_str(),
+                    chosen = ?best.value,
+                    chosen_source = best.source,
+                    other = ?candidate.value,
+                    other_source = candidate.source,
+                    "conflicting values"
+                );
+            }
+        }
+        Some((&best.value, references))
+    }
+
+    /// The work type (P31) to use, if any source knows it.
+    pub fn work_type(&self, precedence: &Precedence) -> Option<&str> {
+        let sorted = precedence.sorted(RecordField::WorkType, &self.work_types);
+        sorted.first().map(|c| c.value.as_str())
+    }
+
+    /// Author lists of all sources, best first.
+    pub fn author_lists(&self, precedence: &Precedence) -> Vec<&Vec<GenericAuthorInfo>> {
+        precedence
+            .sorted(RecordField::Authors, &self.authors)
+            .into_iter()
+            .map(|c| &c.value)
+            .collect()
+    }
+
+    /// Names of the adapters that reported anything, in the order they were
+    /// merged.
+    pub fn sources(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = vec![];
+        let sources = (self.identifiers.iter().map(|c| &c.source))
+            .chain(self.titles.iter().map(|c| &c.source))
+            .chain(self.publication_dates.iter().map(|c| &c.source))
+            .chain(self.volumes.iter().map(|c| &c.source))
+            .chain(self.issues.iter().map(|c| &c.source))
+            .chain(self.pages.iter().map(|c| &c.source))
+            .chain(self.work_types.iter().map(|c| &c.source))
+            .chain(self.issns.iter().map(|c| &c.source))
+            .chain(self.languages.iter().map(|c| &c.source))
+            .chain(self.authors.iter().map(|c| &c.source));
+        for source in sources {
+            if !ret.contains(&source.as_str()) {
+                ret.push(source);
+            }
+        }
+        ret
+    }
+
+    /// The fields other than titles, ISSNs and authors, with their values
+    /// as strings (dates as `YYYY-MM-DD`, or less precise).
+    pub fn single_valued_fields(&self) -> Vec<(RecordField, Vec<Sourced<String>>)> {
+        let date = |d: &WorkDate| format_date(*d);
+        vec![
+            (RecordField::PublicationDate, rendered(&self.publication_dates, date)),
+            (RecordField::Volume, rendered(&self.volumes, String::to_owned)),
+            (RecordField::Issue, rendered(&self.issues, String::to_owned)),
+            (RecordField::Pages, rendered(&self.pages, String::to_owned)),
+            (RecordField::WorkType, rendered(&self.work_types, String::to_owned)),
+            (RecordField::Language, rendered(&self.languages, String::to_owned)),
+        ]
+    }
+
+    /// The record as JSON: every title and ISSN list, and for the other
+    /// fields (except authors) the winning value with the sources that
+    /// agree on it.
+    pub fn to_json(&self, precedence: &Precedence) -> serde_json::Value {
+        let titles: Vec<serde_json::Value> = self
+            .titles
+            .iter()
+            .map(|c| {
+                json!({"language": c.value.language(), "value": c.value.value(), "source": c.source})
+            })
+            .collect();
+        let issns: Vec<serde_json::Value> = precedence
+            .sorted(RecordField::Issn, &self.issns)
+            .into_iter()
+            .map(|c| json!({"value": c.value, "source": c.source}))
+            .collect();
+        let mut ret = json!({"titles": titles, "issns": issns});
+        for (field, candidates) in self.single_valued_fields() {
+            ret[field.as_str()] = chosen_json(field, &candidates, precedence);
+        }
+        ret
+    }
+
+    /// Writes labels and statements for everything except the work type and
+    /// authors into `item`. Existing statements with a different value are
+    /// left alone.
+    pub async fn apply_to_item(
+        &self,
+        item: &mut Entity,
+        precedence: &Precedence,
+        cache: &WikidataStringCache,
+    ) {
+        self.apply_identifiers(item, precedence);
+        self.apply_titles(item, precedence);
+        self.apply_journal(item, precedence, cache).await;
+        let strings = [
+            (RecordField::Volume, "P478", &self.volumes),
+            (RecordField::Issue, "P433", &self.issues),
+            (RecordField::Pages, "P304", &self.pages),
+        ];
+        for (field, property, candidates) in strings {
+            if let Some((value, references)) = Self::agreed(field, candidates, precedence) {
+                let snak = Snak::new_string(property, value);
+                add_or_merge_statement(item, Statement::new_normal(snak, vec![], references));
+            }
+        }
+        let dates = &self.publication_dates;
+        if let Some((date, references)) =
+            Self::agreed(RecordField::PublicationDate, dates, precedence)
+        {
+            let (year, month, day) = *date;
+            add_or_merge_statement(
+                item,
+                wb_time_from_partial("P577", year, month, day, references),
+            );
+        }
+        if !item.has_claims_with_property("P407") {
+            if let Some((q, references)) =
+                Self::agreed(RecordField::Language, &self.languages, precedence)
+            {
+                item.add_claim(Statement::new_normal(
+                    Snak::new_item("P407", q),
+                    vec![],
+                    references,
+                ));
+            }
+        }
+    }
+
+    /// Adds one statement per identifier property, with the best value for
+    /// it and the references of the sources that agree.
+    fn apply_identifiers(&self, item: &mut Entity, precedence: &Precedence) {
+        let mut by_property: Vec<(&str, Vec<Sourced<String>>)> = vec![];
+        for c in &self.identifiers {
+            let (property, value) = &c.value;
+            let id = Sourced::new(value.to_owned(), &c.source, c.references.clone());
+            match by_property.iter_mut().find(|(p, _)| p == property) {
+                Some((_, ids)) => ids.push(id),
+                None => by_property.push((property, vec![id])),
+            }
+        }
+        for (property, candidates) in by_property {
+            if let Some((value, references)) =
+                Self::agreed(RecordField::Identifier, &candidates, precedence)
+            {
+                let snak = Snak::new_external_id(property, value);
+                add_or_merge_statement(item, Statement::new_normal(snak, vec![], references));
+            }
+        }
+    }
+
+    /// Sets the label and P1476 per language, from the best title in that
+    /// language. A title in an unknown language can't be a label; it only
+    /// becomes P1476.
+    fn apply_titles(&self, item: &mut Entity, precedence: &Precedence) {
+        // Group by language, stripping HTML tags from title values (APIs
+        // like Crossref and PubMed may return titles with <i>, <b>, <sub>,
+        // <sup> etc.)
+        let mut by_lang: Vec<(&str, Vec<Sourced<String>>)> = vec![];
+        for t in &self.titles {
+            let language = t.value.language();
+            let title =
+                Sourced::new(strip_html_tags(t.value.value()), &t.source, t.references.clone());
+            match by_lang.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, titles)) => titles.push(title),
+                None => by_lang.push((language, vec![title])),
+            }
+        }
+        for (language, candidates) in by_lang {
+            let sorted = precedence.sorted(RecordField::Title, &candidates);
+            let best = match sorted.first() {
+                Some(best) => best.value.to_owned(),
+                None => continue,
+            };
+            let title = if language == UNDETERMINED_LANGUAGE {
+                best
+            } else {
+                match item.label_in_locale(language) {
+                    Some(label) => label.to_owned(),
+                    None => {
+                        item.set_label(LocaleString::new(language, &best));
+                        best
+                    },
+                }
+            };
+            let references: Vec<Reference> = sorted
+                .iter()
+                .filter(|c| titles_are_equal(&c.value, &title))
+                .flat_map(|c| c.references.iter().cloned())
+                .collect();
+            add_or_merge_monolingual_statement(
+                item,
+                Statement::new_normal(
+                    Snak::new_monolingual_text("P1476", &title, language),
+                    vec![],
+                    references,
+                ),
+            );
+        }
+    }
+
+    /// Adds P1433 for the first source, by precedence, whose ISSNs resolve
+    /// to a journal item.
+    async fn apply_journal(
+        &self,
+        item: &mut Entity,
+        precedence: &Precedence,
+        cache: &WikidataStringCache,
+    ) {
+        if item.has_claims_with_property("P1433") {
+            return;
+        }
+        for candidate in precedence.sorted(RecordField::Issn, &self.issns) {
+            if let Some(q) = cache.issn2q(&candidate.value).await {
+                item.add_claim(Statement::new_normal(
+                    Snak::new_item("P1433", &q),
+                    vec![],
+                    candidate.references.clone(),
+                ));
+                return;
+            }
+        }
+    }
+}
+
+fn rendered<T>(candidates: &[Sourced<T>], render: impl Fn(&T) -> String) -> Vec<Sourced<String>> {
+    candidates
+        .iter()
+        .map(|c| Sourced::new(render(&c.value), &c.source, c.references.clone()))
+        .collect()
+}
+
+/// `{"value": …, "sources": […]}` for the winning candidate and those that
+/// agree with it, or `null`.
+fn chosen_json(
+    field: RecordField,
+    candidates: &[Sourced<String>],
+    precedence: &Precedence,
+) -> serde_json::Value {
+    let sorted = precedence.sorted(field, candidates);
+    let Some(best) = sorted.first() else {
+        return serde_json::Value::Null;
+    };
+    let sources: Vec<&str> =
+        sorted.iter().filter(|c| c.value == best.value).map(|c| c.source.as_str()).collect();
+    json!({"value": best.value, "sources": sources})
+}
+
+/// `2020-03-05`, `2020-03` or `2020`, depending on what is known.
+pub fn format_date((year, month, day): WorkDate) -> String {
+    match (month, day) {
+        (Some(month), Some(day)) => format!("{year}-{month:02}-{day:02}"),
+        (Some(month), None) => format!("{year}-{month:02}"),
+        _ => format!("{year}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wikibase::mediawiki::api::Api;
+    use wiremock::MockServer;
+
+    use super::*;
+    use crate::test_helpers::{add_search_mock, start_mediawiki_mock_server as start_mock_server};
+
+    const SEARCH_Q15757256: &str = include_str!("../test_data/search_found_q15757256.json");
+    const SEARCH_MULTIPLE: &str = include_str!("../test_data/search_found_multiple.json");
+
+    fn reference(source: &str) -> Vec<Reference> {
+        vec![Reference::new(vec![Snak::new_item("P248", source)])]
+    }
+
+    fn sourced<T>(value: T, source: &str) -> Sourced<T> {
+        Sourced::new(value, source, reference(source))
+    }
+
+    fn title(language: &str, value: &str, source: &str) -> Sourced<LocaleString> {
+        sourced(LocaleString::new(language, value), source)
+    }
+
+    fn record_with_titles(titles: Vec<(&str, &str)>) -> PublicationRecord {
+        PublicationRecord {
+            titles: titles.into_iter().map(|(l, t)| title(l, t, "Q1")).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn has_title(item: &Entity, title: &str, language: &str) -> bool {
+        let snak = Snak::new_monolingual_text("P1476", title, language);
+        item.claims().iter().any(|s| s.main_snak().data_value() == snak.data_value())
+    }
+
+    fn claim<'a>(item: &'a Entity, property: &str) -> &'a Statement {
+        item.claims().iter().find(|s| s.property() == property).unwrap()
+    }
+
+    async fn mock_cache(mock_server: &MockServer) -> WikidataStringCache {
+        let api = Api::new(&mock_server.uri()).await.unwrap();
+        WikidataStringCache::new(Arc::new(tokio::sync::RwLock::new(api)))
+    }
+
+    // === Precedence ===
+
+    #[test]
+    fn precedence_default_follows_registration_order() {
+        let candidates = vec![sourced("1", "OpenAlex2Wikidata"), sourced("2", "Pubmed2Wikidata")];
+        let sorted = Precedence::default().sorted(RecordField::Volume, &candidates);
+        assert_eq!(sorted[0].source, "Pubmed2Wikidata");
+    }
+
+    #[test]
+    fn precedence_unlisted_sources_keep_collection_order() {
+        let candidates = vec![sourced("1", "b"), sourced("2", "a"), sourced("3", "listed")];
+        let precedence = Precedence::default().with_order(RecordField::Volume, &["listed"]);
+        let sorted = precedence.sorted(RecordField::Volume, &candidates);
+        let sources: Vec<&str> = sorted.iter().map(|c| c.source.as_str()).collect();
+        assert_eq!(sources, vec!["listed", "b", "a"]);
+    }
+
+    #[test]
+    fn precedence_work_type_prefers_crossref() {
+        let record = PublicationRecord {
+            work_types: vec![
+                sourced("Q13442814".to_string(), "PMC2Wikidata"),
+                sourced("Q571".to_string(), "Crossref2Wikidata"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(record.work_type(&Precedence::default()), Some("Q571"));
+    }
+
+    #[test]
+    fn record_field_round_trips_through_str() {
+        for field in RecordField::ALL {
+            assert_eq!(RecordField::from_name(field.as_str()), Some(field));
+        }
+        assert_eq!(RecordField::from_name("nope"), None);
+    }
+
+    // === agreed ===
+
+    #[test]
+    fn agreed_collects_references_of_agreeing_sources() {
+        let candidates = vec![sourced(1, "a"), sourced(2, "b"), sourced(1, "c")];
+        let precedence = Precedence::default().with_order(RecordField::Issue, &["c"]);
+        let (value, references) =
+            PublicationRecord::agreed(RecordField::Issue, &candidates, &precedence).unwrap();
+        assert_eq!(*value, 1);
+        assert_eq!(references.len(), 2);
+    }
+
+    #[test]
+    fn to_json_shows_winner_and_agreeing_sources() {
+        let record = PublicationRecord {
+            volumes: vec![
+                sourced("13".to_string(), "Crossref2Wikidata"),
+                sourced("12".to_string(), "Pubmed2Wikidata"),
+                sourced("12".to_string(), "PMC2Wikidata"),
+            ],
+            publication_dates: vec![sourced((2020, Some(3), None), "Crossref2Wikidata")],
+            ..Default::default()
+        };
+        let j = record.to_json(&Precedence::default());
+        assert_eq!(
+            j["volume"],
+            json!({"value": "12", "sources": ["PMC2Wikidata", "Pubmed2Wikidata"]})
+        );
+        assert_eq!(j["publication_date"]["value"], "2020-03");
+        assert!(j["issue"].is_null());
+        assert_eq!(record.sources(), vec!["Crossref2Wikidata", "Pubmed2Wikidata", "PMC2Wikidata"]);
+    }
+
+    #[test]
+    fn format_date_uses_known_precision() {
+        assert_eq!(format_date((2020, Some(3), Some(5))), "2020-03-05");
+        assert_eq!(format_date((2020, Some(3), None)), "2020-03");
+        assert_eq!(format_date((2020, None, None)), "2020");
+    }
+
+    #[test]
+    fn agreed_without_candidates_is_none() {
+        let candidates: Vec<Sourced<u8>> = vec![];
+        let precedence = Precedence::default();
+        assert!(PublicationRecord::agreed(RecordField::Issue, &candidates, &precedence).is_none());
+    }
+
+    // === apply_to_item ===
+
+    #[tokio::test]
+    async fn apply_to_item_uses_precedence_not_collection_order() {
+        let mock_server = start_mock_server().await;
+        let cache = mock_cache(&mock_server).await;
+        let record = PublicationRecord {
+            volumes: vec![sourced("12".to_string(), "a"), sourced("13".to_string(), "b")],
+            publication_dates: vec![
+                sourced((2020, Some(3), None), "a"),
+                sourced((2020, Some(3), Some(5)), "b"),
+            ],
+            ..Default::default()
+        };
+        let precedence = Precedence::default()
+            .with_order(RecordField::Volume, &["b", "a"])
+            .with_order(RecordField::PublicationDate, &["b", "a"]);
+        let mut item = Entity::new_empty_item();
+        record.apply_to_item(&mut item, &precedence, &cache).await;
+        let volume = Snak::new_string("P478", "13");
+        assert_eq!(claim(&item, "P478").main_snak().data_value(), volume.data_value());
+        assert_eq!(item.claims().iter().filter(|s| s.property() == "P478").count(), 1);
+        let date = wb_time_from_partial("P577", 2020, Some(3), Some(5), vec![]);
+        assert_eq!(claim(&item, "P577").main_snak().data_value(), date.main_snak().data_value());
+    }
+
+    #[tokio::test]
+    async fn apply_to_item_keeps_existing_language() {
+        let mock_server = start_mock_server().await;
+        let cache = mock_cache(&mock_server).await;
+        let record = PublicationRecord {
+            languages: vec![sourced("Q188".to_string(), "a")],
+            ..Default::default()
+        };
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(Snak::new_item("P407", "Q1860"), vec![], vec![]));
+        record.apply_to_item(&mut item, &Precedence::default(), &cache).await;
+        assert!(item.has_target_entity("P407", "Q1860"));
+        assert!(!item.has_target_entity("P407", "Q188"));
+    }
+
+    #[test]
+    fn identifiers_follow_precedence_per_property() {
+        let id = |property: &str, value: &str| (property.to_string(), value.to_string());
+        let record = PublicationRecord {
+            identifiers: vec![
+                sourced(id("P356", "10.1/A"), "a"),
+                sourced(id("P356", "10.1/B"), "b"),
+                sourced(id("P698", "123"), "a"),
+                sourced(id("P698", "123"), "b"),
+            ],
+            ..Default::default()
+        };
+        let precedence = Precedence::default().with_order(RecordField::Identifier, &["b", "a"]);
+        let mut item = Entity::new_empty_item();
+        record.apply_identifiers(&mut item, &precedence);
+        assert_eq!(item.claims().len(), 2);
+        let doi = Snak::new_external_id("P356", "10.1/B");
+        assert_eq!(claim(&item, "P356").main_snak().data_value(), doi.data_value());
+        assert_eq!(claim(&item, "P356").references().len(), 1);
+        assert_eq!(claim(&item, "P698").references().len(), 2);
+    }
+
+    // === titles (HTML tags: issue #7) ===
+
+    #[test]
+    fn apply_titles_strips_html_from_label() {
+        let record = record_with_titles(vec![(
+            "en",
+            "Correction: <i>Accidental aspiration of a solid tablet of sodium hydroxide</i>",
+        )]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(
+            item.label_in_locale("en"),
+            Some("Correction: Accidental aspiration of a solid tablet of sodium hydroxide"),
+        );
+    }
+
+    #[test]
+    fn apply_titles_strips_html_from_species_names() {
+        let record = record_with_titles(vec![(
+            "en",
+            "Population genetics of <i>Drosophila melanogaster</i> in tropical environments",
+        )]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(
+            item.label_in_locale("en"),
+            Some("Population genetics of Drosophila melanogaster in tropical environments"),
+        );
+    }
+
+    #[test]
+    fn apply_titles_plain_text_unaffected() {
+        let record = record_with_titles(vec![("en", "A plain text title")]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(item.label_in_locale("en"), Some("A plain text title"));
+    }
+
+    #[test]
+    fn apply_titles_uses_title_language() {
+        let record = record_with_titles(vec![("de", "Ein deutscher Titel")]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(item.label_in_locale("de"), Some("Ein deutscher Titel"));
+        assert_eq!(item.label_in_locale("en"), None);
+        assert!(has_title(&item, "Ein deutscher Titel", "de"));
+    }
+
+    #[test]
+    fn apply_titles_original_and_translated() {
+        let record =
+            record_with_titles(vec![("en", "A German title"), ("de", "Ein deutscher Titel")]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(item.label_in_locale("en"), Some("A German title"));
+        assert_eq!(item.label_in_locale("de"), Some("Ein deutscher Titel"));
+        assert!(has_title(&item, "A German title", "en"));
+        assert!(has_title(&item, "Ein deutscher Titel", "de"));
+    }
+
+    #[test]
+    fn apply_titles_undetermined_language_is_not_a_label() {
+        let record = record_with_titles(vec![
+            ("en", "An English title"),
+            (UNDETERMINED_LANGUAGE, "Un titre original"),
+        ]);
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(item.label_in_locale(UNDETERMINED_LANGUAGE), None);
+        assert!(has_title(&item, "Un titre original", UNDETERMINED_LANGUAGE));
+        assert!(has_title(&item, "An English title", "en"));
+    }
+
+    #[test]
+    fn apply_titles_merges_references_of_equal_titles() {
+        let record = PublicationRecord {
+            titles: vec![
+                title("en", "A title", "a"),
+                title("en", "A title.", "b"),
+                title("en", "Another title", "c"),
+            ],
+            ..Default::default()
+        };
+        let mut item = Entity::new_empty_item();
+        record.apply_titles(&mut item, &Precedence::default());
+        assert_eq!(item.label_in_locale("en"), Some("A title"));
+        assert_eq!(claim(&item, "P1476").references().len(), 2);
+    }
+
+    // === journal ===
+
+    #[tokio::test]
+    async fn apply_journal_adds_resolved_journal() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1351-5101", SEARCH_Q15757256).await;
+        let cache = mock_cache(&mock_server).await;
+        let record = PublicationRecord {
+            issns: vec![sourced(vec!["1351-5101".to_string()], "a")],
+            ..Default::default()
+        };
+        let mut item = Entity::new_empty_item();
+        record.apply_journal(&mut item, &Precedence::default(), &cache).await;
+        assert!(item.has_target_entity("P1433", "Q15757256"));
+        assert_eq!(item.claims().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn apply_journal_skips_unresolved_issn() {
+        // Used to add a P1433 with an empty item
+        let mock_server = start_mock_server().await;
+        let cache = mock_cache(&mock_server).await;
+        let record = PublicationRecord {
+            issns: vec![sourced(vec!["1351-5101".to_string()], "a")],
+            ..Default::default()
+        };
+        let mut item = Entity::new_empty_item();
+        record.apply_journal(&mut item, &Precedence::default(), &cache).await;
+        assert!(!item.has_claims_with_property("P1433"));
+    }
+
+    #[tokio::test]
+    async fn apply_journal_skips_ambiguous_issn() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1351-5101", SEARCH_MULTIPLE).await;
+        let cache = mock_cache(&mock_server).await;
+        let record = PublicationRecord {
+            issns: vec![sourced(vec!["1351-5101".to_string()], "a")],
+            ..Default::default()
+        };
+        let mut item = Entity::new_empty_item();
+        record.apply_journal(&mut item, &Precedence::default(), &cache).await;
+        assert!(!item.has_claims_with_property("P1433"));
+    }
+}
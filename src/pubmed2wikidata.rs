@@ -4,9 +4,12 @@ use async_trait::async_trait;
 use pubmed::*;
 
 use crate::{
-    adapter_helpers::{get_external_identifier_from_item, sanitize_author_name},
+    adapter_helpers::{
+        get_external_identifier_from_item, sanitize_author_name, wikidata_language_code,
+    },
     generic_author_info::GenericAuthorInfo,
     identifiers::{is_pubmed_id, GenericWorkIdentifier, GenericWorkType, IdProp},
+    journal_info::JournalInfo,
     scientific_publication_adapter::ScientificPublicationAdapter,
     *,
 };
@@ -59,6 +62,17 @@ impl Pubmed2Wikidata {
         Some(sanitize_author_name(&full_name))
     }
 
+    /// `"[Title in English]."` → `"Title in English"`, the form MEDLINE uses
+    /// for titles translated from another language.
+    fn strip_translation_brackets(title: &str) -> &str {
+        let title = title.trim();
+        let inner = title.strip_suffix('.').unwrap_or(title);
+        match inner.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(inner) => inner.trim(),
+            None => title,
+        }
+    }
+
     async fn publication_id_from_pubmed(&mut self, publication_id: &str) -> Option<String> {
         if !is_pubmed_id(publication_id) {
             return None;
@@ -151,6 +165,10 @@ impl ScientificPublicationAdapter for Pubmed2Wikidata {
         "Pubmed2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q180686") // PubMed
+    }
+
     fn author_cache(&self) -> &HashMap<String, String> {
         &self.author_cache
     }
@@ -211,15 +229,29 @@ impl ScientificPublicationAdapter for Pubmed2Wikidata {
     }
 
     fn get_work_titles(&self, publication_id: &str) -> Vec<LocaleString> {
-        let title = self
+        let article = match self
             .get_cached_publication_from_id(publication_id)
             .and_then(|w| w.medline_citation.as_ref())
             .and_then(|c| c.article.as_ref())
-            .and_then(|a| a.title.as_ref());
-        match title {
-            Some(t) => vec![LocaleString::new("en", t)],
-            None => vec![],
+        {
+            Some(article) => article,
+            None => return vec![],
+        };
+        let mut ret = vec![];
+        // ArticleTitle is in English; MEDLINE puts translated titles in brackets
+        if let Some(title) = article.title.as_deref().map(Self::strip_translation_brackets) {
+            if !title.is_empty() {
+                ret.push(LocaleString::new("en", title));
+            }
+        }
+        // VernacularTitle is the original title, in the language of the article
+        let language = article.language.as_deref().and_then(wikidata_language_code);
+        if let (Some(title), Some(language)) = (&article.vernacular_title, language) {
+            if !title.trim().is_empty() {
+                ret.push(LocaleString::new(language, title.trim()));
+            }
         }
+        ret
     }
 
     fn get_work_issn(&self, publication_id: &str) -> Option<String> {
@@ -231,6 +263,20 @@ impl ScientificPublicationAdapter for Pubmed2Wikidata {
         Some(issn)
     }
 
+    fn get_journal_info(&self, publication_id: &str) -> Option<JournalInfo> {
+        let citation =
+            self.get_cached_publication_from_id(publication_id)?.medline_citation.as_ref()?;
+        let journal = citation.article.as_ref()?.journal.as_ref()?;
+        let medline_journal = citation.medline_journal_info.as_ref();
+        Some(JournalInfo {
+            title: journal.title.clone(),
+            issn_l: medline_journal.and_then(|j| j.issn_linking.clone()),
+            issns: journal.issn.iter().cloned().collect(),
+            publisher_openalex_id: None,
+            nlm_id: medline_journal.and_then(|j| j.nlm_unique_id.clone()),
+        })
+    }
+
     async fn get_identifier_list(
         &mut self,
         ids: &[GenericWorkIdentifier],
@@ -257,31 +303,6 @@ impl ScientificPublicationAdapter for Pubmed2Wikidata {
         ret
     }
 
-    async fn update_statements_for_publication_id(&self, publication_id: &str, item: &mut Entity) {
-        let work = match self.get_cached_publication_from_id(publication_id) {
-            Some(w) => w,
-            None => return,
-        };
-
-        // Work language
-        if !item.has_claims_with_property("P407") {
-            if let Some(medline_citation) = &work.medline_citation {
-                if let Some(article) = &medline_citation.article {
-                    if let Some(language) = &article.language {
-                        if let Some(q) = self.language2q(language).await {
-                            let statement = Statement::new_normal(
-                                Snak::new_item("P407", &q),
-                                vec![],
-                                self.reference(),
-                            );
-                            item.add_claim(statement);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
     async fn get_language_item(&self, publication_id: &str) -> Option<String> {
         self.language2q(
             self.get_cached_publication_from_id(publication_id)?
@@ -545,6 +566,41 @@ mod tests {
         assert_eq!(titles[0].language(), "en");
     }
 
+    #[test]
+    fn get_work_titles_adds_vernacular_title_in_article_language() {
+        let mut pm = Pubmed2Wikidata::new();
+        pm.work_cache.insert("1".to_string(), PubmedArticle {
+            medline_citation: Some(MedlineCitation {
+                pmid: 1,
+                article: Some(Article {
+                    title: Some("[Treatment of migraine in children]".to_string()),
+                    vernacular_title: Some("Migränebehandlung bei Kindern".to_string()),
+                    language: Some("ger".to_string()),
+                    ..Article::new()
+                }),
+                ..MedlineCitation::new()
+            }),
+            pubmed_data: None,
+        });
+        let titles = pm.get_work_titles("1");
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[0].value(), "Treatment of migraine in children");
+        assert_eq!(titles[0].language(), "en");
+        assert_eq!(titles[1].value(), "Migränebehandlung bei Kindern");
+        assert_eq!(titles[1].language(), "de");
+    }
+
+    #[test]
+    fn strip_translation_brackets() {
+        assert_eq!(Pubmed2Wikidata::strip_translation_brackets("[A title]."), "A title");
+        assert_eq!(Pubmed2Wikidata::strip_translation_brackets("[A title]"), "A title");
+        assert_eq!(Pubmed2Wikidata::strip_translation_brackets("A title."), "A title.");
+        assert_eq!(
+            Pubmed2Wikidata::strip_translation_brackets("[3H]thymidine uptake"),
+            "[3H]thymidine uptake"
+        );
+    }
+
     #[test]
     fn get_work_titles_returns_empty_for_missing_publication() {
         let pm = Pubmed2Wikidata::new();
@@ -578,6 +634,30 @@ mod tests {
         assert_eq!(pm.get_work_issn("3"), Some("1234-5678".to_string()));
     }
 
+    #[test]
+    fn get_journal_info_reads_journal_block() {
+        let mut pm = Pubmed2Wikidata::new();
+        pm.work_cache.insert("3".to_string(), PubmedArticle {
+            medline_citation: Some(MedlineCitation {
+                pmid: 3,
+                article: Some(Article {
+                    journal: Some(Journal {
+                        issn: Some("1234-5678".to_string()),
+                        title: Some("Test Journal".to_string()),
+                        ..Journal::new()
+                    }),
+                    ..Article::new()
+                }),
+                ..MedlineCitation::new()
+            }),
+            pubmed_data: None,
+        });
+        let journal = pm.get_journal_info("3").unwrap();
+        assert_eq!(journal.title.as_deref(), Some("Test Journal"));
+        assert_eq!(journal.issns, vec!["1234-5678"]);
+        assert_eq!(pm.get_journal_info("nonexistent"), None);
+    }
+
     #[test]
     fn get_work_issn_returns_none_for_missing_publication() {
         let pm = Pubmed2Wikidata::new();
@@ -0,0 +1,410 @@
+//! QuickStatements V1 export of entity diffs, for curators who want to
+//! review edits before running them.
+//!
+//! Input is the `wbeditentity` data of an `EntityDiff`. Labels, aliases and
+//! descriptions become `L`/`A`/`D` commands, statements become one line per
+//! reference, with qualifiers and `S` reference snaks. Statement removals
+//! only carry a statement ID, which V1 can't address, so the statement is
+//! looked up in the original item and removed by value (`-Q42\tP2093\t"…"`).
+//! V1 has no way to escape `"`, so statements with such a string value are
+//! skipped.
+
+use std::{io::Write, path::Path};
+
+use anyhow::Result;
+
+use crate::{append_writer::AppendWriter, *};
+
+/// Appends QuickStatements commands to a file (or any writer), shared
+/// between all clones.
+#[derive(Debug, Clone)]
+pub struct QuickStatementsWriter {
+    out: AppendWriter,
+}
+
+impl QuickStatementsWriter {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self { out: AppendWriter::new(out) }
+    }
+
+    /// Appends to the commands file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self { out: AppendWriter::open(path)? })
+    }
+
+    /// Writes the commands for one diff against `original`, and flushes so
+    /// that an interrupted run leaves only complete edits in the file.
+    pub fn write(
+        &self,
+        original: &Entity,
+        summary: Option<&str>,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let lines = commands(original, summary, data);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        self.out.write_lines(&lines)
+    }
+}
+
+/// The QuickStatements V1 lines for `data`, applied to `original` or, if
+/// it has no ID, to a new item.
+pub fn commands(original: &Entity, summary: Option<&str>, data: &serde_json::Value) -> Vec<String> {
+    let entity = original.id();
+    let subject = if entity.is_empty() { "LAST" } else { entity.as_str() };
+    let mut lines = vec![];
+    for (key, prefix) in [("labels", "L"), ("descriptions", "D"), ("aliases", "A")] {
+        for (language, value) in terms(&data[key]) {
+            if let Some(value) = quoted(&value) {
+                lines.push(format!("{subject}\t{prefix}{language}\t{value}"));
+            }
+        }
+    }
+    for claim in claims(&data["claims"]) {
+        if claim.get("remove").is_some() {
+            lines.extend(removal_line(subject, original, claim["id"].as_str()));
+            continue;
+        }
+        lines.extend(statement_lines(subject, claim));
+    }
+    if let Some(summary) = summary {
+        for line in &mut lines {
+            *line += &format!("\t/* {summary} */");
+        }
+    }
+    if entity.is_empty() && !lines.is_empty() {
+        lines.insert(0, "CREATE".to_string());
+    }
+    lines
+}
+
+/// `-Q…\tP…\tvalue` for the statement of `original` with the ID `id`.
+fn removal_line(subject: &str, original: &Entity, id: Option<&str>) -> Option<String> {
+    let statement = original.claims().iter().find(|claim| claim.id().as_deref() == id);
+    let line = statement.and_then(|statement| {
+        let value = statement_value(statement.main_snak().data_value().as_ref()?.value())?;
+        Some(format!("-{subject}\t{}\t{value}", statement.property()))
+    });
+    if line.is_none() {
+        tracing::warn!(?id, "can't remove statement with QuickStatements; skipped");
+    }
+    line
+}
+
+/// The value of a statement to remove. Only the types Papers removes
+/// statements of are supported.
+fn statement_value(value: &Value) -> Option<String> {
+    match value {
+        Value::StringValue(s) => quoted(s),
+        Value::Entity(entity) => Some(entity.id().to_string()),
+        Value::MonoLingual(text) => Some(format!("{}:{}", text.language(), quoted(text.text())?)),
+        _ => None,
+    }
+}
+
+/// `(language, value)` pairs of a terms section, which may be a list of
+/// terms or a map from language to a term or a list of terms.
+fn terms(j: &serde_json::Value) -> Vec<(String, String)> {
+    let list: Vec<&serde_json::Value> = match j {
+        serde_json::Value::Array(a) => a.iter().collect(),
+        serde_json::Value::Object(o) => o
+            .values()
+            .flat_map(|v| match v {
+                serde_json::Value::Array(a) => a.iter().collect(),
+                v => vec![v],
+            })
+            .collect(),
+        _ => vec![],
+    };
+    list.into_iter()
+        .filter(|t| t.get("remove").is_none())
+        .filter_map(|t| {
+            Some((t["language"].as_str()?.to_string(), t["value"].as_str()?.to_string()))
+        })
+        .collect()
+}
+
+/// Statements of a claims section, which may be a list, or a map from
+/// property to a list.
+fn claims(j: &serde_json::Value) -> Vec<&serde_json::Value> {
+    match j {
+        serde_json::Value::Array(a) => a.iter().collect(),
+        serde_json::Value::Object(o) => o.values().filter_map(|v| v.as_array()).flatten().collect(),
+        _ => vec![],
+    }
+}
+
+/// One line per reference (QuickStatements merges them into one statement),
+/// or one line if there are none. A statement with a snak that can't be
+/// written is skipped as a whole, rather than written without it.
+fn statement_lines(subject: &str, claim: &serde_json::Value) -> Vec<String> {
+    let lines = try_statement_lines(subject, claim);
+    if lines.is_none() {
+        let property = claim["mainsnak"]["property"].as_str();
+        tracing::warn!(?property, "can't write statement as QuickStatements; skipped");
+    }
+    lines.unwrap_or_default()
+}
+
+fn try_statement_lines(subject: &str, claim: &serde_json::Value) -> Option<Vec<String>> {
+    let mut line = format!("{subject}\t{}", snak(&claim["mainsnak"])?);
+    for (property, value) in snaks(&claim["qualifiers"])? {
+        line += &format!("\t{property}\t{value}");
+    }
+    let references = match claim["references"].as_array() {
+        Some(refs) => refs.iter().map(|r| snaks(&r["snaks"])).collect::<Option<Vec<_>>>()?,
+        None => vec![],
+    };
+    if references.is_empty() {
+        return Some(vec![line]);
+    }
+    let lines = references
+        .into_iter()
+        .map(|reference| {
+            let mut ret = line.to_owned();
+            for (property, value) in reference {
+                ret += &format!("\tS{}\t{value}", property.trim_start_matches('P'));
+            }
+            ret
+        })
+        .collect();
+    Some(lines)
+}
+
+/// `(property, value)` pairs of a snaks map, in property order. None if one
+/// of them can't be written.
+fn snaks(j: &serde_json::Value) -> Option<Vec<(String, String)>> {
+    let Some(o) = j.as_object() else {
+        return Some(vec![]);
+    };
+    let mut properties: Vec<&String> = o.keys().collect();
+    properties.sort_by_key(|p| p.trim_start_matches('P').parse::<u64>().unwrap_or(u64::MAX));
+    properties
+        .into_iter()
+        .flat_map(|property| o[property].as_array().into_iter().flatten())
+        .map(|s| Some((s["property"].as_str()?.to_string(), snak_value(s)?)))
+        .collect()
+}
+
+/// `P…\tvalue` for a snak.
+fn snak(j: &serde_json::Value) -> Option<String> {
+    Some(format!("{}\t{}", j["property"].as_str()?, snak_value(j)?))
+}
+
+fn snak_value(j: &serde_json::Value) -> Option<String> {
+    match j["snaktype"].as_str() {
+        Some("value") | None => {},
+        Some("somevalue") => return Some("somevalue".to_string()),
+        Some("novalue") => return Some("novalue".to_string()),
+        Some(_) => return None,
+    }
+    let value = &j["datavalue"]["value"];
+    match j["datavalue"]["type"].as_str()? {
+        "string" => quoted(value.as_str()?),
+        "wikibase-entityid" => value["id"].as_str().map(|id| id.to_string()),
+        "monolingualtext" => {
+            Some(format!("{}:{}", value["language"].as_str()?, quoted(value["text"].as_str()?)?))
+        },
+        "time" => Some(format!("{}/{}", value["time"].as_str()?, value["precision"].as_u64()?)),
+        "quantity" => {
+            let amount = value["amount"].as_str()?;
+            match value["unit"].as_str()?.rsplit('/').next() {
+                Some(unit) if unit.starts_with('Q') => {
+                    Some(format!("{amount}U{}", unit.trim_start_matches('Q')))
+                },
+                _ => Some(amount.to_string()),
+            }
+        },
+        "globecoordinate" => {
+            Some(format!("@{}/{}", value["latitude"].as_f64()?, value["longitude"].as_f64()?))
+        },
+        _ => None,
+    }
+}
+
+/// `s` in double quotes. None if `s` contains one, since V1 can't escape
+/// it, and a changed value would be a different one.
+fn quoted(s: &str) -> Option<String> {
+    if s.contains('"') {
+        tracing::warn!(value = s, "QuickStatements V1 can't quote '\"'");
+        return None;
+    }
+    Some(format!("\"{s}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wikibase::from_json::entity_from_json;
+
+    use super::*;
+    use crate::test_helpers::SharedBuffer;
+
+    fn string_snak(property: &str, value: &str) -> serde_json::Value {
+        json!({
+            "snaktype": "value",
+            "property": property,
+            "datatype": "string",
+            "datavalue": {"type": "string", "value": value},
+        })
+    }
+
+    fn item_snak(property: &str, q: &str) -> serde_json::Value {
+        json!({
+            "snaktype": "value",
+            "property": property,
+            "datatype": "wikibase-item",
+            "datavalue": {"type": "wikibase-entityid", "value": {"entity-type": "item", "id": q}},
+        })
+    }
+
+    fn item(id: &str) -> Entity {
+        entity_from_json(&json!({"type": "item", "id": id})).unwrap()
+    }
+
+    fn time_snak(property: &str, time: &str, precision: u64) -> serde_json::Value {
+        json!({
+            "snaktype": "value",
+            "property": property,
+            "datatype": "time",
+            "datavalue": {"type": "time", "value": {"time": time, "precision": precision}},
+        })
+    }
+
+    #[test]
+    fn new_item_starts_with_create() {
+        let data = json!({
+            "labels": {"en": {"language": "en", "value": "A title"}},
+            "claims": [{"mainsnak": item_snak("P31", "Q13442814"), "type": "statement"}],
+        });
+        assert_eq!(
+            commands(&Entity::new_empty_item(), None, &data),
+            vec!["CREATE", "LAST\tLen\t\"A title\"", "LAST\tP31\tQ13442814"]
+        );
+    }
+
+    #[test]
+    fn existing_item_is_the_subject() {
+        let data = json!({"claims": [{"mainsnak": item_snak("P31", "Q5")}]});
+        assert_eq!(commands(&item("Q42"), None, &data), vec!["Q42\tP31\tQ5"]);
+    }
+
+    #[test]
+    fn nothing_to_do_gives_no_lines() {
+        assert!(commands(&Entity::new_empty_item(), None, &json!({})).is_empty());
+    }
+
+    #[test]
+    fn qualifiers_follow_the_main_value() {
+        let data = json!({"claims": [{
+            "mainsnak": item_snak("P50", "Q1"),
+            "qualifiers": {
+                "P1932": [string_snak("P1932", "J. Doe")],
+                "P1545": [string_snak("P1545", "1")],
+            },
+        }]});
+        assert_eq!(
+            commands(&item("Q42"), None, &data),
+            vec!["Q42\tP50\tQ1\tP1545\t\"1\"\tP1932\t\"J. Doe\""]
+        );
+    }
+
+    #[test]
+    fn one_line_per_reference() {
+        let data = json!({"claims": [{
+            "mainsnak": string_snak("P356", "10.1000/XYZ"),
+            "references": [
+                {"snaks": {
+                    "P813": [time_snak("P813", "+2024-05-01T00:00:00Z", 11)],
+                    "P248": [item_snak("P248", "Q5188229")],
+                }},
+                {"snaks": {"P248": [item_snak("P248", "Q180686")]}},
+            ],
+        }]});
+        assert_eq!(
+            commands(&item("Q42"), None, &data),
+            vec![
+                "Q42\tP356\t\"10.1000/XYZ\"\tS248\tQ5188229\tS813\t+2024-05-01T00:00:00Z/11",
+                "Q42\tP356\t\"10.1000/XYZ\"\tS248\tQ180686",
+            ]
+        );
+    }
+
+    #[test]
+    fn claims_may_be_grouped_by_property() {
+        let data = json!({"claims": {"P31": [{"mainsnak": item_snak("P31", "Q5")}]}});
+        assert_eq!(commands(&item("Q42"), None, &data), vec!["Q42\tP31\tQ5"]);
+    }
+
+    #[test]
+    fn removals_are_by_value() {
+        let original = entity_from_json(&json!({
+            "type": "item",
+            "id": "Q42",
+            "claims": {"P2093": [{
+                "id": "Q42$abc",
+                "type": "statement",
+                "rank": "normal",
+                "mainsnak": string_snak("P2093", "Jane Doe"),
+            }]},
+        }))
+        .unwrap();
+        let data = json!({"claims": [
+            {"id": "Q42$abc", "remove": ""},
+            {"id": "Q42$unknown", "remove": ""},
+        ]});
+        assert_eq!(commands(&original, None, &data), vec!["-Q42\tP2093\t\"Jane Doe\""]);
+    }
+
+    #[test]
+    fn values_with_double_quotes_are_skipped() {
+        let data = json!({
+            "labels": {"en": {"language": "en", "value": "The \"best\" title"}},
+            "claims": [
+                {"mainsnak": string_snak("P2093", "J. \"Jim\" Doe")},
+                {
+                    "mainsnak": item_snak("P50", "Q1"),
+                    "qualifiers": {"P1932": [string_snak("P1932", "\"Jim\"")]},
+                },
+                {"mainsnak": item_snak("P31", "Q5")},
+            ],
+        });
+        assert_eq!(commands(&item("Q42"), None, &data), vec!["Q42\tP31\tQ5"]);
+    }
+
+    #[test]
+    fn summary_is_a_comment_on_every_line() {
+        let data = json!({"aliases": [{"language": "de", "value": "Titel"}]});
+        assert_eq!(
+            commands(&item("Q42"), Some("test"), &data),
+            vec!["Q42\tAde\t\"Titel\"\t/* test */"]
+        );
+    }
+
+    #[test]
+    fn value_formats() {
+        let monolingual = json!({
+            "property": "P1476",
+            "datavalue": {"type": "monolingualtext", "value": {"language": "en", "text": "T"}},
+        });
+        assert_eq!(snak_value(&monolingual), Some("en:\"T\"".to_string()));
+        let quantity = json!({
+            "property": "P1104",
+            "datavalue": {"type": "quantity", "value": {"amount": "+123", "unit": "1"}},
+        });
+        assert_eq!(snak_value(&quantity), Some("+123".to_string()));
+        let somevalue = json!({"snaktype": "somevalue", "property": "P50"});
+        assert_eq!(snak_value(&somevalue), Some("somevalue".to_string()));
+    }
+
+    #[test]
+    fn writer_appends_lines() {
+        let buffer = SharedBuffer::default();
+        let writer = QuickStatementsWriter::new(buffer.clone());
+        let data = json!({"claims": [{"mainsnak": item_snak("P31", "Q5")}]});
+        writer.write(&item("Q1"), None, &data).unwrap();
+        writer.write(&item("Q2"), None, &data).unwrap();
+        assert_eq!(buffer.text(), "Q1\tP31\tQ5\nQ2\tP31\tQ5\n");
+    }
+}
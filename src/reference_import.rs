@@ -0,0 +1,544 @@
+//! Reads reference lists in BibTeX and RIS format, and finds the paper
+//! identifiers in each entry.
+//!
+//! Identifiers come from the DOI, PMID, PMCID, arXiv eprint and ISBN
+//! fields, and from DOI, arXiv, PubMed and PMC URLs. Entries without any of
+//! those keep their title, authors and year, so a curator can look them up
+//! by hand.
+
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::{
+    identifiers::{is_pmcid, is_pubmed_id, GenericWorkIdentifier, IdProp},
+    isbn::Isbn,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceFormat {
+    BibTeX,
+    Ris,
+}
+
+impl FromStr for ReferenceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim() {
+            "bibtex" | "bib" => Ok(ReferenceFormat::BibTeX),
+            "ris" => Ok(ReferenceFormat::Ris),
+            _ => Err(format!("Unknown reference format: {s}")),
+        }
+    }
+}
+
+impl ReferenceFormat {
+    pub fn parse(&self, text: &str) -> Vec<Reference> {
+        match self {
+            ReferenceFormat::BibTeX => parse_bibtex(text),
+            ReferenceFormat::Ris => parse_ris(text),
+        }
+    }
+}
+
+/// One entry of a reference list.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reference {
+    /// BibTeX citation key, or RIS `ID`
+    pub key: Option<String>,
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub year: Option<String>,
+    pub ids: Vec<GenericWorkIdentifier>,
+}
+
+impl Reference {
+    /// A short name for the entry: its key, or else its identifiers.
+    pub fn label(&self) -> String {
+        match &self.key {
+            Some(key) => key.to_owned(),
+            None => self.ids.iter().map(|id| id.id()).collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "key": self.key,
+            "title": self.title,
+            "authors": self.authors,
+            "year": self.year,
+        })
+    }
+
+    fn add_id(&mut self, id: GenericWorkIdentifier) {
+        if !self.ids.contains(&id) {
+            self.ids.push(id);
+        }
+    }
+
+    fn add_doi(&mut self, s: &str) {
+        if let Some(doi) = normalize_doi(s) {
+            self.add_id(GenericWorkIdentifier::new_prop(IdProp::DOI, &doi));
+        }
+    }
+
+    fn add_pmid(&mut self, s: &str) {
+        if is_pubmed_id(s.trim()) {
+            self.add_id(GenericWorkIdentifier::new_prop(IdProp::PMID, s.trim()));
+        }
+    }
+
+    fn add_pmcid(&mut self, s: &str) {
+        let s = s.trim().to_uppercase();
+        let pmcid = if is_pubmed_id(&s) { format!("PMC{s}") } else { s };
+        if is_pmcid(&pmcid) {
+            self.add_id(GenericWorkIdentifier::new_prop(IdProp::PMCID, &pmcid));
+        }
+    }
+
+    fn add_arxiv(&mut self, s: &str) {
+        if let Some(arxiv) = normalize_arxiv(s) {
+            self.add_id(GenericWorkIdentifier::new_prop(IdProp::ARXIV, &arxiv));
+        }
+    }
+
+    /// ISBN fields may list several ISBNs; anything else, like an ISSN in
+    /// RIS `SN`, is ignored.
+    fn add_isbns(&mut self, s: &str) {
+        for part in s.split([',', ';']) {
+            if let Some(isbn) = Isbn::parse(part) {
+                for id in GenericWorkIdentifier::new_isbn(&isbn) {
+                    self.add_id(id);
+                }
+            }
+        }
+    }
+
+    fn add_url(&mut self, url: &str) {
+        for id in ids_from_url(url) {
+            self.add_id(id);
+        }
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(key) = &self.key {
+            write!(f, "[{key}] ")?;
+        }
+        write!(f, "{}", self.title.as_deref().unwrap_or("(no title)"))?;
+        let mut details = self.authors.clone();
+        details.extend(self.year.clone());
+        if !details.is_empty() {
+            write!(f, " ({})", details.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `10.xxx/yyy`, without a `doi:` or resolver URL prefix.
+fn normalize_doi(s: &str) -> Option<String> {
+    lazy_static! {
+        static ref RE_DOI: Regex =
+            Regex::new(r"(?i)^(?:doi:\s*|https?://(?:dx\.)?doi\.org/)?(10\.\d+/\S+)$")
+                .expect("RE_DOI");
+    }
+    RE_DOI.captures(s.trim()).and_then(|c| c.get(1)).map(|m| m.as_str().to_string())
+}
+
+/// New (`2301.12345`) or old (`hep-th/9901001`) style arXiv ID, without an
+/// `arXiv:` prefix or version.
+fn normalize_arxiv(s: &str) -> Option<String> {
+    lazy_static! {
+        static ref RE_ARXIV: Regex = Regex::new(
+            r"(?i)^(?:arxiv:\s*)?(\d{4}\.\d{4,5}|[a-z\-]+(?:\.[a-z]{2})?/\d{7})(?:v\d+)?$"
+        )
+        .expect("RE_ARXIV");
+    }
+    RE_ARXIV.captures(s.trim()).and_then(|c| c.get(1)).map(|m| m.as_str().to_string())
+}
+
+/// Identifiers in DOI resolver, arXiv, PubMed and PMC URLs.
+pub fn ids_from_url(url: &str) -> Vec<GenericWorkIdentifier> {
+    lazy_static! {
+        static ref RE_DOI_URL: Regex =
+            Regex::new(r"(?i)doi\.org/(10\.\d+/[^\s?#]+)").expect("RE_DOI_URL");
+        static ref RE_ARXIV_URL: Regex =
+            Regex::new(r"(?i)arxiv\.org/(?:abs|pdf)/([^\s?#]+?)(?:\.pdf)?(?:[?#]|$)")
+                .expect("RE_ARXIV_URL");
+        static ref RE_PUBMED_URL: Regex =
+            Regex::new(r"(?i)(?:pubmed\.ncbi\.nlm\.nih\.gov/|ncbi\.nlm\.nih\.gov/pubmed/)(\d+)")
+                .expect("RE_PUBMED_URL");
+        static ref RE_PMC_URL: Regex =
+            Regex::new(r"(?i)(?:pmc\.)?ncbi\.nlm\.nih\.gov/(?:pmc/)?articles/(PMC\d+)")
+                .expect("RE_PMC_URL");
+    }
+    let capture = |re: &Regex| re.captures(url).and_then(|c| c.get(1)).map(|m| m.as_str());
+    let mut reference = Reference::default();
+    if let Some(doi) = capture(&RE_DOI_URL) {
+        reference.add_doi(doi);
+    }
+    if let Some(arxiv) = capture(&RE_ARXIV_URL) {
+        reference.add_arxiv(arxiv);
+    }
+    if let Some(pmid) = capture(&RE_PUBMED_URL) {
+        reference.add_pmid(pmid);
+    }
+    if let Some(pmcid) = capture(&RE_PMC_URL) {
+        reference.add_pmcid(pmcid);
+    }
+    reference.ids
+}
+
+/// Parses all entries of a BibTeX file. `@comment`, `@preamble` and
+/// `@string` entries are skipped; `@string` abbreviations are not expanded.
+pub fn parse_bibtex(text: &str) -> Vec<Reference> {
+    let mut refs = vec![];
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let entry_type = rest[..open].trim().to_lowercase();
+        // An `@` outside of an entry, like in an e-mail address
+        if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let end = closing_delimiter(rest, open);
+        let body = &rest[open + 1..end];
+        rest = &rest[(end + 1).min(rest.len())..];
+        if !matches!(entry_type.as_str(), "comment" | "preamble" | "string") {
+            refs.push(parse_bibtex_entry(body));
+        }
+    }
+    refs
+}
+
+/// Position of the `}` or `)` closing the entry that opens at `open`, or
+/// the end of `s` for an unterminated entry.
+fn closing_delimiter(s: &str, open: usize) -> usize {
+    let close = if s[open..].starts_with('{') { '}' } else { ')' };
+    let mut depth = 0;
+    for (pos, c) in s.char_indices().skip_while(|(pos, _)| *pos <= open) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return pos,
+            _ => {},
+        }
+    }
+    s.len()
+}
+
+/// Splits at `separator` outside of braces and quotes.
+fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (pos, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '"' if depth == 0 => in_quotes = !in_quotes,
+            c if c == separator && depth == 0 && !in_quotes => {
+                parts.push(&s[start..pos]);
+                start = pos + c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A field value without its delimiters, `#` concatenation, braces and
+/// the escapes that turn up in DOIs and URLs.
+fn bibtex_value(raw: &str) -> String {
+    let joined: String = split_top_level(raw, '#')
+        .into_iter()
+        .map(|part| {
+            let part = part.trim();
+            part.strip_prefix('{')
+                .and_then(|p| p.strip_suffix('}'))
+                .or_else(|| part.strip_prefix('"').and_then(|p| p.strip_suffix('"')))
+                .unwrap_or(part)
+        })
+        .collect();
+    let unescaped = joined
+        .replace("\\_", "_")
+        .replace("\\%", "%")
+        .replace("\\&", "&")
+        .replace("\\#", "#")
+        .replace(['{', '}'], "");
+    unescaped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_bibtex_entry(body: &str) -> Reference {
+    let mut reference = Reference::default();
+    let mut eprint = None;
+    let mut eprint_type = None;
+    for (num, part) in split_top_level(body, ',').into_iter().enumerate() {
+        let Some((name, value)) = part.split_once('=') else {
+            if num == 0 && !part.trim().is_empty() {
+                reference.key = Some(part.trim().to_string());
+            }
+            continue;
+        };
+        let value = bibtex_value(value);
+        if value.is_empty() {
+            continue;
+        }
+        match name.trim().to_lowercase().as_str() {
+            "title" => reference.title = Some(value),
+            "author" => {
+                reference.authors = value.split(" and ").map(|a| a.trim().to_string()).collect()
+            },
+            "year" => reference.year = Some(value),
+            "date" if reference.year.is_none() => reference.year = year_of(&value),
+            "doi" => reference.add_doi(&value),
+            "pmid" => reference.add_pmid(&value),
+            "pmcid" => reference.add_pmcid(&value),
+            "arxiv" | "arxivid" => reference.add_arxiv(&value),
+            "eprint" => eprint = Some(value),
+            "archiveprefix" | "eprinttype" => eprint_type = Some(value.to_lowercase()),
+            "isbn" => reference.add_isbns(&value),
+            "url" => reference.add_url(&value),
+            _ => {},
+        }
+    }
+    // An eprint is an arXiv ID unless the entry says otherwise
+    if let Some(eprint) = eprint {
+        match eprint_type.as_deref() {
+            None | Some("arxiv") => reference.add_arxiv(&eprint),
+            Some("pubmed") => reference.add_pmid(&eprint),
+            Some("pmc") | Some("pmcid") => reference.add_pmcid(&eprint),
+            Some(_) => {},
+        }
+    }
+    reference
+}
+
+fn year_of(date: &str) -> Option<String> {
+    lazy_static! {
+        static ref RE_YEAR: Regex = Regex::new(r"\b(\d{4})\b").expect("RE_YEAR");
+    }
+    RE_YEAR.captures(date).and_then(|c| c.get(1)).map(|m| m.as_str().to_string())
+}
+
+/// Parses all entries of a RIS file. Each entry starts with `TY` and ends
+/// with `ER`; an entry missing its `ER` is kept.
+pub fn parse_ris(text: &str) -> Vec<Reference> {
+    lazy_static! {
+        static ref RE_TAG: Regex = Regex::new(r"^([A-Z][A-Z0-9])  -(?: (.*))?$").expect("RE_TAG");
+    }
+    let mut refs = vec![];
+    let mut current: Option<Reference> = None;
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        let Some(caps) = RE_TAG.captures(line) else {
+            continue;
+        };
+        let tag = caps.get(1).map_or("", |m| m.as_str());
+        let value = caps.get(2).map_or("", |m| m.as_str()).trim();
+        if tag == "TY" {
+            refs.extend(current.replace(Reference::default()));
+            continue;
+        }
+        if tag == "ER" {
+            refs.extend(current.take());
+            continue;
+        }
+        let Some(reference) = current.as_mut() else {
+            continue;
+        };
+        if value.is_empty() {
+            continue;
+        }
+        match tag {
+            "ID" => reference.key = Some(value.to_string()),
+            "TI" | "T1" => reference.title = Some(value.to_string()),
+            "AU" | "A1" => reference.authors.push(value.to_string()),
+            "PY" | "Y1" | "DA" if reference.year.is_none() => reference.year = year_of(value),
+            "DO" => reference.add_doi(value),
+            "SN" => reference.add_isbns(value),
+            "C2" => reference.add_pmcid(value),
+            "UR" | "L1" | "L2" => reference.add_url(value),
+            _ => {},
+        }
+    }
+    refs.extend(current);
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id_strings(reference: &Reference) -> Vec<String> {
+        reference.ids.iter().map(|id| format!("{}:{}", id.work_type(), id.id())).collect()
+    }
+
+    #[test]
+    fn reference_format_from_str() {
+        assert_eq!("bibtex".parse::<ReferenceFormat>().unwrap(), ReferenceFormat::BibTeX);
+        assert_eq!("RIS".parse::<ReferenceFormat>().unwrap(), ReferenceFormat::Ris);
+        assert!("endnote".parse::<ReferenceFormat>().is_err());
+    }
+
+    #[test]
+    fn normalize_doi_strips_prefixes() {
+        assert_eq!(normalize_doi("10.1000/xyz").unwrap(), "10.1000/xyz");
+        assert_eq!(normalize_doi("doi: 10.1000/xyz").unwrap(), "10.1000/xyz");
+        assert_eq!(normalize_doi("https://doi.org/10.1000/xyz").unwrap(), "10.1000/xyz");
+        assert_eq!(normalize_doi("http://dx.doi.org/10.1000/xyz").unwrap(), "10.1000/xyz");
+        assert!(normalize_doi("not a doi").is_none());
+    }
+
+    #[test]
+    fn normalize_arxiv_strips_prefix_and_version() {
+        assert_eq!(normalize_arxiv("arXiv:2301.12345v2").unwrap(), "2301.12345");
+        assert_eq!(normalize_arxiv("hep-th/9901001").unwrap(), "hep-th/9901001");
+        assert_eq!(normalize_arxiv("math.GT/0309136v1").unwrap(), "math.GT/0309136");
+        assert!(normalize_arxiv("12345").is_none());
+    }
+
+    #[test]
+    fn ids_from_url_recognises_known_sites() {
+        let doi = ids_from_url("https://doi.org/10.1000/xyz");
+        assert_eq!(doi, vec![GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1000/xyz")]);
+        let arxiv = ids_from_url("https://arxiv.org/pdf/2301.12345v1.pdf");
+        assert_eq!(arxiv, vec![GenericWorkIdentifier::new_prop(IdProp::ARXIV, "2301.12345")]);
+        let pmid = ids_from_url("https://pubmed.ncbi.nlm.nih.gov/12345678/");
+        assert_eq!(pmid, vec![GenericWorkIdentifier::new_prop(IdProp::PMID, "12345678")]);
+        let pmcid = ids_from_url("https://www.ncbi.nlm.nih.gov/pmc/articles/PMC1234567/");
+        assert_eq!(pmcid, vec![GenericWorkIdentifier::new_prop(IdProp::PMCID, "PMC1234567")]);
+        assert!(ids_from_url("https://example.org/paper.pdf").is_empty());
+    }
+
+    #[test]
+    fn bibtex_entry_with_doi_and_pmid() {
+        let refs = parse_bibtex(
+            r#"@article{Smith2020,
+                title = {A {Study} of
+                         Things},
+                author = "Smith, John and Doe, Jane",
+                year = 2020,
+                doi = {10.1000/ABC\_123},
+                pmid = {12345678},
+            }"#,
+        );
+        assert_eq!(refs.len(), 1);
+        let r = &refs[0];
+        assert_eq!(r.key.as_deref(), Some("Smith2020"));
+        assert_eq!(r.title.as_deref(), Some("A Study of Things"));
+        assert_eq!(r.authors, vec!["Smith, John", "Doe, Jane"]);
+        assert_eq!(r.year.as_deref(), Some("2020"));
+        assert_eq!(id_strings(r), vec!["P356:10.1000/ABC_123", "P698:12345678"]);
+    }
+
+    #[test]
+    fn bibtex_eprint_is_arxiv_unless_stated_otherwise() {
+        let refs = parse_bibtex(
+            "@misc{a, eprint = {2301.12345v3}, archivePrefix = {arXiv}}
+             @misc{b, eprint = {2301.12345}}
+             @misc{c, eprint = {2301.12345}, eprinttype = {hal}}",
+        );
+        assert_eq!(id_strings(&refs[0]), vec!["P818:2301.12345"]);
+        assert_eq!(id_strings(&refs[1]), vec!["P818:2301.12345"]);
+        assert!(refs[2].ids.is_empty());
+    }
+
+    #[test]
+    fn bibtex_isbn_and_url() {
+        let refs =
+            parse_bibtex("@book{b, isbn = {0-306-40615-2}, url = {https://doi.org/10.1000/book}}");
+        assert_eq!(
+            id_strings(&refs[0]),
+            vec!["P212:978-0-306-40615-7", "P957:0-306-40615-2", "P356:10.1000/BOOK"]
+        );
+    }
+
+    #[test]
+    fn bibtex_skips_special_entries_and_keeps_unresolved() {
+        let refs = parse_bibtex(
+            r#"@comment{ignore me}
+               @string{jbio = "Journal of Biology"}
+               @preamble{"\newcommand{\noop}[1]{}"}
+               Text between entries with an e-mail@example.org is ignored.
+               @inproceedings(Doe99,
+                 title = "No " # {identifiers} # " here",
+                 date = {1999-05-01},
+               )"#,
+        );
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].key.as_deref(), Some("Doe99"));
+        assert_eq!(refs[0].title.as_deref(), Some("No identifiers here"));
+        assert_eq!(refs[0].year.as_deref(), Some("1999"));
+        assert!(refs[0].ids.is_empty());
+    }
+
+    #[test]
+    fn bibtex_duplicate_ids_are_kept_once() {
+        let refs =
+            parse_bibtex("@article{x, doi = {10.1000/xyz}, url = {https://doi.org/10.1000/XYZ}}");
+        assert_eq!(id_strings(&refs[0]), vec!["P356:10.1000/XYZ"]);
+    }
+
+    #[test]
+    fn ris_entries() {
+        let refs = parse_ris(
+            "\u{feff}TY  - JOUR
+ID  - smith2020
+TI  - A study of things
+AU  - Smith, John
+AU  - Doe, Jane
+PY  - 2020///
+DO  - 10.1000/xyz
+SN  - 1234-5678
+C2  - PMC1234567
+UR  - https://pubmed.ncbi.nlm.nih.gov/12345678
+ER  -
+TY  - BOOK
+T1  - A book
+SN  - 978-0-306-40615-7
+ER  -
+TY  - GEN
+TI  - Unresolved
+Y1  - 1999
+",
+        );
+        assert_eq!(refs.len(), 3);
+        assert_eq!(refs[0].key.as_deref(), Some("smith2020"));
+        assert_eq!(refs[0].title.as_deref(), Some("A study of things"));
+        assert_eq!(refs[0].authors, vec!["Smith, John", "Doe, Jane"]);
+        assert_eq!(refs[0].year.as_deref(), Some("2020"));
+        assert_eq!(
+            id_strings(&refs[0]),
+            vec!["P356:10.1000/XYZ", "P932:PMC1234567", "P698:12345678"]
+        );
+        assert_eq!(id_strings(&refs[1]), vec!["P212:978-0-306-40615-7", "P957:0-306-40615-2"]);
+        assert!(refs[2].ids.is_empty());
+        assert_eq!(refs[2].year.as_deref(), Some("1999"));
+    }
+
+    #[test]
+    fn reference_label_and_display() {
+        let mut r = Reference {
+            title: Some("Title".to_string()),
+            authors: vec!["Smith, J".to_string()],
+            year: Some("2020".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(r.to_string(), "Title (Smith, J; 2020)");
+        r.add_doi("10.1000/xyz");
+        r.add_pmid("123");
+        assert_eq!(r.label(), "10.1000/XYZ 123");
+        r.key = Some("smith".to_string());
+        assert_eq!(r.label(), "smith");
+        assert_eq!(r.to_string(), "[smith] Title (Smith, J; 2020)");
+        assert_eq!(r.to_json()["authors"][0], "Smith, J");
+    }
+}
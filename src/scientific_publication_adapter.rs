@@ -5,10 +5,11 @@ use tokio::sync::OnceCell;
 
 use self::identifiers::{GenericWorkIdentifier, IdProp};
 use crate::{
-    adapter_helpers::{
-        get_external_identifier_from_item, strip_html_tags, titles_are_equal, wb_time_from_partial,
-    },
+    adapter_helpers::{get_external_identifier_from_item, retrieved_today},
     generic_author_info::GenericAuthorInfo,
+    journal_info::JournalInfo,
+    key_normalization::normalize_value,
+    publication_record::{PublicationRecord, Sourced},
     *,
 };
 
@@ -155,12 +156,6 @@ pub trait ScientificPublicationAdapter {
         tracing::warn!(adapter = self.name(), "{msg}");
     }
 
-    /// Adds/updates "special" statements of an item from the resource, given
-    /// the publication ID. Many common statements, title, aliases etc are
-    /// automatically handeled via
-    /// `update_statements_for_publication_id_default`
-    async fn update_statements_for_publication_id(&self, publication_id: &str, item: &mut Entity);
-
     // You should implement these yourself, where applicable
 
     /// Returns a list of the authors, if available, with list number, name,
@@ -246,6 +241,11 @@ pub trait ScientificPublicationAdapter {
         None
     }
 
+    /// Returns a page range string (P304), or None
+    fn get_pages(&self, _publication_id: &str) -> Option<String> {
+        None
+    }
+
     /// Returns the publication date, or None
     fn get_publication_date(&self, _publication_id: &str) -> Option<(u32, Option<u8>, Option<u8>)> {
         None
@@ -263,6 +263,12 @@ pub trait ScientificPublicationAdapter {
         None
     }
 
+    /// Returns the Wikidata item of the source database, used as "stated in"
+    /// (P248) in references, e.g. Q180686 for PubMed
+    fn source_item(&self) -> Option<&str> {
+        None
+    }
+
     /// Returns the property for a topic ID of the resource as a `String`, e.g.
     /// P6611 for Semantic Scholar
     fn topic_property(&self) -> Option<String> {
@@ -280,8 +286,22 @@ pub trait ScientificPublicationAdapter {
         None
     }
 
+    /// For a publication ID, returns all known ISSNs of the journal, ISSN-L
+    /// first if the source has one. Defaults to `get_work_issn`.
+    fn get_work_issns(&self, publication_id: &str) -> Vec<String> {
+        self.get_work_issn(publication_id).into_iter().collect()
+    }
+
+    /// For a publication ID, returns what the source knows about the journal,
+    /// for creating a journal item if none exists yet.
+    fn get_journal_info(&self, _publication_id: &str) -> Option<JournalInfo> {
+        None
+    }
+
     // For a publication ID, return all known titles as a `Vec<LocaleString>`, main
-    // title first (per language)
+    // title first (per language). Titles are tagged with the language they are
+    // actually in (`UNDETERMINED_LANGUAGE` if unknown); an English label is only
+    // set from an English or translated title.
     fn get_work_titles(&self, _publication_id: &str) -> Vec<LocaleString> {
         vec![]
     }
@@ -292,9 +312,28 @@ pub trait ScientificPublicationAdapter {
         None
     }
 
-    fn reference(&self) -> Vec<Reference> {
-        // TODO
-        vec![]
+    /// Property and value identifying the source record in references.
+    /// Defaults to the publication property and ID, for adapters that have one.
+    fn reference_record_id(&self, publication_id: &str) -> Option<(String, String)> {
+        let prop = self.publication_property()?;
+        let id = self.publication_id_for_statement(publication_id)?;
+        Some((prop.as_str().to_string(), normalize_value(prop.as_str(), &id)))
+    }
+
+    /// Reference for statements based on `publication_id`: stated in (P248)
+    /// the source, the source record ID, and retrieved (P813) today. Empty
+    /// for adapters without a source item.
+    fn reference(&self, publication_id: &str) -> Vec<Reference> {
+        let source = match self.source_item() {
+            Some(source) => source,
+            None => return vec![],
+        };
+        let mut snaks = vec![Snak::new_item("P248", source)];
+        if let Some((prop, id)) = self.reference_record_id(publication_id) {
+            snaks.push(Snak::new_external_id(prop, id));
+        }
+        snaks.push(retrieved_today());
+        vec![Reference::new(snaks)]
     }
 
     /// Returns the sanitized (if required) publication ID to put in a statement
@@ -302,150 +341,38 @@ pub trait ScientificPublicationAdapter {
         Some(id.to_string())
     }
 
-    async fn update_statements_for_publication_id_default(
-        &self,
-        publication_id: &str,
-        item: &mut Entity,
-        // cache: Arc<WikidataStringCache>,
-    ) {
-        self.update_work_item_with_title(publication_id, item);
-        self.update_work_item_with_property(publication_id, item);
-        self.update_work_item_with_journal(publication_id, item).await;
-        self.update_work_item_with_volume(publication_id, item);
-        self.update_work_item_with_issue(publication_id, item);
-        self.update_work_item_with_publication_date(publication_id, item);
-        self.update_work_item_with_language(publication_id, item).await;
-    }
-
-    async fn update_work_item_with_language(&self, publication_id: &str, item: &mut Entity) {
-        if item.has_claims_with_property("P407") {
-            return;
-        }
-        if let Some(language_q) = self.get_language_item(publication_id).await {
-            item.add_claim(Statement::new_normal(
-                Snak::new_item("P407", &language_q),
-                vec![],
-                self.reference(),
-            ))
-        }
-    }
-
-    fn update_work_item_with_volume(&self, publication_id: &str, item: &mut Entity) {
-        if item.has_claims_with_property("P478") {
-            return;
-        }
-        if let Some(volume) = self.get_volume(publication_id) {
-            item.add_claim(Statement::new_normal(
-                Snak::new_string("P478", &volume),
-                vec![],
-                self.reference(),
-            ))
+    /// Everything this adapter reports about `publication_id`, tagged with
+    /// the adapter name and the references for statements made from it.
+    async fn publication_record(&mut self, publication_id: &str) -> PublicationRecord {
+        let name = self.name().to_string();
+        let refs = self.reference(publication_id);
+        let issns = Some(self.get_work_issns(publication_id)).filter(|v| !v.is_empty());
+        let language = self.get_language_item(publication_id).await;
+        let authors = Some(self.get_author_list(publication_id).await).filter(|v| !v.is_empty());
+        PublicationRecord {
+            identifiers: Sourced::all(self.publication_identifier(publication_id), &name, &refs),
+            titles: Sourced::all(self.get_work_titles(publication_id), &name, &refs),
+            publication_dates: Sourced::all(
+                self.get_publication_date(publication_id),
+                &name,
+                &refs,
+            ),
+            volumes: Sourced::all(self.get_volume(publication_id), &name, &refs),
+            issues: Sourced::all(self.get_issue(publication_id), &name, &refs),
+            pages: Sourced::all(self.get_pages(publication_id), &name, &refs),
+            work_types: Sourced::all(self.get_work_type(publication_id), &name, &refs),
+            issns: Sourced::all(issns, &name, &refs),
+            languages: Sourced::all(language, &name, &refs),
+            authors: Sourced::all(authors, &name, &refs),
         }
     }
 
-    fn update_work_item_with_issue(&self, publication_id: &str, item: &mut Entity) {
-        if item.has_claims_with_property("P433") {
-            return;
-        }
-        if let Some(issue) = self.get_issue(publication_id) {
-            item.add_claim(Statement::new_normal(
-                Snak::new_string("P433", &issue),
-                vec![],
-                self.reference(),
-            ))
-        }
-    }
-
-    fn update_work_item_with_publication_date(&self, publication_id: &str, item: &mut Entity) {
-        if item.has_claims_with_property("P577") {
-            return;
-        }
-        if let Some(pubdate) = self.get_publication_date(publication_id) {
-            let statement =
-                wb_time_from_partial("P577", pubdate.0, pubdate.1, pubdate.2, self.reference());
-            item.add_claim(statement);
-        }
-    }
-
-    fn update_work_item_with_title(&self, publication_id: &str, item: &mut Entity) {
-        let titles = self.get_work_titles(publication_id);
-        if titles.is_empty() {
-            return;
-        }
-
-        // Re-org, stripping HTML tags from title values (APIs like Crossref
-        // and PubMed may return titles with <i>, <b>, <sub>, <sup> etc.)
-        let mut by_lang: HashMap<String, Vec<String>> = HashMap::new();
-        titles.iter().for_each(|t| {
-            let lv = by_lang.entry(t.language().to_string()).or_default();
-            lv.push(strip_html_tags(t.value()))
-        });
-        for (language, titles) in by_lang.iter() {
-            let mut titles = titles.clone();
-            // Add title
-            match item.label_in_locale(language) {
-                Some(t) => titles.retain(|x| !titles_are_equal(x, t)), /* Title exists,
-                                                                          * remove from title
-                                                                          * list */
-                None => item.set_label(LocaleString::new("en", &titles.swap_remove(0))), /* No title, add and remove from title list */
-            }
-
-            // Add other potential titles as aliases
-            // let main_title = item.label_in_locale("en").unwrap_or("").to_string();
-            // titles
-            //     .iter()
-            //     .filter(|t| !self.titles_are_equal(t, &main_title))
-            //     .for_each(|t| {
-            //         item.add_alias(LocaleString::new(language.to_string(),
-            // t.to_string()))     });
-
-            // Add P1476 (title)
-            if !item.has_claims_with_property("P1476") {
-                let label = item.label_in_locale(language).map(|s| s.to_owned());
-                if let Some(title) = label {
-                    item.add_claim(Statement::new_normal(
-                        Snak::new_monolingual_text("P1476", &title, language),
-                        vec![],
-                        self.reference(),
-                    ))
-                }
-            }
-        }
-    }
-
-    async fn update_work_item_with_journal(
-        &self,
-        publication_id: &str,
-        item: &mut Entity,
-        // cdn_cache_control_cache: Arc<WikidataStringCache>,
-    ) {
-        if item.has_claims_with_property("P1433") {
-            return;
-        }
-        if let Some(_issn) = self.get_work_issn(publication_id) {
-            let r = Some("".to_string()); // cache.issn2q(&issn).await;
-            if let Some(q) = r {
-                item.add_claim(Statement::new_normal(
-                    Snak::new_item("P1433", &q),
-                    vec![],
-                    self.reference(),
-                ))
-            }
-        }
-    }
-
-    fn update_work_item_with_property(&self, publication_id: &str, item: &mut Entity) {
-        if let Some(prop) = self.publication_property() {
-            if !item.has_claims_with_property(prop.as_str()) {
-                if let Some(pub_id) = self.publication_id_for_statement(publication_id) {
-                    item.add_claim(Statement::new_normal(
-                        Snak::new_external_id(prop.to_string(), pub_id),
-                        vec![],
-                        self.reference(),
-                    ));
-                }
-            }
-        }
+    /// The publication property and its (normalized) value for the record
+    /// `publication_id`, for the item's identifier statement.
+    fn publication_identifier(&self, publication_id: &str) -> Option<(String, String)> {
+        let prop = self.publication_property()?;
+        let pub_id = self.publication_id_for_statement(publication_id)?;
+        Some((prop.as_str().to_string(), normalize_value(prop.as_str(), &pub_id)))
     }
 
     fn set_author_cache_entry(&mut self, catalog_author_id: &str, q: &str) {
@@ -576,21 +503,13 @@ mod tests {
 
     // === ScientificPublicationAdapter helpers =================================
 
-    /// Minimal test adapter that returns configurable titles
+    /// Minimal test adapter that returns configurable titles and ISSNs
     struct TestAdapter {
         titles: Vec<LocaleString>,
+        issns: Vec<String>,
         author_cache: HashMap<String, String>,
     }
 
-    impl TestAdapter {
-        fn with_titles(titles: Vec<&str>) -> Self {
-            Self {
-                titles: titles.into_iter().map(|t| LocaleString::new("en", t)).collect(),
-                author_cache: HashMap::new(),
-            }
-        }
-    }
-
     #[async_trait(?Send)]
     impl ScientificPublicationAdapter for TestAdapter {
         fn name(&self) -> &str {
@@ -602,59 +521,55 @@ mod tests {
         fn author_cache_mut(&mut self) -> &mut HashMap<String, String> {
             &mut self.author_cache
         }
-        async fn update_statements_for_publication_id(
-            &self,
-            _publication_id: &str,
-            _item: &mut Entity,
-        ) {
+        fn source_item(&self) -> Option<&str> {
+            Some("Q1")
         }
         fn get_work_titles(&self, _publication_id: &str) -> Vec<LocaleString> {
             self.titles.clone()
         }
+        fn get_work_issns(&self, _publication_id: &str) -> Vec<String> {
+            self.issns.clone()
+        }
+        fn get_volume(&self, _publication_id: &str) -> Option<String> {
+            Some("12".to_string())
+        }
     }
 
     // Pure-helper tests (strip_html_tags, titles_are_equal,
     // sanitize_author_name, wb_time_from_partial,
     // get_external_identifier_from_item) live in `adapter_helpers::tests`
-    // since the helpers themselves are now free functions there. The
-    // tests below exercise the trait *templates* that still use them.
-
-    // === update_work_item_with_title with HTML tags (issue #7) ===
-
-    #[test]
-    fn update_work_item_with_title_strips_html_from_label() {
-        let adapter = TestAdapter::with_titles(vec![
-            "Correction: <i>Accidental aspiration of a solid tablet of sodium hydroxide</i>",
-        ]);
-        let mut item = Entity::new_empty_item();
-        adapter.update_work_item_with_title("test_id", &mut item);
-
-        assert_eq!(
-            item.label_in_locale("en"),
-            Some("Correction: Accidental aspiration of a solid tablet of sodium hydroxide"),
-        );
-    }
-
-    #[test]
-    fn update_work_item_with_title_strips_html_from_species_names() {
-        let adapter = TestAdapter::with_titles(vec![
-            "Population genetics of <i>Drosophila melanogaster</i> in tropical environments",
-        ]);
-        let mut item = Entity::new_empty_item();
-        adapter.update_work_item_with_title("test_id", &mut item);
-
-        assert_eq!(
-            item.label_in_locale("en"),
-            Some("Population genetics of Drosophila melanogaster in tropical environments"),
-        );
-    }
-
-    #[test]
-    fn update_work_item_with_title_plain_text_unaffected() {
-        let adapter = TestAdapter::with_titles(vec!["A plain text title"]);
-        let mut item = Entity::new_empty_item();
-        adapter.update_work_item_with_title("test_id", &mut item);
-
-        assert_eq!(item.label_in_locale("en"), Some("A plain text title"));
+    // since the helpers themselves are now free functions there. How
+    // titles, journals etc. end up in the item is tested in
+    // `publication_record::tests`.
+
+    // === publication_record ===
+
+    #[tokio::test]
+    async fn publication_record_tags_values_with_adapter() {
+        let mut adapter = TestAdapter {
+            titles: vec![LocaleString::new("en", "A title"), LocaleString::new("de", "Ein Titel")],
+            issns: vec!["1351-5101".to_string(), "1468-1331".to_string()],
+            author_cache: HashMap::new(),
+        };
+        let record = adapter.publication_record("test_id").await;
+        assert_eq!(record.titles.len(), 2);
+        assert!(record.titles.iter().all(|t| t.source == "test"));
+        assert_eq!(record.titles[0].references.len(), 1);
+        assert_eq!(record.volumes[0].value, "12");
+        // All ISSNs of one source are a single candidate
+        assert_eq!(record.issns.len(), 1);
+        assert_eq!(record.issns[0].value, vec!["1351-5101", "1468-1331"]);
+    }
+
+    #[tokio::test]
+    async fn publication_record_skips_unknown_values() {
+        let mut adapter =
+            TestAdapter { titles: vec![], issns: vec![], author_cache: HashMap::new() };
+        let record = adapter.publication_record("test_id").await;
+        assert!(record.titles.is_empty());
+        assert!(record.issns.is_empty());
+        assert!(record.publication_dates.is_empty());
+        assert!(record.languages.is_empty());
+        assert!(record.authors.is_empty());
     }
 }
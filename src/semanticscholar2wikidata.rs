@@ -5,7 +5,7 @@ use semanticscholar::*;
 
 use self::identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp};
 use crate::{
-    adapter_helpers::wb_time_from_partial, generic_author_info::GenericAuthorInfo,
+    generic_author_info::GenericAuthorInfo,
     scientific_publication_adapter::ScientificPublicationAdapter, *,
 };
 
@@ -69,6 +69,10 @@ impl ScientificPublicationAdapter for Semanticscholar2Wikidata {
         "Semanticscholar2Wikidata"
     }
 
+    fn source_item(&self) -> Option<&str> {
+        Some("Q22908627") // Semantic Scholar
+    }
+
     fn author_property(&self) -> Option<String> {
         Some("P4012".to_string())
     }
@@ -157,19 +161,9 @@ impl ScientificPublicationAdapter for Semanticscholar2Wikidata {
         }
     }
 
-    async fn update_statements_for_publication_id(&self, publication_id: &str, item: &mut Entity) {
-        let work = match self.get_cached_publication_from_id(publication_id) {
-            Some(w) => w,
-            None => return,
-        };
-
-        if !item.has_claims_with_property("P577") {
-            if let Some(year) = work.year {
-                let statement =
-                    wb_time_from_partial("P577", year as u32, None, None, self.reference());
-                item.add_claim(statement);
-            }
-        }
+    fn get_publication_date(&self, publication_id: &str) -> Option<(u32, Option<u8>, Option<u8>)> {
+        let year = self.get_cached_publication_from_id(publication_id)?.year?;
+        Some((year as u32, None, None))
     }
 
     async fn get_author_list(&mut self, publication_id: &str) -> Vec<GenericAuthorInfo> {
@@ -275,6 +269,22 @@ mod tests {
         assert!(ss.get_work_titles("nonexistent").is_empty());
     }
 
+    // === get_publication_date ===
+
+    #[test]
+    fn get_publication_date_is_year_only() {
+        let mut work = make_work(None, None, None);
+        work.year = Some(2019);
+        let ss = make_ss("abc123", work);
+        assert_eq!(ss.get_publication_date("abc123"), Some((2019, None, None)));
+    }
+
+    #[test]
+    fn get_publication_date_none_without_year() {
+        let ss = make_ss("abc123", make_work(None, None, None));
+        assert_eq!(ss.get_publication_date("abc123"), None);
+    }
+
     // === add_identifiers_from_cached_publication ===
 
     #[test]
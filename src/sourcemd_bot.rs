@@ -1,16 +1,26 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use anyhow::{anyhow, Context, Result};
 use tokio::sync::RwLock;
+use wikibase::mediawiki::api::Api;
 
 use self::sourcemd_command::SourceMDcommandMode;
 use crate::{
-    generic_author_info::GenericAuthorInfo,
+    edit_output::EditOutput,
+    generic_author_info::{item_has_id, GenericAuthorInfo},
     identifiers::{GenericWorkIdentifier, IdProp},
+    isbn::Isbn,
+    key_normalization::normalize_value,
+    openlibrary::{BookRecord, OpenLibrary},
+    orcid2wikidata::Orcid2Wikidata,
     sourcemd_command::SourceMDcommand,
     sourcemd_config::SourceMD,
-    wikidata_papers::WikidataPapers,
-    wikidata_string_cache::WikidataStringCache,
+    wikidata_interaction::WikidataInteraction,
+    wikidata_papers::{EditResult, ItemUpdate, WikidataPapers},
+    wikidata_string_cache::{LookupError, WikidataStringCache},
     *,
 };
 
@@ -19,6 +29,9 @@ pub struct SourceMDbot {
     config: Arc<RwLock<SourceMD>>,
     cache: Arc<WikidataStringCache>,
     batch_id: i64,
+    output: EditOutput,
+    /// Commands run so far, to prefetch the next window of them in time
+    commands_run: Arc<AtomicUsize>,
 }
 
 impl SourceMDbot {
@@ -27,11 +40,32 @@ impl SourceMDbot {
         cache: Arc<WikidataStringCache>,
         batch_id: i64,
     ) -> Result<Self> {
-        let ret = Self { config, batch_id, cache };
+        let ret = Self::with_batch(config, cache, batch_id);
         ret.start().await?;
         Ok(ret)
     }
 
+    /// A bot that isn't tied to a batch, for running single commands from
+    /// the command line. Never touches the SourceMD database.
+    pub fn new_standalone(config: Arc<RwLock<SourceMD>>, cache: Arc<WikidataStringCache>) -> Self {
+        Self::with_batch(config, cache, 0)
+    }
+
+    fn with_batch(
+        config: Arc<RwLock<SourceMD>>,
+        cache: Arc<WikidataStringCache>,
+        batch_id: i64,
+    ) -> Self {
+        let commands_run = Arc::new(AtomicUsize::new(0));
+        Self { config, batch_id, cache, output: EditOutput::default(), commands_run }
+    }
+
+    /// Whether edits are written to Wikidata or only reported. Live by
+    /// default.
+    pub fn set_output(&mut self, output: EditOutput) {
+        self.output = output;
+    }
+
     pub async fn start(&self) -> Result<()> {
         let config = self.config.read().await;
         config
@@ -42,6 +76,48 @@ impl SourceMDbot {
         Ok(())
     }
 
+    /// Fills the cache with the items for the IDs of the next TODO commands,
+    /// with a few SPARQL queries instead of one search per ID. Only a window
+    /// of commands, since the cache can't hold the IDs of a large batch
+    /// until they are used. Failures only cost the searches later.
+    async fn prefetch_batch(&self) {
+        let window = self.cache.prefetch_window();
+        let commands = self.config.read().await.get_todo_commands(self.batch_id, window).await;
+        let commands = match commands {
+            Ok(commands) => commands,
+            Err(error) => {
+                tracing::warn!(batch_id = self.batch_id, %error, "can't prefetch batch");
+                return;
+            },
+        };
+        let pairs: Vec<(String, String)> = commands.iter().flat_map(Self::prefetch_keys).collect();
+        self.cache.prefetch(&pairs).await;
+    }
+
+    /// The property/key pairs a command will look up in the cache.
+    fn prefetch_keys(command: &SourceMDcommand) -> Vec<(String, String)> {
+        let keys = WikidataStringCache::keys_for_ids;
+        let ids = |s: &str| keys(&GenericWorkIdentifier::parse_ids_from_str(s));
+        let orcid = |s: &str| {
+            let is_orcid = crate::identifiers::is_orcid(s);
+            is_orcid.then(|| ("P496".to_string(), s.to_string())).into_iter().collect::<Vec<_>>()
+        };
+        match command.mode {
+            SourceMDcommandMode::CreatePaperById => ids(&command.identifier),
+            SourceMDcommandMode::CreateBookFromIsbn => match Isbn::parse(&command.identifier) {
+                Some(isbn) => keys(&GenericWorkIdentifier::new_isbn(&isbn)),
+                None => vec![],
+            },
+            SourceMDcommandMode::AddOrcidMetadataToAuthor => orcid(command.identifier.trim()),
+            SourceMDcommandMode::AddAutthorToPublication
+            | SourceMDcommandMode::EditPaperForOrcidAuthor => match command.paper_and_author() {
+                Some((paper, author)) => [ids(&paper), orcid(&author)].concat(),
+                None => vec![],
+            },
+            SourceMDcommandMode::Dummy => vec![],
+        }
+    }
+
     pub async fn run(&self) -> Result<bool> {
         // Check if batch is still valid (STOP etc.)
         let mut command = match self.get_next_command().await? {
@@ -57,11 +133,17 @@ impl SourceMDbot {
             },
         };
 
+        // Before the command is marked running, so it is the first TODO one
+        if self.commands_run.fetch_add(1, Ordering::Relaxed) % self.cache.prefetch_window() == 0 {
+            self.prefetch_batch().await;
+        }
         self.set_command_status("RUNNING", None, &mut command).await?;
+        command.note.clear();
         match self.execute_command(&mut command).await {
             Ok(b) => {
                 let status = if b { "DONE" } else { "DUNNO" };
-                self.set_command_status(status, None, &mut command).await?;
+                let note = (!command.note.is_empty()).then(|| command.note.clone());
+                self.set_command_status(status, note.as_deref(), &mut command).await?;
                 Ok(b)
             },
             Err(e) => {
@@ -74,66 +156,376 @@ impl SourceMDbot {
     async fn execute_command(&self, command: &mut SourceMDcommand) -> Result<bool> {
         match &command.mode {
             SourceMDcommandMode::CreatePaperById => self.process_paper(command).await,
-            SourceMDcommandMode::AddAutthorToPublication => self.process_paper(command).await,
+            SourceMDcommandMode::AddAutthorToPublication => {
+                self.process_add_author_to_publication(command).await
+            },
             SourceMDcommandMode::AddOrcidMetadataToAuthor => {
-                // TODO
-                if true {
-                    Ok(false)
-                } else {
-                    self.process_author_metadata(command).await
-                }
+                self.process_author_metadata(command).await
+            },
+            SourceMDcommandMode::EditPaperForOrcidAuthor => {
+                self.process_paper_for_orcid_author(command).await
             },
-            SourceMDcommandMode::EditPaperForOrcidAuthor => Ok(false), // TODO
-            SourceMDcommandMode::CreateBookFromIsbn => Ok(false),      // TODO
+            SourceMDcommandMode::CreateBookFromIsbn => self.process_book(command).await,
             other => Err(anyhow!("Unrecognized command '{}' on command #{}", other, command.id)),
         }
     }
 
-    async fn get_author_item(&self, identifier: &str) -> Result<GenericAuthorInfo> {
+    /// Author details for a Q-ID or ORCID, without creating anything. For an
+    /// ORCID, name and external IDs come from the ORCID record (if it can be
+    /// loaded), and the item is the one that already carries the ORCID; it is
+    /// an error if several items carry it.
+    async fn get_author_info(&self, identifier: &str) -> Result<GenericAuthorInfo> {
         let mut author = GenericAuthorInfo::new();
         if crate::identifiers::is_qid(identifier) {
             author.set_wikidata_item(Some(identifier.to_owned()));
         } else if crate::identifiers::is_orcid(identifier) {
+            if let Some(orcid_author) =
+                Orcid2Wikidata::new().get_author_data(identifier, "P496").await
+            {
+                author = orcid_author;
+            }
             author.prop2id_mut().insert("P496".to_string(), identifier.to_owned());
-            author = author
-                .get_or_create_author_item(
-                    self.config.read().await.mw_api(),
-                    self.cache.clone(),
-                    false,
-                )
-                .await
+            let q = match self.cache.get_unique("P496", identifier).await {
+                Ok(q) => q,
+                Err(LookupError::Ambiguous(items)) => {
+                    let items = items.join(", ");
+                    return Err(anyhow!("ORCID {identifier} is on several items: {items}"));
+                },
+                Err(LookupError::Failed(error)) => return Err(error),
+            };
+            author.set_wikidata_item(q);
         } else {
             return Err(anyhow!("Not a Wikidata item, nor an ORCID ID {}", identifier));
         }
-
-        // Paranoia
-        if author.wikidata_item().is_none() {
-            return Err(anyhow!("Failed to get/create author item for {}", identifier));
-        }
-
         Ok(author)
     }
 
+    /// The item for `author`, created from its name and external IDs if
+    /// there isn't one yet.
+    async fn create_author_item(
+        &self,
+        author: &GenericAuthorInfo,
+        identifier: &str,
+    ) -> Result<String> {
+        let mw_api = self.config.read().await.mw_api();
+        let author =
+            author.get_or_create_author_item(mw_api, self.cache.clone(), false, &self.output).await;
+        author
+            .wikidata_item()
+            .map(|q| q.to_string())
+            .ok_or_else(|| anyhow!("Failed to get/create author item for {}", identifier))
+    }
+
+    /// Adds external IDs from ORCID to an author item, identified by Q-ID or
+    /// ORCID. Only runs if the ORCID is already on the item, or is on exactly
+    /// one item. IDs that disagree with the item, or belong to another item,
+    /// are skipped. The outcome goes into the command note.
     pub async fn process_author_metadata(&self, command: &mut SourceMDcommand) -> Result<bool> {
-        let author = self.get_author_item(&command.identifier).await?;
+        let identifier = command.identifier.trim().to_string();
+        let mut wdp = self.new_wdp(command).await;
+        wdp.set_edit_summary(Some(self.edit_summary(command)));
+        let mw_api = self.config.read().await.mw_api();
+
+        let (q, orcid) = if crate::identifiers::is_qid(&identifier) {
+            let item = Self::load_item(&mut wdp, &identifier, mw_api.clone()).await?;
+            let orcids: Vec<String> = item
+                .values_for_property("P496")
+                .into_iter()
+                .filter_map(|v| match v {
+                    Value::StringValue(s) => Some(s),
+                    _ => None,
+                })
+                .collect();
+            match orcids.as_slice() {
+                [orcid] => (identifier.to_owned(), orcid.to_owned()),
+                [] => {
+                    command.note = format!("{identifier} has no ORCID");
+                    return Ok(false);
+                },
+                _ => {
+                    command.note = format!("{identifier} has several ORCIDs");
+                    return Ok(false);
+                },
+            }
+        } else if crate::identifiers::is_orcid(&identifier) {
+            match self.cache.get_unique("P496", &identifier).await {
+                Ok(Some(q)) => (q, normalize_value("P496", &identifier)),
+                Ok(None) => {
+                    command.note = format!("No item has ORCID {identifier}");
+                    return Ok(false);
+                },
+                Err(LookupError::Ambiguous(items)) => {
+                    command.note =
+                        format!("ORCID {identifier} is on several items: {}", items.join(", "));
+                    return Ok(false);
+                },
+                Err(LookupError::Failed(error)) => return Err(error),
+            }
+        } else {
+            return Err(anyhow!("Not a Wikidata item, nor an ORCID ID {}", identifier));
+        };
+        command.q = q.to_owned();
 
-        // Create paper object
-        let mut wdp = self.new_wdp(command);
-        wdp.set_edit_summary(Some(format!(
-            "SourceMD [rust bot], [https://sourcemd.toolforge.org/?action=batch&batch={} batch #{}], command #{}",
-            self.batch_id, self.batch_id, command.serial_number
-        )));
-        let config = self.config.read().await;
-        wdp.update_author_items(&vec![author], config.mw_api()).await;
+        let mut author = match Orcid2Wikidata::new().get_author_data(&orcid, "P496").await {
+            Some(author) => author,
+            None => {
+                command.note = format!("Could not load ORCID record {orcid}");
+                return Ok(false);
+            },
+        };
+        author.set_wikidata_item(Some(q.to_owned()));
+        let original_item = Self::load_item(&mut wdp, &q, mw_api.clone()).await?;
+
+        // Skip IDs that contradict the item, or are already on another item
+        let mut skipped = author.conflicting_ids(&original_item);
+        let mut added = vec![];
+        let mut ids: Vec<(String, String)> =
+            author.prop2id().iter().map(|(p, id)| (p.to_owned(), id.to_owned())).collect();
+        ids.sort();
+        for (prop, id) in ids {
+            if skipped.iter().any(|(p, _)| *p == prop) {
+                author.prop2id_mut().remove(&prop);
+                continue;
+            }
+            if item_has_id(&original_item, &prop, &id) {
+                continue;
+            }
+            match self.cache.get_unique(&prop, &id).await {
+                Ok(Some(other)) if other != q => {
+                    author.prop2id_mut().remove(&prop);
+                    skipped.push((prop, format!("{id} on {other}")));
+                },
+                Err(LookupError::Ambiguous(others)) => {
+                    author.prop2id_mut().remove(&prop);
+                    skipped.push((prop, format!("{id} on {}", others.join(", "))));
+                },
+                Err(LookupError::Failed(error)) => return Err(error),
+                _ => added.push(format!("{prop}:{id}")),
+            }
+        }
+
+        let mut item = original_item.clone();
+        author.amend_author_item(&mut item);
+        let edited = match wdp.apply_diff_for_item(original_item, item, mw_api).await? {
+            Some(er) => er.edited(),
+            None => false,
+        };
+
+        let mut note = match (edited, added.is_empty()) {
+            (true, true) => "edited".to_string(),
+            (true, false) => format!("edited, added {}", added.join(", ")),
+            (false, _) => "not edited".to_string(),
+        };
+        if !skipped.is_empty() {
+            let skipped: Vec<String> = skipped.iter().map(|(p, id)| format!("{p}:{id}")).collect();
+            note += &format!("; skipped conflicting {}", skipped.join(", "));
+        }
+        command.note = note;
         Ok(true)
     }
 
+    async fn load_item(
+        wdp: &mut WikidataPapers,
+        q: &str,
+        mw_api: Arc<RwLock<Api>>,
+    ) -> Result<Entity> {
+        let api = mw_api.read().await;
+        Ok(wdp
+            .entities_mut()
+            .load_entity(&api, q.to_owned())
+            .await
+            .with_context(|| format!("load_entity({q})"))?
+            .to_owned())
+    }
+
+    /// EDIT_PAPER_FOR_ORCID_AUTHOR: `<paper>|<ORCID>`. Only upgrades an
+    /// existing P2093; never adds an author the paper doesn't list.
+    async fn process_paper_for_orcid_author(&self, command: &mut SourceMDcommand) -> Result<bool> {
+        let (paper, orcid) = command.paper_and_author().ok_or_else(|| {
+            anyhow!(
+                "Expected '<paper>|<ORCID>' on command #{}, got '{}'",
+                command.id,
+                command.identifier
+            )
+        })?;
+        if !crate::identifiers::is_orcid(&orcid) {
+            return Err(anyhow!("Not an ORCID ID: '{orcid}'"));
+        }
+        self.attach_author_to_paper(command, &paper, &orcid, false).await
+    }
+
+    /// ADD_AUTHOR_TO_PUBLICATION: `<paper>|<author>`, with a Q-ID or ORCID as
+    /// author. A bare paper identifier is handled like CREATE_PAPER_BY_ID.
+    async fn process_add_author_to_publication(
+        &self,
+        command: &mut SourceMDcommand,
+    ) -> Result<bool> {
+        match command.paper_and_author() {
+            Some((paper, author)) => {
+                self.attach_author_to_paper(command, &paper, &author, true).await
+            },
+            None => self.process_paper(command).await,
+        }
+    }
+
+    /// Makes `author_id` (Q-ID or ORCID) a P50 of `paper`. The P2093 that
+    /// matches one of the author's names is turned into the P50, keeping
+    /// the name string as P1932 and its P1545. Failing that, and only with
+    /// `add_missing`, a new P50 is added at the author's position in the
+    /// source author list, as long as no other author has that ordinal.
+    /// With `add_missing`, a paper that isn't on Wikidata yet is created.
+    /// The author item is only created once there is something to attach.
+    async fn attach_author_to_paper(
+        &self,
+        command: &mut SourceMDcommand,
+        paper: &str,
+        author_id: &str,
+        add_missing: bool,
+    ) -> Result<bool> {
+        let mut wdp = self.new_wdp(command).await;
+        wdp.set_edit_summary(Some(self.edit_summary(command)));
+        let mw_api = self.config.read().await.mw_api();
+
+        let paper_q = match self.get_paper_items(&wdp, paper).await?.as_slice() {
+            [q] => q.to_owned(),
+            [] if add_missing => {
+                let mut paper_command = command.clone();
+                paper_command.identifier = paper.to_string();
+                paper_command.q.clear();
+                if !self.process_paper(&mut paper_command).await? || paper_command.q.is_empty() {
+                    command.note = format!("Could not find or create paper {paper}");
+                    return Ok(false);
+                }
+                paper_command.q
+            },
+            [] => {
+                command.note = format!("No Wikidata item for paper {paper}");
+                return Ok(false);
+            },
+            items => {
+                command.note = format!("Paper {paper} is on several items: {}", items.join(", "));
+                return Ok(false);
+            },
+        };
+
+        let author = self.get_author_info(author_id).await?;
+        let mut qs = vec![paper_q.to_owned()];
+        qs.extend(author.wikidata_item().map(|q| q.to_string()));
+        {
+            let api = mw_api.read().await;
+            wdp.entities_mut()
+                .load_entities(&api, &qs)
+                .await
+                .with_context(|| format!("load_entities({})", qs.join(",")))?;
+        }
+        let original_item = wdp
+            .entities_mut()
+            .get_entity(paper_q.to_owned())
+            .ok_or_else(|| anyhow!("Could not load paper item {paper_q}"))?;
+        command.q = paper_q.to_owned();
+
+        let mut names: Vec<String> = author.name().map(|s| s.to_string()).into_iter().collect();
+        if let Some(q) = author.wikidata_item() {
+            if original_item.has_target_entity("P50", q) {
+                command.note = format!("{q} is already an author of {paper_q}");
+                return Ok(true);
+            }
+            let label = wdp
+                .entities_mut()
+                .get_entity(q.to_owned())
+                .and_then(|i| i.label_in_locale("en").map(|s| s.to_string()));
+            if let Some(label) = label {
+                if !names.contains(&label) {
+                    names.push(label);
+                }
+            }
+        }
+        if names.is_empty() {
+            command.note = format!("No name known for {author_id}, can't match authors");
+            return Ok(false);
+        }
+
+        let mut item = original_item.clone();
+        let note = match WikidataPapers::find_author_name_string(&item, &names) {
+            Some(ans) => {
+                let ans_name = ans.name().unwrap_or_default().to_string();
+                let author_q = self.create_author_item(&author, author_id).await?;
+                let mut p50_author = GenericAuthorInfo::new();
+                p50_author.set_wikidata_item(Some(author_q.to_owned()));
+                p50_author.set_list_number(ans.list_number().map(|s| s.to_string()));
+                wdp.update_author_name_statement(&ans_name, &p50_author, &mut item);
+                format!("'{ans_name}' => {author_q}")
+            },
+            None if add_missing => {
+                let source_authors = wdp.get_source_authors(&item).await?;
+                let mut probe = author.clone();
+                if probe.name().is_none() {
+                    probe.set_name(names.first().cloned());
+                }
+                let source_author = match probe.find_best_match(&source_authors) {
+                    Some((candidate, _points)) => source_authors[candidate].to_owned(),
+                    None => {
+                        command.note =
+                            format!("{author_id} is not in the author list of {paper_q}");
+                        return Ok(false);
+                    },
+                };
+                let ordinal = match source_author.list_number() {
+                    Some(ordinal) => ordinal.to_string(),
+                    None => {
+                        command.note =
+                            format!("No position for {author_id} in the author list of {paper_q}");
+                        return Ok(false);
+                    },
+                };
+                if WikidataPapers::author_ordinals(&item).contains(&ordinal) {
+                    command.note = format!(
+                        "Author #{ordinal} on {paper_q} is already set, not adding {author_id}"
+                    );
+                    return Ok(false);
+                }
+                let author_q = self.create_author_item(&author, author_id).await?;
+                let mut p50_author = GenericAuthorInfo::new();
+                p50_author.set_name(source_author.name().map(|s| s.to_string()));
+                p50_author.set_wikidata_item(Some(author_q.to_owned()));
+                p50_author.set_list_number(Some(ordinal.to_owned()));
+                p50_author.create_author_statement_in_paper_item(&mut item);
+                format!("{author_q} added as author #{ordinal}")
+            },
+            None => {
+                command.note = format!(
+                    "No author name string on {paper_q} matches {author_id} ({})",
+                    names.join(" / ")
+                );
+                return Ok(false);
+            },
+        };
+
+        match wdp.apply_diff_for_item(original_item, item, mw_api).await? {
+            Some(er) => {
+                command.note = match er.edited() {
+                    true => note,
+                    false => format!("{} already up to date", er.q()),
+                };
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Resolves a paper identifier (Q-ID, DOI, PMID, PMCID) to the items
+    /// that carry it. Does not create anything.
+    async fn get_paper_items(&self, wdp: &WikidataPapers, paper: &str) -> Result<Vec<String>> {
+        if crate::identifiers::is_qid(paper) {
+            return Ok(vec![paper.to_string()]);
+        }
+        let ids = GenericWorkIdentifier::parse_ids_from_str(paper);
+        wdp.get_items_for_ids(&ids).await
+    }
+
     async fn process_paper(&self, command: &mut SourceMDcommand) -> Result<bool> {
-        let mut wdp = self.new_wdp(command);
-        wdp.set_edit_summary(Some(format!(
-            "SourceMD [rust bot], [https://sourcemd.toolforge.org/?action=batch&batch={} batch #{}], command #{}",
-            self.batch_id, self.batch_id, command.serial_number
-        )));
+        let mut wdp = self.new_wdp(command).await;
+        wdp.set_edit_summary(Some(self.edit_summary(command)));
 
         // Wikidata ID
         if crate::identifiers::is_qid(&command.identifier) {
@@ -144,9 +536,18 @@ impl SourceMDbot {
                 )
                 .await
                 .with_context(|| format!("update {}", command.identifier))?;
-            return result
-                .map(|_| true)
-                .ok_or_else(|| anyhow!("Can't update {}", command.identifier));
+            return match result {
+                ItemUpdate::Done(er) => {
+                    Self::note_discrepancies(command, &er);
+                    Ok(true)
+                },
+                ItemUpdate::Skipped(reason) => {
+                    Err(anyhow!("Can't update {}: {reason}", command.identifier))
+                },
+                ItemUpdate::Duplicates(candidate) => {
+                    Err(anyhow!("Can't update {}: duplicate items {candidate}", command.identifier))
+                },
+            };
         }
 
         // Others: regex-recognised formats
@@ -181,16 +582,135 @@ impl SourceMDbot {
             .await
             .with_context(|| format!("create_or_update for command #{}", command.id))?;
         match result {
-            Some(er) => {
+            ItemUpdate::Done(er) => {
                 if command.q.is_empty() {
                     command.q = er.q().to_string();
                 }
+                Self::note_discrepancies(command, &er);
+                if let Some(candidate) = er.merge_candidate() {
+                    let note = format!("Merge candidate: {candidate}");
+                    command.note = if command.note.is_empty() {
+                        note
+                    } else {
+                        format!("{}; {note}", command.note)
+                    };
+                }
+                Ok(true)
+            },
+            ItemUpdate::Skipped(reason) => {
+                command.note = format!("Skipped: {reason}");
+                Ok(false)
+            },
+            // Recorded in the command note, for batch owners to merge by hand
+            ItemUpdate::Duplicates(candidate) => {
+                command.note = format!("Duplicate items, merge candidate: {candidate}");
+                Ok(false)
+            },
+        }
+    }
+
+    /// Records where the adapters disagreed in the command note, so that
+    /// batch owners can check those values by hand.
+    fn note_discrepancies(command: &mut SourceMDcommand, er: &EditResult) {
+        if !er.discrepancies().is_empty() {
+            command.note = format!("Discrepancies: {}", er.discrepancies());
+        }
+    }
+
+    /// Looks up or creates the book item for the ISBN in `command`, filling
+    /// in metadata from Open Library. Only missing statements are added to
+    /// an existing item.
+    async fn process_book(&self, command: &mut SourceMDcommand) -> Result<bool> {
+        let isbn = Isbn::parse(&command.identifier)
+            .ok_or_else(|| anyhow!("Invalid ISBN '{}'", command.identifier))?;
+        let mut wdp = self.new_wdp(command).await;
+        wdp.set_edit_summary(Some(self.edit_summary(command)));
+        let mw_api = self.config.read().await.mw_api();
+
+        let ids = GenericWorkIdentifier::new_isbn(&isbn);
+        let record = OpenLibrary::default().get_book(&isbn).await;
+        let items = wdp.get_items_for_ids(&ids).await?;
+        if items.len() > 1 {
+            command.note = format!("ISBN {isbn} is on several items: {}", items.join(", "));
+            return Ok(false);
+        }
+        let mut item = match items.first() {
+            Some(q) => Self::load_item(&mut wdp, q, mw_api.clone()).await?,
+            None => Entity::new_empty_item(),
+        };
+        let original_item = item.clone();
+        let is_new = original_item.id().is_empty();
+
+        let record = match record {
+            Some(record) => record,
+            None if is_new => {
+                command.note = format!("No book metadata found for ISBN {isbn}");
+                return Ok(false);
+            },
+            None => BookRecord::default(),
+        };
+        if is_new && record.full_title().is_none() {
+            command.note = format!("No title for ISBN {isbn}, not creating an item");
+            return Ok(false);
+        }
+
+        wdp.update_item_with_ids(&mut item, &ids);
+        record.amend_book_item(&mut item);
+        if !item.has_claims_with_property("P123") {
+            if let Some(q) = self.get_publisher_item(&wdp, &record, mw_api.clone()).await {
+                item.add_claim(Statement::new_normal(
+                    Snak::new_item("P123", &q),
+                    vec![],
+                    record.reference(),
+                ));
+            }
+        }
+
+        match wdp.apply_diff_for_item(original_item, item, mw_api).await? {
+            Some(er) => {
+                command.q = er.q().to_string();
+                if !er.edited() {
+                    command.note = format!("{} already up to date", er.q());
+                }
                 Ok(true)
             },
             None => Ok(false),
         }
     }
 
+    /// Resolves the first publisher name to a Wikidata item, but only if
+    /// the search has exactly one hit among publishers (Q2085381).
+    async fn get_publisher_item(
+        &self,
+        wdp: &WikidataPapers,
+        record: &BookRecord,
+        mw_api: Arc<RwLock<Api>>,
+    ) -> Option<String> {
+        let name = record.publishers.first()?;
+        let query = format!("\"{}\" haswbstatement:P31=Q2085381", name.replace('"', ""));
+        match wdp.search_wikibase(&query, mw_api).await {
+            Ok(items) if items.len() == 1 => items.first().cloned(),
+            Ok(items) => {
+                tracing::info!(publisher = %name, hits = items.len(), "no unique publisher item");
+                None
+            },
+            Err(e) => {
+                tracing::warn!(publisher = %name, error = %e, "publisher search failed");
+                None
+            },
+        }
+    }
+
+    fn edit_summary(&self, command: &SourceMDcommand) -> String {
+        if self.batch_id == 0 {
+            return "SourceMD [rust bot], command line".to_string();
+        }
+        format!(
+            "SourceMD [rust bot], [https://sourcemd.toolforge.org/?action=batch&batch={} batch #{}], command #{}",
+            self.batch_id, self.batch_id, command.serial_number
+        )
+    }
+
     async fn set_command_status(
         &self,
         status: &str,
@@ -214,8 +734,14 @@ impl SourceMDbot {
         self.config.read().await.get_next_command(self.batch_id).await
     }
 
-    fn new_wdp(&self, _command: &SourceMDcommand) -> WikidataPapers {
-        WikidataPapers::with_default_adapters(self.cache.clone())
+    async fn new_wdp(&self, _command: &SourceMDcommand) -> WikidataPapers {
+        let mut wdp = WikidataPapers::with_default_adapters(self.cache.clone());
+        let config = self.config.read().await;
+        wdp.set_create_journals(config.create_journals());
+        wdp.set_duplicate_policy(config.duplicate_policy());
+        drop(config);
+        wdp.set_output(self.output.clone());
+        wdp
     }
 }
 
@@ -225,7 +751,10 @@ mod tests {
     use wiremock::MockServer;
 
     use super::*;
-    use crate::test_helpers::start_mediawiki_mock_server as start_mock_server;
+    use crate::test_helpers::{add_search_mock, start_mediawiki_mock_server as start_mock_server};
+
+    const SEARCH_EMPTY: &str = include_str!("../test_data/search_empty.json");
+    const SEARCH_MULTIPLE: &str = include_str!("../test_data/search_found_multiple.json");
 
     /// Build a SourceMDbot directly from a wiremock-backed SourceMD with no DB
     /// pool — bypasses the normal `new()` which would call `restart_batch()`
@@ -235,39 +764,115 @@ mod tests {
         let mw_api = Arc::new(RwLock::new(api));
         let config = SourceMD::new_for_testing(mw_api.clone());
         let cache = Arc::new(WikidataStringCache::new(mw_api));
-        SourceMDbot { config: Arc::new(RwLock::new(config)), cache, batch_id: 1 }
+        SourceMDbot::with_batch(Arc::new(RwLock::new(config)), cache, 1)
     }
 
     #[tokio::test]
-    async fn execute_command_create_book_from_isbn_returns_false() {
+    async fn execute_command_create_book_from_isbn_rejects_bad_checksum() {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
-        let mut cmd = SourceMDcommand::new_dummy("978-3-16-148410-0");
+        let mut cmd = SourceMDcommand::new_dummy("978-3-16-148410-1");
         cmd.mode = SourceMDcommandMode::CreateBookFromIsbn;
-        let result = bot.execute_command(&mut cmd).await.unwrap();
-        assert!(!result, "CreateBookFromIsbn is unimplemented and should return Ok(false)");
+        let err = bot.execute_command(&mut cmd).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid ISBN"), "unexpected error: {err}");
+        assert!(cmd.q.is_empty());
     }
 
     #[tokio::test]
-    async fn execute_command_edit_paper_for_orcid_author_returns_false() {
+    async fn execute_command_edit_paper_for_orcid_author_requires_paper() {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
         let mut cmd = SourceMDcommand::new_dummy("0000-0001-2345-6789");
         cmd.mode = SourceMDcommandMode::EditPaperForOrcidAuthor;
-        let result = bot.execute_command(&mut cmd).await.unwrap();
-        assert!(!result, "EditPaperForOrcidAuthor is unimplemented and should return Ok(false)");
+        let err = bot.execute_command(&mut cmd).await.unwrap_err();
+        assert!(err.to_string().contains("Expected '<paper>|<ORCID>'"), "unexpected error: {err}");
     }
 
     #[tokio::test]
-    async fn execute_command_add_orcid_metadata_returns_false() {
-        // The current implementation short-circuits with `if true { Ok(false) }`,
-        // so this should return false without touching the network.
+    async fn execute_command_edit_paper_for_orcid_author_rejects_non_orcid() {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy("Q123|Q42");
+        cmd.mode = SourceMDcommandMode::EditPaperForOrcidAuthor;
+        let err = bot.execute_command(&mut cmd).await.unwrap_err();
+        assert!(err.to_string().contains("Not an ORCID ID"), "unexpected error: {err}");
+    }
+
+    #[tokio::test]
+    async fn execute_command_edit_paper_for_orcid_author_notes_unknown_paper() {
+        // No search mock: the DOI lookup finds nothing, so the command
+        // stops before ORCID is queried.
+        let mock_server = start_mock_server().await;
+        let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy("10.1234/nope|0000-0001-2345-6789");
+        cmd.mode = SourceMDcommandMode::EditPaperForOrcidAuthor;
+        assert!(!bot.execute_command(&mut cmd).await.unwrap());
+        assert_eq!(cmd.note, "No Wikidata item for paper 10.1234/nope");
+    }
+
+    #[tokio::test]
+    async fn execute_command_add_author_without_author_falls_back_to_paper() {
+        // No recognisable paper ID either, so process_paper gives up early
+        let mock_server = start_mock_server().await;
+        let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy("garbage");
+        cmd.mode = SourceMDcommandMode::AddAutthorToPublication;
+        assert!(!bot.execute_command(&mut cmd).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn execute_command_add_author_rejects_unknown_author() {
+        let mock_server = start_mock_server().await;
+        let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy(r#"{"paper":"Q123","author":"John Smith"}"#);
+        cmd.mode = SourceMDcommandMode::AddAutthorToPublication;
+        let err = bot.execute_command(&mut cmd).await.unwrap_err();
+        assert!(err.to_string().contains("Not a Wikidata item, nor an ORCID ID"));
+    }
+
+    #[tokio::test]
+    async fn execute_command_add_orcid_metadata_needs_item_for_orcid() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P496=0000-0001-2345-6789", SEARCH_EMPTY)
+            .await;
+        let bot = make_bot(&mock_server).await;
         let mut cmd = SourceMDcommand::new_dummy("0000-0001-2345-6789");
         cmd.mode = SourceMDcommandMode::AddOrcidMetadataToAuthor;
-        let result = bot.execute_command(&mut cmd).await.unwrap();
-        assert!(!result);
+        assert!(!bot.execute_command(&mut cmd).await.unwrap());
+        assert_eq!(cmd.note, "No item has ORCID 0000-0001-2345-6789");
+        assert!(cmd.q.is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_command_add_orcid_metadata_refuses_ambiguous_orcid() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P496=0000-0002-1825-0097", SEARCH_MULTIPLE)
+            .await;
+        let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy("0000-0002-1825-0097");
+        cmd.mode = SourceMDcommandMode::AddOrcidMetadataToAuthor;
+        assert!(!bot.execute_command(&mut cmd).await.unwrap());
+        assert_eq!(cmd.note, "ORCID 0000-0002-1825-0097 is on several items: Q15757256, Q46664291");
+        assert!(cmd.q.is_empty());
+    }
+
+    #[tokio::test]
+    async fn execute_command_add_orcid_metadata_rejects_unknown_identifier() {
+        let mock_server = start_mock_server().await;
+        let bot = make_bot(&mock_server).await;
+        let mut cmd = SourceMDcommand::new_dummy("not-an-id");
+        cmd.mode = SourceMDcommandMode::AddOrcidMetadataToAuthor;
+        let err = bot.execute_command(&mut cmd).await.unwrap_err();
+        assert!(err.to_string().contains("Not a Wikidata item, nor an ORCID ID"));
+    }
+
+    #[tokio::test]
+    async fn standalone_bot_uses_command_line_summary() {
+        let mock_server = start_mock_server().await;
+        let bot = make_bot(&mock_server).await;
+        let bot = SourceMDbot::new_standalone(bot.config, bot.cache);
+        let cmd = SourceMDcommand::new_dummy("x");
+        assert_eq!(bot.edit_summary(&cmd), "SourceMD [rust bot], command line");
     }
 
     #[tokio::test]
@@ -295,28 +900,51 @@ mod tests {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
         let cmd = SourceMDcommand::new_dummy("x");
-        let mut wdp = bot.new_wdp(&cmd);
+        let mut wdp = bot.new_wdp(&cmd).await;
         // PMC, Pubmed, Crossref, Semanticscholar, Orcid, Arxiv, OpenAlex,
         // DataCite, EuropePMC = 9 adapters
         assert_eq!(wdp.adapters_mut().len(), 9);
     }
 
+    #[test]
+    fn prefetch_keys_per_mode() {
+        let command = |mode, identifier| {
+            let mut command = SourceMDcommand::new_dummy(identifier);
+            command.mode = mode;
+            SourceMDbot::prefetch_keys(&command)
+        };
+        let pair = |p: &str, k: &str| (p.to_string(), k.to_string());
+        assert_eq!(
+            command(SourceMDcommandMode::CreatePaperById, "10.1000/xyz"),
+            vec![pair("P356", "10.1000/XYZ")]
+        );
+        assert_eq!(
+            command(SourceMDcommandMode::EditPaperForOrcidAuthor, "12345|0000-0002-1825-0097"),
+            vec![pair("P698", "12345"), pair("P496", "0000-0002-1825-0097")]
+        );
+        assert_eq!(
+            command(SourceMDcommandMode::AddOrcidMetadataToAuthor, "Q42"),
+            Vec::<(String, String)>::new()
+        );
+        assert_eq!(command(SourceMDcommandMode::CreateBookFromIsbn, "0306406152").len(), 4);
+    }
+
     #[tokio::test]
-    async fn get_author_item_rejects_unknown_identifier() {
+    async fn get_author_info_rejects_unknown_identifier() {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
         // Not a Q-id and not an ORCID -> error
-        let err = bot.get_author_item("not-an-id").await.unwrap_err();
+        let err = bot.get_author_info("not-an-id").await.unwrap_err();
         assert!(err.to_string().contains("Not a Wikidata item, nor an ORCID ID"));
     }
 
     #[tokio::test]
-    async fn get_author_item_accepts_wikidata_qid() {
+    async fn get_author_info_accepts_wikidata_qid() {
         let mock_server = start_mock_server().await;
         let bot = make_bot(&mock_server).await;
         // Pure-regex branch: a valid Q-id is set as the wikidata_item without
         // any further lookups.
-        let author = bot.get_author_item("Q42").await.unwrap();
+        let author = bot.get_author_info("Q42").await.unwrap();
         assert_eq!(author.wikidata_item().map(str::to_string), Some("Q42".to_string()));
     }
 }
@@ -85,6 +85,28 @@ impl SourceMDcommand {
         })
     }
 
+    /// Splits an identifier that names both a paper and an author, given
+    /// either as `<paper>|<author>` or as JSON `{"paper":…, "author":…}`
+    /// (`"orcid"` is accepted in place of `"author"`).
+    pub fn paper_and_author(&self) -> Option<(String, String)> {
+        let identifier = self.identifier.trim();
+        let (paper, author) = match serde_json::from_str::<serde_json::Value>(identifier) {
+            Ok(j) => (
+                j["paper"].as_str()?.to_string(),
+                j["author"].as_str().or_else(|| j["orcid"].as_str())?.to_string(),
+            ),
+            Err(_) => {
+                let (paper, author) = identifier.split_once('|')?;
+                (paper.to_string(), author.to_string())
+            },
+        };
+        let (paper, author) = (paper.trim(), author.trim());
+        if paper.is_empty() || author.is_empty() {
+            return None;
+        }
+        Some((paper.to_string(), author.to_string()))
+    }
+
     fn rowvalue_as_i64(v: &my::Value) -> i64 {
         match v {
             my::Value::Int(x) => *x,
@@ -131,6 +153,33 @@ mod tests {
         assert_eq!(SourceMDcommand::rowvalue_as_string(&v), "abc");
     }
 
+    #[test]
+    fn paper_and_author_pipe_separated() {
+        let cmd = SourceMDcommand::new_dummy("10.1234/abc | 0000-0001-2345-6789");
+        assert_eq!(
+            cmd.paper_and_author(),
+            Some(("10.1234/abc".to_string(), "0000-0001-2345-6789".to_string()))
+        );
+    }
+
+    #[test]
+    fn paper_and_author_json() {
+        let cmd = SourceMDcommand::new_dummy(r#"{"paper":"Q123","orcid":"0000-0001-2345-6789"}"#);
+        assert_eq!(
+            cmd.paper_and_author(),
+            Some(("Q123".to_string(), "0000-0001-2345-6789".to_string()))
+        );
+        let cmd = SourceMDcommand::new_dummy(r#"{"paper":"PMC12345","author":"Q42"}"#);
+        assert_eq!(cmd.paper_and_author(), Some(("PMC12345".to_string(), "Q42".to_string())));
+    }
+
+    #[test]
+    fn paper_and_author_rejects_incomplete() {
+        assert_eq!(SourceMDcommand::new_dummy("0000-0001-2345-6789").paper_and_author(), None);
+        assert_eq!(SourceMDcommand::new_dummy("Q123|").paper_and_author(), None);
+        assert_eq!(SourceMDcommand::new_dummy(r#"{"paper":"Q123"}"#).paper_and_author(), None);
+    }
+
     // TODO:
     // pub fn new_from_row(row: my::Row) -> Self {
 }
@@ -11,7 +11,12 @@ use tokio::sync::RwLock;
 use tracing::info;
 use wikibase::mediawiki::api::Api;
 
-use crate::sourcemd_command::SourceMDcommand;
+use crate::{
+    identifier_index::IdentifierIndex,
+    merge_candidate::DuplicatePolicy,
+    persistent_cache::{PersistentCache, DEFAULT_NEGATIVE_TTL, DEFAULT_TTL},
+    sourcemd_command::SourceMDcommand,
+};
 
 #[derive(Debug, Clone)]
 pub struct SourceMD {
@@ -19,6 +24,8 @@ pub struct SourceMD {
     failed_batch_ids: DashSet<i64>,
     pool: Option<my::Pool>,
     mw_api: Arc<RwLock<Api>>,
+    create_journals: bool,
+    duplicate_policy: DuplicatePolicy,
 }
 
 impl SourceMD {
@@ -29,6 +36,8 @@ impl SourceMD {
             failed_batch_ids: DashSet::new(),
             pool: None,
             mw_api,
+            create_journals: false,
+            duplicate_policy: DuplicatePolicy::default(),
         }
     }
 
@@ -38,6 +47,8 @@ impl SourceMD {
             failed_batch_ids: DashSet::new(),
             pool: None,
             mw_api: Arc::new(RwLock::new(Self::create_mw_api(ini_file).await?)),
+            create_journals: Self::create_journals_from_ini(ini_file),
+            duplicate_policy: Self::duplicate_policy_from_ini(ini_file),
         })
     }
 
@@ -49,6 +60,87 @@ impl SourceMD {
         self.mw_api.clone()
     }
 
+    /// Whether missing journal items may be created for papers
+    pub fn create_journals(&self) -> bool {
+        self.create_journals
+    }
+
+    /// Reads `create_journals` from the `[papers]` section of the ini file.
+    /// Defaults to `false` if the file or the setting is missing.
+    pub fn create_journals_from_ini(ini_file: &str) -> bool {
+        Config::builder()
+            .add_source(File::with_name(ini_file))
+            .build()
+            .and_then(|settings| settings.get_bool("papers.create_journals"))
+            .unwrap_or(false)
+    }
+
+    /// What to do with papers whose IDs are on several items
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    /// Reads `duplicates` (`refuse` or `oldest`) from the `[papers]` section
+    /// of the ini file. Defaults to refusing if the file or the setting is
+    /// missing or invalid.
+    pub fn duplicate_policy_from_ini(ini_file: &str) -> DuplicatePolicy {
+        Config::builder()
+            .add_source(File::with_name(ini_file))
+            .build()
+            .and_then(|settings| settings.get_string("papers.duplicates"))
+            .ok()
+            .and_then(|policy| policy.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Opens the persistent identifier cache at `file`, or else at
+    /// `cache_file` from the `[papers]` section of the ini file. Entries
+    /// expire after `cache_ttl_hours`, or `cache_negative_ttl_hours` if no
+    /// item was found. `None` if no file is given.
+    pub fn persistent_cache_from_ini(
+        ini_file: &str,
+        file: Option<&str>,
+    ) -> Result<Option<PersistentCache>> {
+        let settings = Config::builder().add_source(File::with_name(ini_file)).build().ok();
+        let setting = |key: &str| settings.as_ref().and_then(|s| s.get_string(key).ok());
+        let Some(file) = file.map(|file| file.to_string()).or_else(|| setting("papers.cache_file"))
+        else {
+            return Ok(None);
+        };
+        let hours = |key: &str| {
+            let hours = settings.as_ref().and_then(|s| s.get_int(key).ok())?;
+            Some(std::time::Duration::from_secs(u64::try_from(hours).ok()? * 3600))
+        };
+        let ttl = hours("papers.cache_ttl_hours").unwrap_or(DEFAULT_TTL);
+        let negative_ttl = hours("papers.cache_negative_ttl_hours").unwrap_or(DEFAULT_NEGATIVE_TTL);
+        let cache = PersistentCache::open(&file, ttl, negative_ttl)
+            .with_context(|| format!("opening cache file '{file}'"))?;
+        info!(file, entries = cache.len(), "persistent cache opened");
+        Ok(Some(cache))
+    }
+
+    /// Opens the identifier index at `file`, or else at `id_index` from the
+    /// `[papers]` section of the ini file. `None` if no file is given.
+    pub fn identifier_index_from_ini(
+        ini_file: &str,
+        file: Option<&str>,
+    ) -> Result<Option<IdentifierIndex>> {
+        let file = match file {
+            Some(file) => file.to_string(),
+            None => match Config::builder()
+                .add_source(File::with_name(ini_file))
+                .build()
+                .and_then(|settings| settings.get_string("papers.id_index"))
+            {
+                Ok(file) => file,
+                Err(_) => return Ok(None),
+            },
+        };
+        let index = IdentifierIndex::open(&file)
+            .with_context(|| format!("opening identifier index '{file}'"))?;
+        Ok(Some(index))
+    }
+
     /// Borrow the configured MySQL pool, or return a contextual error if
     /// `init()` has not been run / the connection could not be established.
     fn pool(&self) -> Result<&my::Pool> {
@@ -111,7 +203,7 @@ impl SourceMD {
     ///   confuse this with "no work").
     pub async fn get_next_batch(&self) -> Result<Option<i64>> {
         let mut conn = self.conn().await?;
-        let sql = r#"SELECT * FROM batch WHERE `status` ='TODO' AND NOT EXISTS (SELECT * FROM command WHERE batch_id=batch.id AND `status` IN ("RUNNING","TODO") AND `mode` NOT IN ("CREATE_PAPER_BY_ID","ADD_AUTHOR_TO_PUBLICATION")) ORDER BY `last_action`"#;
+        let sql = r#"SELECT * FROM batch WHERE `status` ='TODO' AND NOT EXISTS (SELECT * FROM command WHERE batch_id=batch.id AND `status` IN ("RUNNING","TODO") AND `mode` NOT IN ("CREATE_PAPER_BY_ID","ADD_AUTHOR_TO_PUBLICATION","ADD_METADATA_FROM_ORCID_TO_AUTHOR","EDIT_PAPER_FOR_ORCID_AUTHOR","CREATE_BOOK_FROM_ISBN")) ORDER BY `last_action`"#;
         // SELECT returns a small candidate set (TODO batches only); collecting
         // ids into a Vec avoids juggling streaming-iterator lifetimes for the
         // running/failed-set filter that follows.
@@ -195,6 +287,21 @@ impl SourceMD {
         Ok(row.and_then(SourceMDcommand::new_from_row))
     }
 
+    /// The first `limit` TODO commands of a batch, in order.
+    pub async fn get_todo_commands(
+        &self,
+        batch_id: i64,
+        limit: usize,
+    ) -> Result<Vec<SourceMDcommand>> {
+        let mut conn = self.conn().await?;
+        let sql = r#"SELECT * FROM command FORCE INDEX (batch_id_4) WHERE `batch_id`=? AND `status`='TODO' ORDER BY `serial_number` LIMIT ?"#;
+        let rows: Vec<my::Row> = conn
+            .exec(sql, (batch_id, limit))
+            .await
+            .with_context(|| format!("get_todo_commands: batch {batch_id}"))?;
+        Ok(rows.into_iter().filter_map(SourceMDcommand::new_from_row).collect())
+    }
+
     pub async fn set_command_status(
         &self,
         command: &mut SourceMDcommand,
@@ -356,6 +463,17 @@ mod tests {
         SourceMD::new_for_testing(Arc::new(RwLock::new(api)))
     }
 
+    #[test]
+    fn create_journals_from_ini() {
+        use std::io::Write;
+        let mut f = tempfile::Builder::new().suffix(".ini").tempfile().unwrap();
+        writeln!(f, "[user]\nuser = test").unwrap();
+        assert!(!SourceMD::create_journals_from_ini(f.path().to_str().unwrap()));
+        writeln!(f, "[papers]\ncreate_journals = true").unwrap();
+        assert!(SourceMD::create_journals_from_ini(f.path().to_str().unwrap()));
+        assert!(!SourceMD::create_journals_from_ini("/nonexistent/bot.ini"));
+    }
+
     #[tokio::test]
     async fn timestamp_format() {
         let mock_server = start_mock_server().await;
@@ -9,6 +9,11 @@
 
 #![cfg(test)]
 
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
 use wiremock::matchers::{method, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -33,3 +38,37 @@ pub(crate) async fn start_mediawiki_mock_server() -> MockServer {
         .await;
     mock_server
 }
+
+/// Registers a search mock for a specific `srsearch` value.
+pub(crate) async fn add_search_mock(mock_server: &MockServer, srsearch: &str, body: &'static str) {
+    Mock::given(method("GET"))
+        .and(query_param("srsearch", srsearch))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/json; charset=utf-8")
+                .set_body_string(body),
+        )
+        .mount(mock_server)
+        .await;
+}
+
+/// An in-memory writer whose clones share one buffer, for tests of the
+/// report writers.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub(crate) fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
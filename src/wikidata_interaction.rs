@@ -5,6 +5,8 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 use wikibase::{entity_diff::*, mediawiki::api::Api, *};
 
+use crate::edit_output::EditOutput;
+
 #[async_trait]
 pub trait WikidataInteraction {
     async fn search_wikibase(&self, query: &str, mw_api: Arc<RwLock<Api>>) -> Result<Vec<String>> {
@@ -28,20 +30,28 @@ pub trait WikidataInteraction {
     }
 
     /// Creates a new Wikidata entity from `item`'s diff against an empty
-    /// item. Returns `Ok(None)` if the diff is empty (nothing to write);
-    /// `Err(_)` if the underlying API call fails so callers can mark the
-    /// command failed instead of silently dropping the write.
+    /// item. Returns `Ok(None)` if the diff is empty (nothing to write) or
+    /// `output` isn't live (the creation is only reported); `Err(_)` if the
+    /// underlying API call fails so callers can mark the command failed
+    /// instead of silently dropping the write.
     async fn create_item(
         &self,
         item: &Entity,
         mw_api: Arc<RwLock<Api>>,
+        output: &EditOutput,
     ) -> Result<Option<String>> {
+        const SUMMARY: &str = "(automated edit by SourceMD)";
         let params = EntityDiffParams::all();
-        let mut diff = EntityDiff::new(&Entity::new_empty_item(), item, &params);
-        diff.set_edit_summary(Some("(automated edit by SourceMD)".to_string()));
+        let original = Entity::new_empty_item();
+        let mut diff = EntityDiff::new(&original, item, &params);
+        diff.set_edit_summary(Some(SUMMARY.to_string()));
         if diff.is_empty() {
             return Ok(None);
         }
+        if !output.is_live() {
+            output.report(&original, Some(SUMMARY), &diff);
+            return Ok(None);
+        }
         // Write lock is held for the duration of `apply_diff` because
         // `wikibase::EntityDiff::apply_diff` requires `&mut Api` (its
         // internal `get_edit_token` mutates the CSRF cache; the actual
@@ -61,13 +71,10 @@ pub trait WikidataInteraction {
 
 #[cfg(test)]
 mod tests {
-    use wiremock::{
-        matchers::{method, query_param},
-        Mock, MockServer, ResponseTemplate,
-    };
+    use wiremock::MockServer;
 
     use super::*;
-    use crate::test_helpers::start_mediawiki_mock_server as start_mock_server;
+    use crate::test_helpers::{add_search_mock, start_mediawiki_mock_server as start_mock_server};
 
     const SEARCH_Q46664291: &str = include_str!("../test_data/search_found_q46664291.json");
     const SEARCH_EMPTY: &str = include_str!("../test_data/search_empty.json");
@@ -76,18 +83,6 @@ mod tests {
     struct DummyInteractor;
     impl WikidataInteraction for DummyInteractor {}
 
-    async fn add_search_mock(mock_server: &MockServer, srsearch: &str, body: &'static str) {
-        Mock::given(method("GET"))
-            .and(query_param("srsearch", srsearch))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .insert_header("content-type", "application/json; charset=utf-8")
-                    .set_body_string(body),
-            )
-            .mount(mock_server)
-            .await;
-    }
-
     async fn mock_api(mock_server: &MockServer) -> Arc<RwLock<Api>> {
         Arc::new(RwLock::new(Api::new(&mock_server.uri()).await.unwrap()))
     }
@@ -120,7 +115,20 @@ mod tests {
         let api = mock_api(&mock_server).await;
         let d = DummyInteractor;
         let empty = Entity::new_empty_item();
-        let r = d.create_item(&empty, api).await;
+        let r = d.create_item(&empty, api, &EditOutput::Live).await;
+        assert!(matches!(r, Ok(None)), "expected Ok(None), got {:?}", r.as_ref().err());
+    }
+
+    #[tokio::test]
+    async fn create_item_dry_run_does_not_write() {
+        let mock_server = start_mock_server().await;
+        let api = mock_api(&mock_server).await;
+        let d = DummyInteractor;
+        let mut item = Entity::new_empty_item();
+        item.set_label(LocaleString::new("en", "A new item"));
+        let r = d.create_item(&item, api, &EditOutput::DryRun).await;
         assert!(matches!(r, Ok(None)), "expected Ok(None), got {:?}", r.as_ref().err());
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        assert!(requests.iter().all(|r| r.method != wiremock::http::Method::POST));
     }
 }
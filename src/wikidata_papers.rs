@@ -9,13 +9,22 @@ use tokio::sync::RwLock;
 use wikibase::mediawiki::api::Api;
 
 use self::{
-    identifiers::{GenericWorkIdentifier, GenericWorkType},
+    identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp},
     wikidata_interaction::WikidataInteraction,
 };
 use crate::{
+    audit_report::AuditReport,
+    discrepancy_report::DiscrepancyReport,
+    edit_output::EditOutput,
     generic_author_info::GenericAuthorInfo,
+    isbn::Isbn,
+    journal_info::JournalInfo,
+    key_normalization::normalize_value,
+    merge_candidate::{DuplicatePolicy, MergeCandidate},
+    publication_record::{Precedence, PublicationRecord},
     scientific_publication_adapter::ScientificPublicationAdapter,
-    wikidata_string_cache::WikidataStringCache, *,
+    wikidata_string_cache::{LookupError, WikidataStringCache},
+    *,
 };
 
 pub type Spas = Box<dyn ScientificPublicationAdapter + Sync>;
@@ -24,6 +33,44 @@ lazy_static! {
     static ref SNAK_REMOVE_STATEMENT: Snak = Snak::new_no_value("P2093", SnakDataType::String);
 }
 
+/// Why `create_or_update_item_from_*` didn't get to an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// No identifiers to start from
+    NoIdentifiers,
+    /// The item would have had too few claims to be worth writing
+    TooFewClaims,
+    /// Nothing to write for a new item, or no ID in the API's response
+    NoItem,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::NoIdentifiers => "no identifiers",
+            SkipReason::TooFewClaims => "too few claims",
+            SkipReason::NoItem => "no item",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What `create_or_update_item_from_ids` and `create_or_update_item_from_q`
+/// did.
+pub enum ItemUpdate {
+    /// The item was written, or was up to date already
+    Done(EditResult),
+    Skipped(SkipReason),
+    /// The IDs are on several items, and the `DuplicatePolicy` picked none
+    /// of them; nothing was edited
+    Duplicates(MergeCandidate),
+}
+
 /// Hard cap on the identifier-discovery convergence loop in
 /// `update_from_paper_ids`. A misbehaving adapter that keeps inventing new
 /// IDs each call would otherwise loop forever; the cap bounds the worst
@@ -34,9 +81,26 @@ pub(crate) const MAX_ID_DISCOVERY_PASSES: usize = 8;
 pub struct EditResult {
     q: String,
     edited: bool,
+    created: bool,
+    written: bool,
+    discrepancies: DiscrepancyReport,
+    merge_candidate: Option<MergeCandidate>,
 }
 
 impl EditResult {
+    fn new(q: String, edited: bool) -> Self {
+        Self {
+            q,
+            edited,
+            created: false,
+            written: edited,
+            discrepancies: DiscrepancyReport::default(),
+            merge_candidate: None,
+        }
+    }
+
+    /// The item; empty if an item would have been created, but the output
+    /// isn't live.
     pub fn q(&self) -> &str {
         &self.q
     }
@@ -44,13 +108,39 @@ impl EditResult {
     pub fn edited(&self) -> bool {
         self.edited
     }
+
+    /// The item is new.
+    pub fn created(&self) -> bool {
+        self.created
+    }
+
+    /// The edit was written to Wikidata, rather than only reported because
+    /// the output isn't live. Always `false` if nothing was edited.
+    pub fn written(&self) -> bool {
+        self.written
+    }
+
+    /// Where the sources disagreed with each other or with the item. Empty
+    /// unless the item was updated from the adapters.
+    pub fn discrepancies(&self) -> &DiscrepancyReport {
+        &self.discrepancies
+    }
+
+    /// The other items the IDs were found on, if the `DuplicatePolicy`
+    /// picked this one among them.
+    pub fn merge_candidate(&self) -> Option<&MergeCandidate> {
+        self.merge_candidate.as_ref()
+    }
 }
 
 pub struct WikidataPapers {
     adapters: Vec<Spas>,
     cache: Arc<WikidataStringCache>,
     edit_summary: Option<String>,
-    testing: bool,
+    output: EditOutput,
+    create_journals: bool,
+    precedence: Precedence,
+    duplicate_policy: DuplicatePolicy,
     entities: entity_container::EntityContainer,
 }
 
@@ -58,27 +148,36 @@ impl WikidataInteraction for WikidataPapers {}
 
 impl WikidataPapers {
     pub fn new(cache: Arc<WikidataStringCache>) -> WikidataPapers {
-        let mut entities = entity_container::EntityContainer::new();
-        entities.allow_special_entity_data(false);
         WikidataPapers {
             adapters: vec![],
             cache,
             edit_summary: None,
-            testing: false,
-            entities,
+            output: EditOutput::default(),
+            create_journals: false,
+            precedence: Precedence::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            entities: Self::new_entity_container(),
         }
     }
 
+    fn new_entity_container() -> entity_container::EntityContainer {
+        let mut entities = entity_container::EntityContainer::new();
+        entities.allow_special_entity_data(false);
+        entities
+    }
+
     /// Constructor that registers every built-in provider adapter in the
     /// canonical order.
     ///
-    /// The order is meaningful: `update_item_from_adapters` and
-    /// `update_from_paper_ids` iterate `self.adapters` in registration
-    /// order, so earlier adapters get first claim on identifier mappings
-    /// and statement edits. Changing the order risks subtle behaviour
-    /// shifts in production. Keep PMC → PubMed → Crossref → Semantic
-    /// Scholar → ORCID → arXiv → OpenAlex → DataCite → EuropePMC in sync
-    /// across binaries; this constructor is the single source of truth.
+    /// The order is meaningful: `update_from_paper_ids` iterates
+    /// `self.adapters` in registration order, so earlier adapters get first
+    /// claim on identifier mappings. Which adapter's value wins for a
+    /// statement is decided by the `Precedence`, whose default falls back
+    /// to this order (`publication_record::DEFAULT_SOURCE_ORDER`). Changing
+    /// the order risks subtle behaviour shifts in production. Keep PMC →
+    /// PubMed → Crossref → Semantic Scholar → ORCID → arXiv → OpenAlex →
+    /// DataCite → EuropePMC in sync across binaries and with
+    /// `DEFAULT_SOURCE_ORDER`; this constructor is the single source of truth.
     pub fn with_default_adapters(cache: Arc<WikidataStringCache>) -> WikidataPapers {
         use crate::{
             arxiv2wikidata::Arxiv2Wikidata,
@@ -120,8 +219,32 @@ impl WikidataPapers {
         self.adapters.push(adapter_box);
     }
 
-    pub fn set_testing(&mut self, testing: bool) {
-        self.testing = testing;
+    /// Whether edits are written to Wikidata or only reported. Live by
+    /// default.
+    pub fn set_output(&mut self, output: EditOutput) {
+        self.output = output;
+    }
+
+    pub fn output(&self) -> &EditOutput {
+        &self.output
+    }
+
+    /// Allows creating journal items for papers whose ISSN is not on Wikidata
+    /// yet. Off by default.
+    pub fn set_create_journals(&mut self, create_journals: bool) {
+        self.create_journals = create_journals;
+    }
+
+    /// Which adapter wins when adapters report different values for the same
+    /// field. Defaults to `Precedence::default()`.
+    pub fn set_precedence(&mut self, precedence: Precedence) {
+        self.precedence = precedence;
+    }
+
+    /// What to do when a paper's IDs are on several items. Refuses to edit
+    /// by default.
+    pub fn set_duplicate_policy(&mut self, duplicate_policy: DuplicatePolicy) {
+        self.duplicate_policy = duplicate_policy;
     }
 
     pub fn edit_summary(&self) -> &Option<String> {
@@ -165,7 +288,7 @@ impl WikidataPapers {
             Some(q) => q,
             None => return,
         };
-        if Self::get_p50s_from_item(item).contains(&format!("Q{}", author_q)) {
+        if Self::get_p50s_from_item(item).iter().any(|q| q == author_q) {
             return; // Had that author already
         }
         item.claims_mut()
@@ -186,6 +309,34 @@ impl WikidataPapers {
         Self::remove_statements_with_no_value(item);
     }
 
+    /// Finds the P2093 on `item` that matches one of `names`, trying them in
+    /// order. Returns the author as read from that statement, i.e. with the
+    /// name string and P1545 ordinal as they are on Wikidata.
+    pub fn find_author_name_string(item: &Entity, names: &[String]) -> Option<GenericAuthorInfo> {
+        let candidates: Vec<GenericAuthorInfo> = item
+            .claims()
+            .iter()
+            .filter(|statement| statement.property() == "P2093")
+            .filter_map(GenericAuthorInfo::new_from_statement)
+            .collect();
+        names.iter().find_map(|name| {
+            let mut probe = GenericAuthorInfo::new();
+            probe.set_name(Some(name.to_owned()));
+            let (candidate, _points) = probe.find_best_match(&candidates)?;
+            Some(candidates[candidate].to_owned())
+        })
+    }
+
+    /// P1545 ordinals already used by P50 and P2093 statements on `item`.
+    pub fn author_ordinals(item: &Entity) -> HashSet<String> {
+        item.claims()
+            .iter()
+            .filter(|statement| ["P50", "P2093"].contains(&statement.property()))
+            .filter_map(GenericAuthorInfo::new_from_statement)
+            .filter_map(|author| author.list_number().map(|s| s.to_string()))
+            .collect()
+    }
+
     /// For each P2093 ("author name string") statement on `item`, tries to
     /// match it against `authors` (typically merged adapter-supplied
     /// authors) and either:
@@ -278,8 +429,8 @@ impl WikidataPapers {
         item: &mut Entity,
         adapter2work_id: &mut HashMap<usize, String>,
         mw_api: Arc<RwLock<Api>>,
-    ) -> Result<()> {
-        let mut authors: Vec<GenericAuthorInfo> = vec![];
+    ) -> Result<DiscrepancyReport> {
+        let mut record = PublicationRecord::default();
         for adapter_id in 0..self.adapters.len() {
             let publication_id =
                 match self.adapters[adapter_id].publication_id_from_item(item).await {
@@ -289,34 +440,40 @@ impl WikidataPapers {
 
             let adapter = &mut self.adapters[adapter_id];
             adapter2work_id.insert(adapter_id, publication_id.clone());
-            adapter
-                .update_statements_for_publication_id_default(
-                    &publication_id,
-                    item,
-                    // self.cache.clone(),
-                )
-                .await;
-            adapter.update_statements_for_publication_id(&publication_id, item).await;
-
-            // Authors
-            let authors2 = adapter.get_author_list(&publication_id).await;
-            self.merge_authors(&mut authors, &authors2);
+            record.merge(adapter.publication_record(&publication_id).await);
+        }
+        let discrepancies = DiscrepancyReport::new(&record, item, &self.precedence);
+        record.apply_to_item(item, &self.precedence, &self.cache).await;
+
+        if self.create_journals && !item.has_claims_with_property("P1433") {
+            match self.create_journal_item(adapter2work_id, mw_api.clone()).await {
+                Ok(Some((q, references))) => item.add_claim(Statement::new_normal(
+                    Snak::new_item("P1433", &q),
+                    vec![],
+                    references,
+                )),
+                Ok(None) => {},
+                Err(e) => tracing::warn!(error = %e, "could not create journal item"),
+            }
         }
 
         // Set P31 (instance of) based on work type from adapters, if not already set.
         // Adapters like Crossref can determine the correct type (book, article, etc.)
         if !item.has_claims_with_property("P31") {
-            let work_type_q = adapter2work_id
-                .iter()
-                .find_map(|(adapter_id, pub_id)| self.adapters[*adapter_id].get_work_type(pub_id))
-                .unwrap_or_else(|| "Q13442814".to_string()); // default: scientific article
+            // Default: scientific article
+            let work_type_q = record.work_type(&self.precedence).unwrap_or("Q13442814");
             item.add_claim(Statement::new_normal(
-                Snak::new_item("P31", &work_type_q),
+                Snak::new_item("P31", work_type_q),
                 vec![],
                 vec![],
             ));
         }
 
+        let mut authors: Vec<GenericAuthorInfo> = vec![];
+        for authors2 in record.author_lists(&self.precedence) {
+            self.merge_authors(&mut authors, authors2);
+        }
+
         // Final deduplication pass after all sources have been merged
         // CPU-bound: O(n²) author matching with regex — offload from async runtime
         let authors = tokio::task::spawn_blocking(move || {
@@ -327,15 +484,177 @@ impl WikidataPapers {
 
         let mut futures = vec![];
         for author in &authors {
-            let future =
-                author.get_or_create_author_item(mw_api.clone(), self.cache.clone(), false);
+            let future = author.get_or_create_author_item(
+                mw_api.clone(),
+                self.cache.clone(),
+                false,
+                &self.output,
+            );
             futures.push(future);
         }
         let new_authors = futures::future::join_all(futures).await;
 
         self.update_author_items(&new_authors, mw_api.clone()).await;
         self.create_or_update_author_statements(item, &new_authors);
-        Ok(())
+        Ok(discrepancies)
+    }
+
+    /// Creates a journal item from what the adapters report about the journal,
+    /// if the sources corroborate each other (see [`JournalInfo::corroborated`])
+    /// and no item has any of its ISSNs yet. Returns the new item, and the
+    /// reference for the P1433 to it: the first source that reported one of
+    /// the ISSNs it was created for.
+    async fn create_journal_item(
+        &self,
+        adapter2work_id: &HashMap<usize, String>,
+        mw_api: Arc<RwLock<Api>>,
+    ) -> Result<Option<(String, Vec<Reference>)>> {
+        // In adapter order, so the preferred sources supply the title
+        let mut adapter_ids: Vec<&usize> = adapter2work_id.keys().collect();
+        adapter_ids.sort();
+        let sourced_infos: Vec<(usize, JournalInfo)> = adapter_ids
+            .into_iter()
+            .filter_map(|id| {
+                let info = self.adapters[*id].get_journal_info(&adapter2work_id[id])?;
+                Some((*id, info))
+            })
+            .collect();
+        let infos: Vec<JournalInfo> = sourced_infos.iter().map(|(_, info)| info.clone()).collect();
+        let journal = match JournalInfo::corroborated(&infos) {
+            Some(journal) => journal,
+            None => return Ok(None),
+        };
+        let references = sourced_infos
+            .iter()
+            .find(|(_, info)| info.all_issns().iter().any(|issn| journal.issns.contains(issn)))
+            .map(|(id, _)| self.adapters[*id].reference(&adapter2work_id[id]))
+            .unwrap_or_default();
+
+        // Search again rather than trusting the cache: an ISSN that is on
+        // several items (and so resolved to nothing) must not get another one
+        for issn in &journal.issns {
+            for property in ["P7363", "P236"] {
+                let query = format!("haswbstatement:{property}={issn}");
+                let items = self.search_wikibase(&query, mw_api.clone()).await?;
+                if !items.is_empty() {
+                    tracing::info!(issn, ?items, "not creating journal, ISSN is in use");
+                    return Ok(None);
+                }
+            }
+        }
+
+        // Publisher only if exactly one item has its OpenAlex ID
+        let publisher = match &journal.publisher_openalex_id {
+            Some(id) => {
+                let query = format!("haswbstatement:P10283={id}");
+                match self.search_wikibase(&query, mw_api.clone()).await?.as_slice() {
+                    [q] => Some(q.to_owned()),
+                    _ => None,
+                }
+            },
+            None => None,
+        };
+        let journal_item = journal.new_item(publisher.as_deref());
+        let q = match self.create_item(&journal_item, mw_api, &self.output).await? {
+            Some(q) => q,
+            None => return Ok(None),
+        };
+        tracing::info!(q, title = ?journal.title, "created journal item");
+        for issn in &journal.issns {
+            self.cache.set("P236", issn, Some(q.to_owned())).await;
+        }
+        if let Some(issn_l) = &journal.issn_l {
+            self.cache.set("P7363", issn_l, Some(q.to_owned())).await;
+        }
+        Ok(Some((q, references)))
+    }
+
+    /// What the adapters report for the work with `ids`, merged as for an
+    /// edit, as JSON: all identifiers found, the record (see
+    /// [`PublicationRecord::to_json`]), the merged authors, and the adapters
+    /// that contributed. Nothing is written, and no item is looked up.
+    pub async fn lookup(&mut self, ids: &[GenericWorkIdentifier]) -> Result<serde_json::Value> {
+        let ids = self.update_from_paper_ids(ids).await;
+        let mut item = self.new_publication_item();
+        self.update_item_with_ids(&mut item, &ids);
+
+        let mut record = PublicationRecord::default();
+        for adapter in self.adapters.iter_mut() {
+            if let Some(publication_id) = adapter.publication_id_from_item(&item).await {
+                record.merge(adapter.publication_record(&publication_id).await);
+            }
+        }
+
+        let mut authors: Vec<GenericAuthorInfo> = vec![];
+        for authors2 in record.author_lists(&self.precedence) {
+            self.merge_authors(&mut authors, authors2);
+        }
+        let authors = tokio::task::spawn_blocking(move || {
+            GenericAuthorInfo::deduplicate(&mut authors);
+            authors
+        })
+        .await?;
+
+        let identifiers: Vec<serde_json::Value> = ids
+            .iter()
+            .map(|id| json!({"property": id.work_type().to_string(), "id": id.id()}))
+            .collect();
+        let authors: Vec<serde_json::Value> = authors
+            .iter()
+            .map(|author| {
+                json!({
+                    "name": author.name(),
+                    "list_number": author.list_number(),
+                    "prop2id": author.prop2id(),
+                    "item": author.wikidata_item(),
+                })
+            })
+            .collect();
+        let mut ret = record.to_json(&self.precedence);
+        ret["identifiers"] = json!(identifiers);
+        ret["authors"] = json!(authors);
+        ret["sources"] = json!(record.sources());
+        Ok(ret)
+    }
+
+    /// Compares `item` with what the adapters report for it. Like
+    /// [`Self::lookup`], this neither edits nor creates anything.
+    pub async fn audit(&mut self, item: &Entity) -> AuditReport {
+        let mut record = PublicationRecord::default();
+        let mut source_ids = vec![];
+        for adapter in self.adapters.iter_mut() {
+            let Some(publication_id) = adapter.publication_id_from_item(item).await else {
+                continue;
+            };
+            let mut ids = vec![];
+            adapter.add_identifiers_from_cached_publication(&publication_id, &mut ids);
+            let name = adapter.name().to_string();
+            source_ids.extend(ids.into_iter().map(|id| (name.to_owned(), id)));
+            record.merge(adapter.publication_record(&publication_id).await);
+        }
+        AuditReport::new(item, &record, &source_ids)
+    }
+
+    /// The author list for `item` as the adapters report it, merged and
+    /// deduplicated the same way as in [`Self::update_item_from_adapters`].
+    /// Neither the item nor any author items are touched.
+    pub async fn get_source_authors(&mut self, item: &Entity) -> Result<Vec<GenericAuthorInfo>> {
+        let mut authors: Vec<GenericAuthorInfo> = vec![];
+        for adapter_id in 0..self.adapters.len() {
+            let publication_id =
+                match self.adapters[adapter_id].publication_id_from_item(item).await {
+                    Some(id) => id,
+                    _ => continue,
+                };
+            let authors2 = self.adapters[adapter_id].get_author_list(&publication_id).await;
+            self.merge_authors(&mut authors, &authors2);
+        }
+        let authors = tokio::task::spawn_blocking(move || {
+            GenericAuthorInfo::deduplicate(&mut authors);
+            authors
+        })
+        .await?;
+        Ok(authors)
     }
 
     pub async fn update_author_items(
@@ -356,11 +675,18 @@ impl WikidataPapers {
         drop(api);
 
         for author in authors {
-            author.update_author_item(&mut self.entities, mw_api.clone()).await;
+            author.update_author_item(&mut self.entities, mw_api.clone(), &self.output).await;
         }
     }
 
-    fn update_item_with_ids(&self, item: &mut wikibase::Entity, ids: &Vec<GenericWorkIdentifier>) {
+    /// Adds statements for identifiers the item doesn't have yet. Values come
+    /// from the adapter responsible for the property (ISBNs have none), and
+    /// are written in their canonical form.
+    pub fn update_item_with_ids(
+        &self,
+        item: &mut wikibase::Entity,
+        ids: &Vec<GenericWorkIdentifier>,
+    ) {
         for id in ids {
             let prop = match id.work_type() {
                 GenericWorkType::Property(prop) => prop.to_owned(),
@@ -374,12 +700,21 @@ impl WikidataPapers {
                 .adapters
                 .iter()
                 .filter(|adapter| adapter.publication_property().as_ref() == Some(&prop))
-                .find_map(|adapter| adapter.publication_id_for_statement(id.id()));
-            if let Some(id) = id2statement {
+                .find_map(|adapter| {
+                    let statement_id = adapter.publication_id_for_statement(id.id())?;
+                    Some((statement_id, adapter.reference(id.id())))
+                })
+                .or_else(|| match prop {
+                    IdProp::ISBN10 | IdProp::ISBN13 if id.is_legit() => {
+                        Some((id.id().to_owned(), vec![]))
+                    },
+                    _ => None,
+                });
+            if let Some((id, references)) = id2statement {
                 item.add_claim(Statement::new_normal(
-                    Snak::new_external_id(prop.as_str(), &id),
-                    vec![],
+                    Snak::new_external_id(prop.as_str(), &normalize_value(prop.as_str(), &id)),
                     vec![],
+                    references,
                 ))
             }
         }
@@ -389,22 +724,31 @@ impl WikidataPapers {
         &mut self,
         mw_api: Arc<RwLock<Api>>,
         ids: &Vec<GenericWorkIdentifier>,
-    ) -> Result<Option<EditResult>> {
+    ) -> Result<ItemUpdate> {
         if ids.is_empty() {
-            return Ok(None);
+            return Ok(ItemUpdate::Skipped(SkipReason::NoIdentifiers));
         }
-        let items = match self.testing {
-            true => vec![],
-            false => self.get_items_for_ids(ids).await,
+        let candidate = MergeCandidate::new(self.get_item_hits_for_ids(ids).await?);
+        if !candidate.is_duplicate() {
+            return self.create_or_update_item_from_items(mw_api, ids, &candidate.items()).await;
+        }
+        let Some(q) = candidate.winner(self.duplicate_policy) else {
+            tracing::warn!(?ids, %candidate, "IDs are on several items; not editing");
+            return Ok(ItemUpdate::Duplicates(candidate));
         };
-        self.create_or_update_item_from_items(mw_api, ids, &items).await
+        tracing::warn!(?ids, %candidate, %q, "IDs are on several items; editing the oldest");
+        let mut update = self.create_or_update_item_from_items(mw_api, ids, &[q]).await?;
+        if let ItemUpdate::Done(result) = &mut update {
+            result.merge_candidate = Some(candidate);
+        }
+        Ok(update)
     }
 
     pub async fn create_or_update_item_from_q(
         &mut self,
         mw_api: Arc<RwLock<Api>>,
         q: &str,
-    ) -> Result<Option<EditResult>> {
+    ) -> Result<ItemUpdate> {
         let items = vec![q.to_owned()];
         self.create_or_update_item_from_items(mw_api, &vec![], &items).await
     }
@@ -418,7 +762,7 @@ impl WikidataPapers {
         mw_api: Arc<RwLock<Api>>,
         ids: &Vec<GenericWorkIdentifier>,
         items: &[String],
-    ) -> Result<Option<EditResult>> {
+    ) -> Result<ItemUpdate> {
         let mut item: wikibase::Entity;
         let mut original_item = Entity::new_empty_item();
         match items.first() {
@@ -439,25 +783,56 @@ impl WikidataPapers {
         self.update_item_with_ids(&mut item, ids);
 
         let mut adapter2work_id = HashMap::new();
-        self.update_item_from_adapters(&mut item, &mut adapter2work_id, mw_api.clone())
-            .await?;
+        let discrepancies =
+            self.update_item_from_adapters(&mut item, &mut adapter2work_id, mw_api.clone()).await?;
+        if !discrepancies.is_empty() {
+            tracing::info!(?ids, %discrepancies, "sources disagree");
+        }
 
         // Paranoia
         if item.claims().len() < 4 {
             tracing::warn!(?ids, claim_count = item.claims().len(), "skipping: too few claims to write");
-            return Ok(None);
+            return Ok(ItemUpdate::Skipped(SkipReason::TooFewClaims));
         }
 
-        self.apply_diff_for_item(original_item, item, mw_api).await
+        match self.apply_diff_for_item(original_item, item, mw_api).await? {
+            Some(mut result) => {
+                result.discrepancies = discrepancies;
+                Ok(ItemUpdate::Done(result))
+            },
+            None => Ok(ItemUpdate::Skipped(SkipReason::NoItem)),
+        }
+    }
+
+    /// Properties of statements on `original_item` that have other
+    /// references on `item`. Statements are matched by their ID, so new
+    /// statements are not included.
+    fn properties_with_merged_references(original_item: &Entity, item: &Entity) -> Vec<String> {
+        let mut ret: Vec<String> = item
+            .claims()
+            .iter()
+            .filter(|statement| statement.id().is_some())
+            .filter(|statement| {
+                original_item.claims().iter().any(|original| {
+                    original.id() == statement.id()
+                        && original.references() != statement.references()
+                })
+            })
+            .map(|statement| statement.property().to_string())
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
     }
 
     /// Applies the diff between `original_item` and `item` to Wikidata.
     ///
     /// Returns:
     /// - `Ok(Some(EditResult))` for a successful write or an empty diff
-    ///   against an existing item (in which case `edited == false`),
-    /// - `Ok(None)` for an empty diff against an unsaved/empty original
-    ///   or for the testing/dry-run branch,
+    ///   against an existing item (in which case `edited == false`), or
+    ///   an edit that was only reported because the output isn't live (in
+    ///   which case `written == false`),
+    /// - `Ok(None)` for an empty diff against an unsaved/empty original,
     /// - `Err(_)` if the underlying Wikidata API write fails — so the
     ///   bot loop can mark the command FAILED instead of silently DUNNO.
     pub async fn apply_diff_for_item(
@@ -471,6 +846,15 @@ impl WikidataPapers {
         params.aliases.add = EntityDiffParamState::All;
         params.claims.add = EntityDiffParamState::All;
         params.claims.remove = EntityDiffParamState::some(&vec!["P2093"]);
+        // Existing statements are only altered for the properties where
+        // `add_or_merge_statement` merged references into one of them;
+        // changes to statements of other properties are left out.
+        let merged = Self::properties_with_merged_references(&original_item, &item);
+        params.claims.alter =
+            EntityDiffParamState::some(&merged.iter().map(String::as_str).collect());
+        // References are compared without their retrieval date (P813), so
+        // re-running on an item doesn't re-add a reference that is only
+        // newer.
         params.references.list =
             vec![(EntityDiffParamState::All, EntityDiffParamState::except(&vec!["P813"]))];
         let mut diff = EntityDiff::new(&original_item, &item, &params);
@@ -479,13 +863,17 @@ impl WikidataPapers {
         if diff.is_empty() {
             return Ok(match original_item.id().as_str() {
                 "" => None,
-                id => Some(EditResult { q: id.to_string(), edited: false }),
+                id => Some(EditResult::new(id.to_string(), false)),
             });
         }
 
-        if self.testing {
-            println!("{}", diff.to_string_pretty().unwrap());
-            Ok(None)
+        if !self.output.is_live() {
+            let id = original_item.id();
+            self.output.report(&original_item, self.edit_summary.as_deref(), &diff);
+            let mut result = EditResult::new(id.to_owned(), true);
+            result.created = id.is_empty();
+            result.written = false;
+            Ok(Some(result))
         } else {
             // Why a write lock here at all:
             // `wikibase::EntityDiff::apply_diff` takes `&mut mediawiki::Api`
@@ -510,12 +898,36 @@ impl WikidataPapers {
                 // `api` (the write lock guard) drops here.
             };
             match EntityDiff::get_entity_id(&new_json) {
-                Some(q) => Ok(Some(EditResult { q, edited: true })),
+                Some(q) => {
+                    let mut result = EditResult::new(q, true);
+                    result.created = original_item.id().is_empty();
+                    if result.created {
+                        self.cache_ids_of_new_item(&result.q, &item).await;
+                    }
+                    Ok(Some(result))
+                },
                 None => Ok(None),
             }
         }
     }
 
+    /// Caches the identifiers on a new item as being on `q`. They were most
+    /// likely cached as not being on any item.
+    async fn cache_ids_of_new_item(&self, q: &str, item: &Entity) {
+        let props: HashSet<IdProp> = item
+            .claims()
+            .iter()
+            .filter_map(|claim| claim.main_snak().property().parse().ok())
+            .collect();
+        for prop in props {
+            for value in item.values_for_property(prop.as_str()) {
+                if let Value::StringValue(id) = value {
+                    self.cache.set(prop.as_str(), &id, Some(q.to_owned())).await;
+                }
+            }
+        }
+    }
+
     pub async fn update_from_paper_ids(
         &mut self,
         original_ids: &[GenericWorkIdentifier],
@@ -553,31 +965,60 @@ impl WikidataPapers {
         ids.iter().filter(|id| id.is_legit()).cloned().collect()
     }
 
-    pub async fn get_items_for_ids(&self, ids: &Vec<GenericWorkIdentifier>) -> Vec<String> {
-        let mut items: Vec<String> = vec![];
-        for id in ids {
-            let r = match id.work_type() {
-                GenericWorkType::Property(prop) => self.cache.get(prop.as_str(), id.id()).await,
-                GenericWorkType::Item => Some(id.id().to_owned()),
-            };
-            if let Some(q) = r {
-                items.push(q)
-            }
-        }
+    pub async fn get_items_for_ids(&self, ids: &Vec<GenericWorkIdentifier>) -> Result<Vec<String>> {
+        let mut items: Vec<String> =
+            self.get_item_hits_for_ids(ids).await?.into_iter().map(|(_, q)| q).collect();
         // CPU work: sort + dedup on potentially large identifier list
-        tokio::task::spawn_blocking(move || {
+        Ok(tokio::task::spawn_blocking(move || {
             items.sort();
             items.dedup();
             items
         })
         .await
-        .unwrap_or_default()
+        .unwrap_or_default())
+    }
+
+    /// The items each of `ids` is on. An ID that is on several items has a
+    /// hit for each, so that the paper becomes a merge candidate. An ID that
+    /// can't be searched for is an error, rather than a paper without item.
+    pub async fn get_item_hits_for_ids(
+        &self,
+        ids: &[GenericWorkIdentifier],
+    ) -> Result<Vec<(GenericWorkIdentifier, String)>> {
+        let mut hits = vec![];
+        for id in ids {
+            let r = match id.work_type() {
+                GenericWorkType::Property(IdProp::ISBN10 | IdProp::ISBN13) => {
+                    match Isbn::parse(id.id()) {
+                        Some(isbn) => self.cache.isbn2q(&isbn).await,
+                        None => Ok(None),
+                    }
+                },
+                GenericWorkType::Property(prop) => {
+                    self.cache.get_unique(prop.as_str(), id.id()).await
+                },
+                GenericWorkType::Item => Ok(Some(id.id().to_owned())),
+            };
+            let items = match r {
+                Ok(q) => q.into_iter().collect(),
+                Err(LookupError::Ambiguous(items)) => items,
+                Err(LookupError::Failed(error)) => return Err(error),
+            };
+            hits.extend(items.into_iter().map(|q| (id.to_owned(), q)));
+        }
+        Ok(hits)
     }
 
     pub fn entities_mut(&mut self) -> &mut entity_container::EntityContainer {
         &mut self.entities
     }
 
+    /// Forgets all loaded items, so an instance that is reused for the next
+    /// paper doesn't edit against revisions it loaded earlier.
+    pub fn clear_entities(&mut self) {
+        self.entities = Self::new_entity_container();
+    }
+
     fn get_p50s_from_item(item: &mut Entity) -> Vec<String> {
         item.claims()
             .par_iter()
@@ -706,6 +1147,92 @@ mod tests {
         WikidataPapers::new(cache)
     }
 
+    #[tokio::test]
+    async fn update_author_name_statement_replaces_matching_p2093() {
+        let wdp = make_wdp().await;
+        let mut item = Entity::new_empty_item();
+        item.add_claim(make_p2093("Jane Doe", "1"));
+        item.add_claim(make_p2093("John Smith", "2"));
+        let mut author = GenericAuthorInfo::new();
+        author.set_wikidata_item(Some("Q42".to_string()));
+        wdp.update_author_name_statement("John Smith", &author, &mut item);
+        let p50 = item.claims().iter().find(|s| s.property() == "P50").expect("P50");
+        assert_eq!(get_ordinal(p50).as_deref(), Some("2"));
+        assert_eq!(get_named_as(p50).as_deref(), Some("John Smith"));
+        assert_eq!(item.claims().iter().filter(|s| s.property() == "P2093").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_author_name_statement_skips_existing_author() {
+        let wdp = make_wdp().await;
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(Snak::new_item("P50", "Q42"), vec![], vec![]));
+        item.add_claim(make_p2093("John Smith", "2"));
+        let mut author = GenericAuthorInfo::new();
+        author.set_wikidata_item(Some("Q42".to_string()));
+        wdp.update_author_name_statement("John Smith", &author, &mut item);
+        assert!(item.has_claims_with_property("P2093"));
+    }
+
+    #[test]
+    fn author_ordinals_covers_p50_and_p2093() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(make_p2093("Jane Doe", "1"));
+        item.add_claim(Statement::new_normal(
+            Snak::new_item("P50", "Q42"),
+            vec![Snak::new_string("P1545", "3")],
+            vec![],
+        ));
+        item.add_claim(Statement::new_normal(Snak::new_item("P50", "Q43"), vec![], vec![]));
+        let ordinals = WikidataPapers::author_ordinals(&item);
+        assert_eq!(ordinals, HashSet::from(["1".to_string(), "3".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn get_source_authors_without_adapters_is_empty() {
+        let mut wdp = make_wdp().await;
+        let item = Entity::new_empty_item();
+        assert!(wdp.get_source_authors(&item).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn find_author_name_string_tries_names_in_order() {
+        let mut item = Entity::new_empty_item();
+        item.add_claim(make_p2093("Jane Doe", "1"));
+        item.add_claim(make_p2093("John Smith", "2"));
+        let names = vec!["Nobody Known".to_string(), "John Smith".to_string()];
+        let found = WikidataPapers::find_author_name_string(&item, &names).expect("match");
+        assert_eq!(found.name(), Some("John Smith"));
+        assert_eq!(found.list_number(), Some("2"));
+        let names = vec!["Nobody Known".to_string()];
+        assert!(WikidataPapers::find_author_name_string(&item, &names).is_none());
+    }
+
+    #[tokio::test]
+    async fn update_item_with_ids_adds_isbn_statements() {
+        let wdp = make_wdp().await;
+        let isbn = Isbn::parse("0306406152").unwrap();
+        let mut item = Entity::new_empty_item();
+        wdp.update_item_with_ids(&mut item, &GenericWorkIdentifier::new_isbn(&isbn));
+        assert_eq!(
+            item.values_for_property("P212"),
+            vec![Value::StringValue("978-0-306-40615-7".to_string())]
+        );
+        assert_eq!(
+            item.values_for_property("P957"),
+            vec![Value::StringValue("0-306-40615-2".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn update_item_with_ids_skips_invalid_isbn() {
+        let wdp = make_wdp().await;
+        let ids = vec![GenericWorkIdentifier::new_prop(IdProp::ISBN13, "9780306406158")];
+        let mut item = Entity::new_empty_item();
+        wdp.update_item_with_ids(&mut item, &ids);
+        assert!(!item.has_claims_with_property("P212"));
+    }
+
     fn get_string_qualifier(statement: &Statement, property: &str) -> Option<String> {
         statement.qualifiers().iter().find_map(|q| {
             if q.property() != property {
@@ -943,7 +1470,6 @@ mod tests {
         fn author_cache_mut(&mut self) -> &mut HashMap<String, String> {
             &mut self.author_cache
         }
-        async fn update_statements_for_publication_id(&self, _: &str, _: &mut Entity) {}
         async fn get_identifier_list(
             &mut self,
             _ids: &[GenericWorkIdentifier],
@@ -1178,4 +1704,389 @@ mod tests {
         assert_eq!(p50_count, 1);
         assert_eq!(p2093_count, 1, "nameless author with same name as a Q-item author still gets a P2093");
     }
+
+    // === create_journal_item ===
+
+    /// An adapter that only reports a journal, from the source item `source`
+    struct FakeJournalAdapter {
+        source: String,
+        journal: JournalInfo,
+        author_cache: HashMap<String, String>,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl crate::scientific_publication_adapter::ScientificPublicationAdapter for FakeJournalAdapter {
+        fn name(&self) -> &str {
+            "FakeJournalAdapter"
+        }
+        fn source_item(&self) -> Option<&str> {
+            Some(&self.source)
+        }
+        fn author_cache(&self) -> &HashMap<String, String> {
+            &self.author_cache
+        }
+        fn author_cache_mut(&mut self) -> &mut HashMap<String, String> {
+            &mut self.author_cache
+        }
+        fn get_journal_info(&self, _publication_id: &str) -> Option<JournalInfo> {
+            Some(self.journal.clone())
+        }
+    }
+
+    /// A WikidataPapers with one `FakeJournalAdapter` per journal (the first
+    /// with source Q1, the next Q2, …), and the mock server its API talks to.
+    async fn make_journal_wdp(
+        journals: Vec<JournalInfo>,
+    ) -> (WikidataPapers, wiremock::MockServer, Arc<RwLock<Api>>, HashMap<usize, String>) {
+        let mock_server = crate::test_helpers::start_mediawiki_mock_server().await;
+        let mw_api = Arc::new(RwLock::new(Api::new(&mock_server.uri()).await.unwrap()));
+        let mut wdp = WikidataPapers::new(Arc::new(WikidataStringCache::new(mw_api.clone())));
+        let mut adapter2work_id = HashMap::new();
+        for (adapter_id, journal) in journals.into_iter().enumerate() {
+            let source = format!("Q{}", adapter_id + 1);
+            let author_cache = HashMap::new();
+            wdp.add_adapter(Box::new(FakeJournalAdapter { source, journal, author_cache }));
+            adapter2work_id.insert(adapter_id, "work".to_string());
+        }
+        (wdp, mock_server, mw_api, adapter2work_id)
+    }
+
+    fn nature(issns: &[&str]) -> JournalInfo {
+        JournalInfo {
+            title: Some("Nature".to_string()),
+            issns: issns.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn create_journal_item_needs_corroboration() {
+        // No search is mocked; the evidence check must come first
+        let (wdp, _mock_server, mw_api, adapter2work_id) =
+            make_journal_wdp(vec![nature(&["0028-0836"]), nature(&["0028-0860"])]).await;
+        let created = wdp.create_journal_item(&adapter2work_id, mw_api).await.unwrap();
+        assert!(created.is_none());
+    }
+
+    #[tokio::test]
+    async fn create_journal_item_skips_issn_in_use() {
+        use crate::test_helpers::add_search_mock;
+        const SEARCH_MULTIPLE: &str = include_str!("../test_data/search_found_multiple.json");
+        let (wdp, mock_server, mw_api, adapter2work_id) =
+            make_journal_wdp(vec![nature(&["0028-0836"]), nature(&["00280836"])]).await;
+        // Ambiguous in the cache, so the paper got no P1433; still in use
+        add_search_mock(&mock_server, "haswbstatement:P7363=0028-0836", SEARCH_MULTIPLE).await;
+        let created = wdp.create_journal_item(&adapter2work_id, mw_api).await.unwrap();
+        assert!(created.is_none());
+    }
+
+    #[tokio::test]
+    async fn create_journal_item_references_the_source_of_the_issn() {
+        use crate::test_helpers::add_search_mock;
+        const SEARCH_EMPTY: &str = include_str!("../test_data/search_empty.json");
+        // The first source's ISSN is not corroborated, so the second one's is used
+        let (wdp, mock_server, mw_api, adapter2work_id) = make_journal_wdp(vec![
+            nature(&["1476-4687"]),
+            nature(&["0028-0836"]),
+            nature(&["0028-0836"]),
+        ])
+        .await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=0028-0836", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P236=0028-0836", SEARCH_EMPTY).await;
+        mock_edits(&mock_server).await;
+        let (q, references) =
+            wdp.create_journal_item(&adapter2work_id, mw_api).await.unwrap().unwrap();
+        assert_eq!(q, "Q42");
+        assert_eq!(references.len(), 1);
+        let stated_in = references[0]
+            .snaks()
+            .iter()
+            .find(|snak| snak.property() == "P248")
+            .and_then(|snak| snak.data_value().as_ref())
+            .map(|dv| dv.value().to_owned());
+        assert!(matches!(stated_in, Some(Value::Entity(source)) if source.id() == "Q2"));
+    }
+
+    #[tokio::test]
+    async fn create_journal_item_search_failure_is_an_error() {
+        // Unmocked searches fail; that must not be taken for "no such journal"
+        let (wdp, _mock_server, mw_api, adapter2work_id) =
+            make_journal_wdp(vec![nature(&["0028-0836"]), nature(&["0028-0836"])]).await;
+        assert!(wdp.create_journal_item(&adapter2work_id, mw_api).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_diff_for_item_dry_run_does_not_write() {
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        wdp.set_output(EditOutput::DryRun);
+        let original_item = Entity::new_empty_item();
+        let mut item = original_item.clone();
+        item.add_claim(make_p2093("Jane Doe", "1"));
+        let result = wdp.apply_diff_for_item(original_item, item, mw_api).await.unwrap().unwrap();
+        assert!(result.edited() && !result.written());
+        assert!(result.created() && result.q().is_empty());
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        assert!(requests.iter().all(|r| r.method != wiremock::http::Method::POST));
+    }
+
+    /// Mocks a CSRF token, and a successful `wbeditentity` on Q42.
+    async fn mock_edits(mock_server: &wiremock::MockServer) {
+        use wiremock::{
+            matchers::{body_string_contains, method, query_param},
+            Mock, ResponseTemplate,
+        };
+        Mock::given(method("GET"))
+            .and(query_param("meta", "tokens"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"batchcomplete": "", "query": {"tokens": {"csrftoken": "token+\\"}}}),
+            ))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("wbeditentity"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"success": 1, "entity": {"id": "Q42", "type": "item"}})),
+            )
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn apply_diff_for_item_writes_reference_merged_into_existing_statement() {
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        mock_edits(&mock_server).await;
+        let original_item = wikibase::from_json::entity_from_json(&json!({
+            "type": "item",
+            "id": "Q42",
+            "claims": {"P478": [{
+                "id": "Q42$0A8B5F2C-0000-0000-0000-000000000000",
+                "type": "statement",
+                "rank": "normal",
+                "mainsnak": {
+                    "snaktype": "value",
+                    "property": "P478",
+                    "datatype": "string",
+                    "datavalue": {"value": "12", "type": "string"}
+                }
+            }]}
+        }))
+        .unwrap();
+        let mut item = original_item.clone();
+        let reference = Reference::new(vec![Snak::new_item("P248", "Q5188229")]);
+        crate::adapter_helpers::add_or_merge_statement(
+            &mut item,
+            Statement::new_normal(Snak::new_string("P478", "12"), vec![], vec![reference]),
+        );
+        assert_eq!(item.claims().len(), 1);
+
+        let result = wdp.apply_diff_for_item(original_item, item, mw_api).await.unwrap();
+        assert!(result.is_some_and(|result| result.edited()));
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        let edit = requests.iter().find(|r| r.method == wiremock::http::Method::POST).unwrap();
+        let body = String::from_utf8_lossy(&edit.body);
+        assert!(body.contains("P248"), "reference not written: {body}");
+    }
+
+    #[test]
+    fn only_properties_with_merged_references_are_altered() {
+        let statement = |property: &str, id: &str| {
+            json!({
+                "id": format!("Q42${id}"),
+                "type": "statement",
+                "rank": "normal",
+                "mainsnak": {
+                    "snaktype": "value",
+                    "property": property,
+                    "datatype": "string",
+                    "datavalue": {"value": "12", "type": "string"}
+                }
+            })
+        };
+        let original_item = wikibase::from_json::entity_from_json(&json!({
+            "type": "item",
+            "id": "Q42",
+            "claims": {
+                "P478": [statement("P478", "0A8B5F2C-0000-0000-0000-000000000001")],
+                "P304": [statement("P304", "0A8B5F2C-0000-0000-0000-000000000002")]
+            }
+        }))
+        .unwrap();
+        let mut item = original_item.clone();
+        let reference = Reference::new(vec![Snak::new_item("P248", "Q5188229")]);
+        crate::adapter_helpers::add_or_merge_statement(
+            &mut item,
+            Statement::new_normal(Snak::new_string("P478", "12"), vec![], vec![reference.clone()]),
+        );
+        item.add_claim(Statement::new_normal(
+            Snak::new_string("P433", "3"),
+            vec![],
+            vec![reference],
+        ));
+        let merged = |item| WikidataPapers::properties_with_merged_references(&original_item, item);
+        assert_eq!(merged(&item), vec!["P478".to_string()]);
+        assert!(merged(&original_item).is_empty());
+    }
+
+    #[tokio::test]
+    async fn ids_of_created_item_are_cached() {
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        mock_edits(&mock_server).await;
+        let mut item = Entity::new_empty_item();
+        let doi = Snak::new_string("P356", "10.1000/XYZ");
+        item.add_claim(Statement::new_normal(doi, vec![], vec![]));
+        let result = wdp.apply_diff_for_item(Entity::new_empty_item(), item, mw_api).await.unwrap();
+        assert!(result.is_some_and(|result| result.created));
+        // Nothing is mocked for the DOI, so this can only come from the cache
+        assert_eq!(wdp.cache.get("P356", "10.1000/xyz").await, Some("Q42".to_string()));
+    }
+
+    /// Reports one record for any DOI, and a PMID for it.
+    struct FakeDoiAdapter {
+        author_cache: HashMap<String, String>,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl crate::scientific_publication_adapter::ScientificPublicationAdapter for FakeDoiAdapter {
+        fn name(&self) -> &str {
+            "FakeDoiAdapter"
+        }
+        fn author_cache(&self) -> &HashMap<String, String> {
+            &self.author_cache
+        }
+        fn author_cache_mut(&mut self) -> &mut HashMap<String, String> {
+            &mut self.author_cache
+        }
+        fn publication_property(&self) -> Option<IdProp> {
+            Some(IdProp::DOI)
+        }
+        async fn do_cache_work(&mut self, publication_id: &str) -> Option<String> {
+            Some(publication_id.to_string())
+        }
+        async fn get_identifier_list(
+            &mut self,
+            ids: &[GenericWorkIdentifier],
+        ) -> Vec<GenericWorkIdentifier> {
+            match ids.iter().any(|id| id.work_type() == &GenericWorkType::Property(IdProp::DOI)) {
+                true => vec![GenericWorkIdentifier::new_prop(IdProp::PMID, "123")],
+                false => vec![],
+            }
+        }
+        fn get_work_titles(&self, _publication_id: &str) -> Vec<LocaleString> {
+            vec![LocaleString::new("en", "A title")]
+        }
+        fn get_volume(&self, _publication_id: &str) -> Option<String> {
+            Some("12".to_string())
+        }
+        async fn get_author_list(&mut self, _publication_id: &str) -> Vec<GenericAuthorInfo> {
+            vec![author_named("Alice Smith", 1)]
+        }
+    }
+
+    #[tokio::test]
+    async fn lookup_reports_merged_record() {
+        let (mut wdp, _mock_server, _mw_api, _) = make_journal_wdp(vec![]).await;
+        wdp.add_adapter(Box::new(FakeDoiAdapter { author_cache: HashMap::new() }));
+        let ids = vec![GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1000/xyz")];
+        let j = wdp.lookup(&ids).await.unwrap();
+        let identifiers = j["identifiers"].as_array().unwrap();
+        assert!(identifiers.contains(&json!({"property": "P356", "id": "10.1000/XYZ"})));
+        assert!(identifiers.contains(&json!({"property": "P698", "id": "123"})));
+        assert_eq!(j["titles"][0]["value"], "A title");
+        assert_eq!(j["volume"]["value"], "12");
+        assert_eq!(j["authors"][0]["name"], "Alice Smith");
+        assert_eq!(j["authors"][0]["list_number"], "1");
+        assert_eq!(j["sources"], json!(["FakeDoiAdapter"]));
+    }
+
+    #[tokio::test]
+    async fn audit_reports_mismatches_without_writing() {
+        let (mut wdp, mock_server, _mw_api, _) = make_journal_wdp(vec![]).await;
+        wdp.add_adapter(Box::new(FakeDoiAdapter { author_cache: HashMap::new() }));
+        let mut item = Entity::new_empty_item();
+        item.add_claim(Statement::new_normal(
+            Snak::new_string("P356", "10.1000/xyz"),
+            vec![],
+            vec![],
+        ));
+        item.add_claim(Statement::new_normal(
+            Snak::new_monolingual_text("P1476", "Another title", "en"),
+            vec![],
+            vec![],
+        ));
+        item.add_claim(make_p2093("Alice Smith", "1"));
+        item.add_claim(Statement::new_normal(
+            Snak::new_string("P2093", "Bob Jones"),
+            vec![],
+            vec![],
+        ));
+
+        let report = wdp.audit(&item).await;
+        let kinds: Vec<&str> = report.findings().iter().map(|f| f.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["doi_case", "title_mismatch", "author_count", "missing_ordinal"]);
+        assert_eq!(report.to_json()["sources"], json!(["FakeDoiAdapter"]));
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        assert!(requests.iter().all(|r| r.method != wiremock::http::Method::POST));
+    }
+
+    #[tokio::test]
+    async fn create_or_update_item_from_ids_skips_without_ids() {
+        let (mut wdp, _mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        let update = wdp.create_or_update_item_from_ids(mw_api, &vec![]).await.unwrap();
+        assert!(matches!(update, ItemUpdate::Skipped(SkipReason::NoIdentifiers)));
+    }
+
+    #[tokio::test]
+    async fn create_or_update_item_from_ids_refuses_duplicates() {
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        wdp.cache.set("P356", "10.1000/XYZ", Some("Q20".to_string())).await;
+        wdp.cache.set("P698", "123", Some("Q3".to_string())).await;
+        let ids = vec![
+            GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1000/xyz"),
+            GenericWorkIdentifier::new_prop(IdProp::PMID, "123"),
+        ];
+        let update = wdp.create_or_update_item_from_ids(mw_api, &ids).await.unwrap();
+        match update {
+            ItemUpdate::Duplicates(candidate) => {
+                assert_eq!(candidate.items(), vec!["Q3", "Q20"]);
+                assert_eq!(candidate.hits()[0], (ids[0].clone(), "Q20".to_string()));
+            },
+            _ => panic!("expected duplicates"),
+        }
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        assert!(requests.iter().all(|r| r.method != wiremock::http::Method::POST));
+    }
+
+    #[tokio::test]
+    async fn failed_search_is_an_error_not_a_new_item() {
+        // Nothing is mocked for the DOI, so searching for it fails
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        let ids = vec![GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1000/XYZ")];
+        assert!(wdp.create_or_update_item_from_ids(mw_api, &ids).await.is_err());
+        let requests = mock_server.received_requests().await.unwrap_or_default();
+        assert!(requests.iter().all(|r| r.method != wiremock::http::Method::POST));
+    }
+
+    #[tokio::test]
+    async fn ambiguous_id_makes_a_merge_candidate() {
+        use crate::test_helpers::add_search_mock;
+        const SEARCH_MULTIPLE: &str = include_str!("../test_data/search_found_multiple.json");
+        let (mut wdp, mock_server, mw_api, _) = make_journal_wdp(vec![]).await;
+        add_search_mock(&mock_server, "haswbstatement:P356=10.1000/XYZ", SEARCH_MULTIPLE).await;
+        wdp.set_duplicate_policy(DuplicatePolicy::Oldest);
+        let ids = vec![GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1000/XYZ")];
+        assert_eq!(wdp.get_items_for_ids(&ids).await.unwrap(), vec!["Q15757256", "Q46664291"]);
+        match wdp.create_or_update_item_from_ids(mw_api, &ids).await.unwrap() {
+            ItemUpdate::Duplicates(candidate) => {
+                assert_eq!(candidate.items(), vec!["Q15757256", "Q46664291"]);
+            },
+            _ => panic!("expected duplicates"),
+        }
+    }
+
+    #[test]
+    fn skip_reason_strings() {
+        assert_eq!(SkipReason::TooFewClaims.to_string(), "too few claims");
+        assert_eq!(SkipReason::NoIdentifiers.as_str(), "no identifiers");
+    }
 }
@@ -1,25 +1,64 @@
-use std::{collections::HashMap, sync::Arc, time::SystemTime};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::SystemTime,
+};
 
 use wikibase::mediawiki::api::Api;
 
-use crate::wikidata_interaction::WikidataInteraction;
+use crate::{
+    duplicates_log::DuplicatesLog,
+    identifier_index::IdentifierIndex,
+    identifiers::{GenericWorkIdentifier, GenericWorkType, IdProp},
+    isbn::Isbn,
+    journal_info::normalize_issn,
+    key_normalization::{normalize_value, KeyNormalization},
+    persistent_cache::PersistentCache,
+    wikidata_interaction::WikidataInteraction,
+};
 
 const MAX_CACHE_SIZE_PER_PROPERTY: usize = 10000;
+/// Keys per SPARQL query in `prefetch`. Each key is sent in up to three
+/// spellings, and the query has to fit in a GET request.
+const PREFETCH_CHUNK_SIZE: usize = 100;
 
 #[derive(Debug, Clone)]
 struct WikidataStringValue {
     timestamp: SystemTime,
-    key: Option<String>, // "Qxxx", or none
+    items: Vec<String>, // "Qxxx"; none, one, or several if the key is ambiguous
 }
 
 impl WikidataStringValue {
     pub fn new(key: Option<String>) -> Self {
-        Self { key, timestamp: SystemTime::now() }
+        Self::new_items(key.into_iter().collect())
     }
 
+    pub fn new_items(items: Vec<String>) -> Self {
+        Self { items, timestamp: SystemTime::now() }
+    }
+
+    /// The item, if there is exactly one.
     pub fn key(&mut self) -> Option<String> {
+        match self.items().as_slice() {
+            [q] => Some(q.to_owned()),
+            _ => None,
+        }
+    }
+
+    pub fn items(&mut self) -> Vec<String> {
         self.update_timestamp();
-        self.key.to_owned()
+        self.items.to_owned()
+    }
+
+    /// The item, `None` if there is none, or `Err` with all items if there
+    /// are several.
+    fn unique(&mut self) -> Result<Option<String>, Vec<String>> {
+        if self.items.len() > 1 {
+            Err(self.items())
+        } else {
+            Ok(self.key())
+        }
     }
 
     pub fn timestamp(&self) -> SystemTime {
@@ -33,11 +72,33 @@ impl WikidataStringValue {
 
 type WikidataStringHash = HashMap<String, WikidataStringValue>;
 
+/// Why `get_unique` has no answer for a key.
+#[derive(Debug)]
+pub enum LookupError {
+    /// The key is on all of these items
+    Ambiguous(Vec<String>),
+    /// Wikidata couldn't be searched. Nothing was cached, so the next lookup
+    /// searches again.
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::Ambiguous(items) => write!(f, "on several items: {}", items.join(", ")),
+            LookupError::Failed(error) => write!(f, "search failed: {error:#}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WikidataStringCache {
     cache: Arc<tokio::sync::RwLock<HashMap<String, WikidataStringHash>>>,
     mw_api: Arc<tokio::sync::RwLock<Api>>,
     max_cache_size_per_property: usize,
+    store: Option<Arc<PersistentCache>>,
+    index: Option<Arc<IdentifierIndex>>,
+    duplicates: Option<DuplicatesLog>,
 }
 
 impl WikidataInteraction for WikidataStringCache {}
@@ -48,48 +109,280 @@ impl WikidataStringCache {
             cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             mw_api,
             max_cache_size_per_property: MAX_CACHE_SIZE_PER_PROPERTY,
+            store: None,
+            index: None,
+            duplicates: None,
         }
     }
 
+    /// Keeps lookups in `store` as well, and uses those that haven't expired
+    /// before searching.
+    pub fn with_store(mut self, store: Arc<PersistentCache>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Looks keys up in `index` before searching. Keys that aren't in the
+    /// index are still searched for, since their items may be newer than
+    /// the dump it was built from.
+    pub fn with_index(mut self, index: Arc<IdentifierIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Writes keys that are on several items to `log`.
+    pub fn with_duplicates_log(mut self, log: DuplicatesLog) -> Self {
+        self.duplicates = Some(log);
+        self
+    }
+
     /// Gets an item ID for the property/key
     /// Uses search to find it if it's not in the cache
+    /// Returns None if the key is on several items, or the search failed;
+    /// use `get_unique` to tell those apart from a key that isn't on any
+    /// item.
     pub async fn get(&self, property: &str, key: &str) -> Option<String> {
-        let key = self.fix_key(key);
+        self.get_unique(property, key).await.unwrap_or(None)
+    }
+
+    /// Like `get`, but returns all items as `Err` if the key is on several.
+    /// Those are cached in memory (not in the persistent cache), and logged
+    /// once.
+    pub async fn get_unique(
+        &self,
+        property: &str,
+        key: &str,
+    ) -> Result<Option<String>, LookupError> {
+        let fixed_key = self.fix_key(property, key);
         self.ensure_property(property).await;
-        let (ret, do_search) = match self.cache.write().await.get_mut(property)?.get_mut(&key) {
-            Some(ret) => {
-                ret.update_timestamp();
-                (ret.key(), false)
-            },
-            None => (None, true),
-        };
-        if do_search {
-            self.search(property, &key).await
-        } else {
-            ret
+        if let Some(value) =
+            self.cache.write().await.get_mut(property).and_then(|hash| hash.get_mut(&fixed_key))
+        {
+            return value.unique().map_err(LookupError::Ambiguous);
+        }
+        if let Some(q) = self.stored(property, &fixed_key).await {
+            return Ok(q);
+        }
+        match self.indexed(property, &fixed_key) {
+            items if items.is_empty() => self.search(property, key).await,
+            items => self.set_items(property, key, items).await,
         }
     }
 
     /// Set the key/q tuple for a property
     pub async fn set(&self, property: &str, key: &str, q: Option<String>) {
-        let key = self.fix_key(key);
+        let key = self.fix_key(property, key);
+        if let Some(store) = &self.store {
+            if let Err(error) = store.set(property, &key, q.to_owned()) {
+                tracing::warn!(%error, property, key, "could not write to the persistent cache");
+            }
+        }
+        self.set_in_memory(property, key, q).await;
+    }
+
+    /// Forgets the key for a property, for example because an item with it
+    /// was just created, so that the next `get` searches again.
+    pub async fn remove(&self, property: &str, key: &str) {
+        let key = self.fix_key(property, key);
+        if let Some(store) = &self.store {
+            if let Err(error) = store.remove(property, &key) {
+                tracing::warn!(%error, property, key, "could not write to the persistent cache");
+            }
+        }
+        if let Some(hash) = self.cache.write().await.get_mut(property) {
+            hash.remove(&key);
+        }
+    }
+
+    async fn set_in_memory(&self, property: &str, key: String, q: Option<String>) {
+        self.insert(property, key, WikidataStringValue::new(q)).await;
+    }
+
+    /// Caches the items found for a key. A single item (or none) is cached
+    /// like `set` does; several are cached in memory and logged.
+    async fn set_items(
+        &self,
+        property: &str,
+        key: &str,
+        items: Vec<String>,
+    ) -> Result<Option<String>, LookupError> {
+        if items.len() < 2 {
+            let q = items.into_iter().next();
+            self.set(property, key, q.to_owned()).await;
+            return Ok(q);
+        }
+        let key = self.fix_key(property, key);
+        tracing::warn!(property, key, ?items, "identifier is on several items");
+        if let Some(log) = &self.duplicates {
+            if let Err(error) = log.write(property, &key, &items) {
+                tracing::warn!(%error, property, key, "could not write to the duplicates log");
+            }
+        }
+        self.insert(property, key, WikidataStringValue::new_items(items.to_owned())).await;
+        Err(LookupError::Ambiguous(items))
+    }
+
+    async fn insert(&self, property: &str, key: String, value: WikidataStringValue) {
         self.ensure_property(property).await;
         self.cache
             .write()
             .await
             .get_mut(property)
             .expect("wikidata_string_cache::set: property not found")
-            .insert(key, WikidataStringValue::new(q));
+            .insert(key, value);
         self.prune_property(property).await;
     }
 
-    /// Convenience wrapper
-    pub async fn issn2q(&self, issn: &str) -> Option<String> {
-        self.get("P236", issn).await
+    /// The items the identifier index has for the key, if there is an index.
+    fn indexed(&self, property: &str, key: &str) -> Vec<String> {
+        let Some(index) = &self.index else {
+            return vec![];
+        };
+        index.lookup(property, key).unwrap_or_else(|error| {
+            tracing::warn!(%error, property, key, "identifier index lookup failed");
+            vec![]
+        })
+    }
+
+    /// The key/q tuple from the persistent cache, if any; copied to memory.
+    async fn stored(&self, property: &str, key: &str) -> Option<Option<String>> {
+        let q = self.store.as_ref()?.get(property, key)?;
+        self.set_in_memory(property, key.to_string(), q.to_owned()).await;
+        Some(q)
+    }
+
+    /// How many inputs to `prefetch` for at a time. Prefetched keys have to
+    /// stay in memory until they are used, and a property that outgrows
+    /// the cache loses its older half, so a window is well below that.
+    pub fn prefetch_window(&self) -> usize {
+        (self.max_cache_size_per_property / 4).max(1)
+    }
+
+    /// Looks up many property/key pairs with a few SPARQL queries, so that
+    /// `get` finds them in the cache instead of searching for each one.
+    /// Pairs that are cached already, or in the identifier index, are
+    /// skipped. Returns the number of pairs cached.
+    pub async fn prefetch(&self, pairs: &[(String, String)]) -> usize {
+        let mut property2keys: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut seen: HashSet<(&str, String)> = HashSet::new();
+        let mut cached = 0;
+        for (property, key) in pairs {
+            let fixed_key = self.fix_key(property, key);
+            if !seen.insert((property, fixed_key)) || self.is_cached(property, key).await {
+                continue;
+            }
+            match self.indexed(property, key) {
+                items if items.is_empty() => property2keys.entry(property).or_default().push(key),
+                items => {
+                    let _ = self.set_items(property, key, items).await;
+                    cached += 1;
+                },
+            }
+        }
+        for (property, keys) in property2keys {
+            for chunk in keys.chunks(PREFETCH_CHUNK_SIZE) {
+                match self.prefetch_chunk(property, chunk).await {
+                    Ok(n) => cached += n,
+                    Err(error) => tracing::warn!(%error, property, "prefetch query failed"),
+                }
+            }
+        }
+        tracing::info!(pairs = pairs.len(), cached, "prefetched identifiers");
+        cached
+    }
+
+    /// The property/key pairs `get` and `isbn2q` look up for `ids`.
+    pub fn keys_for_ids(ids: &[GenericWorkIdentifier]) -> Vec<(String, String)> {
+        let mut keys = vec![];
+        for id in ids {
+            match id.work_type() {
+                GenericWorkType::Property(IdProp::ISBN10 | IdProp::ISBN13) => {
+                    if let Some(isbn) = Isbn::parse(id.id()) {
+                        keys.extend(
+                            Self::isbn_keys(&isbn).into_iter().map(|(p, k)| (p.to_string(), k)),
+                        );
+                    }
+                },
+                GenericWorkType::Property(prop) => {
+                    keys.push((prop.as_str().to_string(), id.id().to_string()))
+                },
+                GenericWorkType::Item => {},
+            }
+        }
+        keys
+    }
+
+    async fn prefetch_chunk(&self, property: &str, keys: &[&str]) -> anyhow::Result<usize> {
+        let query = prefetch_query(property, keys)
+            .ok_or_else(|| anyhow::anyhow!("not a property: {property}"))?;
+        let result = self
+            .mw_api
+            .read()
+            .await
+            .sparql_query(&query)
+            .await
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        let hits = prefetch_hits(&result, |key| self.fix_key(property, key));
+        for key in keys {
+            let items = hits.get(&self.fix_key(property, key)).cloned().unwrap_or_default();
+            let _ = self.set_items(property, key, items).await;
+        }
+        Ok(keys.len())
     }
 
-    fn fix_key(&self, key: &str) -> String {
-        key.trim().to_lowercase()
+    async fn is_cached(&self, property: &str, key: &str) -> bool {
+        let key = self.fix_key(property, key);
+        let in_memory =
+            self.cache.read().await.get(property).is_some_and(|hash| hash.contains_key(&key));
+        in_memory || self.store.as_ref().is_some_and(|store| store.get(property, &key).is_some())
+    }
+
+    /// Finds the journal for a list of ISSNs, in order of preference (ISSN-L
+    /// first, then print/electronic). Each ISSN is tried as ISSN-L (P7363),
+    /// then as ISSN (P236). If an ISSN is on several items, the journal is
+    /// ambiguous, and `None` is returned rather than guessing.
+    pub async fn issn2q(&self, issns: &[String]) -> Option<String> {
+        let mut tried: Vec<String> = vec![];
+        for issn in issns.iter().filter_map(|issn| normalize_issn(issn)) {
+            if tried.contains(&issn) {
+                continue;
+            }
+            for property in ["P7363", "P236"] {
+                match self.get_unique(property, &issn).await {
+                    Ok(Some(q)) => return Some(q),
+                    Ok(None) => {},
+                    Err(error) => {
+                        tracing::warn!(%error, property, issn, "no journal for ISSN");
+                        return None;
+                    },
+                }
+            }
+            tried.push(issn);
+        }
+        None
+    }
+
+    /// Finds a book by ISBN-13 (P212), then ISBN-10 (P957). Stops at the
+    /// first that is on several items, or can't be searched for.
+    pub async fn isbn2q(&self, isbn: &Isbn) -> Result<Option<String>, LookupError> {
+        for (property, key) in Self::isbn_keys(isbn) {
+            if let Some(q) = self.get_unique(property, &key).await? {
+                return Ok(Some(q));
+            }
+        }
+        Ok(None)
+    }
+
+    fn isbn_keys(isbn: &Isbn) -> Vec<(&'static str, String)> {
+        let mut candidates = vec![("P212", normalize_value("P212", isbn.isbn13()))];
+        if let Some(isbn10) = isbn.isbn10() {
+            candidates.push(("P957", normalize_value("P957", &isbn10)));
+        }
+        candidates
+    }
+
+    fn fix_key(&self, property: &str, key: &str) -> String {
+        normalize_value(property, key)
     }
 
     async fn property_needs_pruning(&self, property: &str) -> bool {
@@ -137,51 +430,92 @@ impl WikidataStringCache {
     }
 
     /// Searches for items with a specific property/key
+    /// Tries the other spellings of the key if the canonical one isn't found
     /// Stores result in cache, and returns it
     /// Stores/returns None if no result found
-    /// Stores/returns the first result, if multiple found
-    async fn search(&self, property: &str, key: &str) -> Option<String> {
-        let ret = match self
-            .search_wikibase(&format!("haswbstatement:{}={}", property, key), self.mw_api.clone())
-            .await
-        {
-            Ok(items) => items.first().map(|s| s.to_string()), // Picking first one, if several
-            Err(_) => None,
-        };
-        self.set(property, key, ret.to_owned()).await;
-        ret
+    /// Stores/returns all results as `Err`, if multiple found
+    /// Stores nothing if the search fails, so a failure isn't taken for a
+    /// missing item later
+    async fn search(&self, property: &str, key: &str) -> Result<Option<String>, LookupError> {
+        let mut items = vec![];
+        for spelling in KeyNormalization::for_property(property).spellings(key) {
+            let query = format!("haswbstatement:{}={}", property, spelling);
+            items = self
+                .search_wikibase(&query, self.mw_api.clone())
+                .await
+                .map_err(LookupError::Failed)?;
+            if !items.is_empty() {
+                break;
+            }
+        }
+        self.set_items(property, key, items).await
     }
 }
 
+/// A SPARQL query for the items with any of `keys` as a `property` value,
+/// in any rank. Keys are also tried in their other spellings, and in upper
+/// and lower case, since not all values on Wikidata are in canonical form.
+/// `None` if `property` isn't one.
+fn prefetch_query(property: &str, keys: &[&str]) -> Option<String> {
+    let is_property = property.strip_prefix('P').is_some_and(|n| n.parse::<u64>().is_ok());
+    if !is_property {
+        return None;
+    }
+    let normalization = KeyNormalization::for_property(property);
+    let mut values: Vec<String> = vec![];
+    for key in keys {
+        let mut spellings = vec![key.to_string()];
+        spellings.extend(normalization.spellings(key));
+        spellings.extend([key.to_uppercase(), key.to_lowercase()]);
+        for value in spellings {
+            let literal = format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+            if !values.contains(&literal) {
+                values.push(literal);
+            }
+        }
+    }
+    Some(format!(
+        "SELECT ?item ?value WHERE {{ VALUES ?value {{ {} }} ?item p:{property}/ps:{property} ?value }}",
+        values.join(" ")
+    ))
+}
+
+/// The items in a `prefetch_query` result for each value, keyed by
+/// `fix_key(value)`, sorted and without duplicates.
+fn prefetch_hits(
+    result: &serde_json::Value,
+    fix_key: impl Fn(&str) -> String,
+) -> HashMap<String, Vec<String>> {
+    let mut hits: HashMap<String, Vec<String>> = HashMap::new();
+    for binding in result["results"]["bindings"].as_array().into_iter().flatten() {
+        let item = binding["item"]["value"]
+            .as_str()
+            .and_then(|uri| uri.strip_prefix("http://www.wikidata.org/entity/"));
+        if let (Some(item), Some(value)) = (item, binding["value"]["value"].as_str()) {
+            hits.entry(fix_key(value)).or_default().push(item.to_string());
+        }
+    }
+    for items in hits.values_mut() {
+        items.sort();
+        items.dedup();
+    }
+    hits
+}
+
 #[cfg(test)]
 mod tests {
     use std::{thread, time::Duration};
 
     use wikibase::mediawiki::api::Api;
-    use wiremock::{
-        matchers::{method, query_param},
-        Mock, MockServer, ResponseTemplate,
-    };
+    use wiremock::MockServer;
 
     use super::*;
-    use crate::test_helpers::start_mediawiki_mock_server as start_mock_server;
+    use crate::test_helpers::{add_search_mock, start_mediawiki_mock_server as start_mock_server};
 
     const SEARCH_Q46664291: &str = include_str!("../test_data/search_found_q46664291.json");
     const SEARCH_Q15757256: &str = include_str!("../test_data/search_found_q15757256.json");
     const SEARCH_EMPTY: &str = include_str!("../test_data/search_empty.json");
-
-    /// Registers a search mock for a specific `srsearch` value.
-    async fn add_search_mock(mock_server: &MockServer, srsearch: &str, body: &'static str) {
-        Mock::given(method("GET"))
-            .and(query_param("srsearch", srsearch))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .insert_header("content-type", "application/json; charset=utf-8")
-                    .set_body_string(body),
-            )
-            .mount(mock_server)
-            .await;
-    }
+    const SEARCH_MULTIPLE: &str = include_str!("../test_data/search_found_multiple.json");
 
     /// Creates an `Api` connected to the mock server.
     async fn mock_api(mock_server: &MockServer) -> Arc<tokio::sync::RwLock<Api>> {
@@ -210,7 +544,8 @@ mod tests {
     async fn fix_key() {
         let mock_server = start_mock_server().await;
         let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
-        assert_eq!(wsc.fix_key(" fOoBAr  "), "foobar".to_string());
+        assert_eq!(wsc.fix_key("P356", " 10.1/aB  "), "10.1/AB".to_string());
+        assert_eq!(wsc.fix_key("P932", "PMC123"), "123".to_string());
     }
 
     #[tokio::test]
@@ -228,8 +563,40 @@ mod tests {
         add_search_mock(&mock_server, "haswbstatement:P698=16116339", SEARCH_Q46664291).await;
         add_search_mock(&mock_server, "haswbstatement:P698=not_a_valid_id", SEARCH_EMPTY).await;
         let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
-        assert_eq!(wsc.search("P698", "16116339").await, Some("Q46664291".to_string()));
-        assert_eq!(wsc.search("P698", "not_a_valid_id").await, None);
+        assert_eq!(wsc.search("P698", "16116339").await.unwrap(), Some("Q46664291".to_string()));
+        assert_eq!(wsc.search("P698", "not_a_valid_id").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn failed_search_is_not_cached() {
+        let mock_server = start_mock_server().await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        // Nothing is mocked yet, so the search fails
+        let result = wsc.get_unique("P698", "16116339").await;
+        assert!(matches!(result, Err(LookupError::Failed(_))));
+        assert!(!wsc.is_cached("P698", "16116339").await);
+        add_search_mock(&mock_server, "haswbstatement:P698=16116339", SEARCH_Q46664291).await;
+        assert_eq!(wsc.get("P698", "16116339").await, Some("Q46664291".to_string()));
+    }
+
+    #[tokio::test]
+    async fn get_refuses_ambiguous_key() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P496=0000-0002-1825-0097", SEARCH_MULTIPLE)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("duplicates.jsonl");
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await)
+            .with_duplicates_log(DuplicatesLog::open(&path).unwrap());
+        let items = vec!["Q15757256".to_string(), "Q46664291".to_string()];
+        let result = wsc.get_unique("P496", "0000-0002-1825-0097").await;
+        assert!(matches!(result, Err(LookupError::Ambiguous(found)) if found == items));
+        assert_eq!(wsc.get("P496", "0000-0002-1825-0097").await, None);
+        assert!(wsc.is_cached("P496", "0000-0002-1825-0097").await);
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        let j: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(j["items"], json!(items));
     }
 
     #[tokio::test]
@@ -246,14 +613,206 @@ mod tests {
         assert_eq!(wsc.get("P698", "16116339").await, None);
     }
 
+    #[tokio::test]
+    async fn get_searches_canonical_form() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P932=16116339", SEARCH_Q46664291).await;
+        add_search_mock(&mock_server, "haswbstatement:P496=0000-0002-1825-009X", SEARCH_Q15757256)
+            .await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        assert_eq!(wsc.get("P932", "PMC16116339").await, Some("Q46664291".to_string()));
+        assert_eq!(wsc.get("P496", "000000021825009x").await, Some("Q15757256".to_string()));
+        assert!(wsc.is_cached("P932", "16116339").await);
+        assert!(wsc.is_cached("P496", "0000-0002-1825-009X").await);
+    }
+
+    #[tokio::test]
+    async fn get_uses_persistent_cache() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P698=16116339", SEARCH_Q46664291).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let open = || {
+            let ttl = std::time::Duration::from_secs(3600);
+            Arc::new(PersistentCache::open(&path, ttl, ttl).unwrap())
+        };
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await).with_store(open());
+        assert_eq!(wsc.get("P698", "16116339").await, Some("Q46664291".to_string()));
+        wsc.set("P356", "10.1/X", Some("Q1".to_string())).await;
+        wsc.set("P356", "10.1/y", None).await;
+        drop(wsc);
+
+        // Nothing else is mocked, so these can only come from the file
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await).with_store(open());
+        assert_eq!(wsc.get("P356", "10.1/x").await, Some("Q1".to_string()));
+        assert_eq!(wsc.get("P698", "16116339").await, Some("Q46664291".to_string()));
+        wsc.remove("P698", "16116339").await;
+        assert_eq!(open().get("P698", "16116339"), None);
+        assert_eq!(open().get("P356", "10.1/Y"), Some(None));
+    }
+
+    #[test]
+    fn prefetch_query_lists_all_spellings() {
+        let query = prefetch_query("P356", &["10.1/Ab", "10.1/\"x\""]).unwrap();
+        assert_eq!(
+            query,
+            r#"SELECT ?item ?value WHERE { VALUES ?value { "10.1/Ab" "10.1/AB" "10.1/ab" "10.1/\"x\"" "10.1/\"X\"" } ?item p:P356/ps:P356 ?value }"#
+        );
+        assert_eq!(prefetch_query("P356 } #", &["x"]), None);
+    }
+
+    #[test]
+    fn prefetch_hits_groups_items_by_key() {
+        let result = json!({"results": {"bindings": [
+            {"item": {"value": "http://www.wikidata.org/entity/Q2"}, "value": {"value": "10.1/A"}},
+            {"item": {"value": "http://www.wikidata.org/entity/Q1"}, "value": {"value": "10.1/a"}},
+            {"item": {"value": "http://www.wikidata.org/entity/Q3"}, "value": {"value": "10.1/B"}},
+            {"item": {"value": "http://www.wikidata.org/entity/Q3"}, "value": {"value": "10.1/B"}},
+        ]}});
+        let hits = prefetch_hits(&result, |key| key.to_lowercase());
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits["10.1/a"], vec!["Q1", "Q2"]);
+        assert_eq!(hits["10.1/b"], vec!["Q3"]);
+    }
+
+    #[test]
+    fn keys_for_ids_expands_isbns() {
+        let ids = vec![
+            GenericWorkIdentifier::new_prop(IdProp::DOI, "10.1/x"),
+            GenericWorkIdentifier::new_prop(IdProp::ISBN13, "9780306406157"),
+        ];
+        let keys = WikidataStringCache::keys_for_ids(&ids);
+        assert_eq!(keys[0], ("P356".to_string(), "10.1/X".to_string()));
+        assert!(keys.contains(&("P212".to_string(), "978-0-306-40615-7".to_string())));
+        assert!(keys.contains(&("P957".to_string(), "0-306-40615-2".to_string())));
+    }
+
+    #[tokio::test]
+    async fn prefetch_skips_cached_pairs() {
+        let mock_server = start_mock_server().await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        wsc.set("P356", "10.1/x", Some("Q1".to_string())).await;
+        // No SPARQL query is needed, so none can fail
+        let pairs = vec![("P356".to_string(), "10.1/X".to_string())];
+        assert_eq!(wsc.prefetch(&pairs).await, 0);
+        assert!(wsc.is_cached("P356", "10.1/X").await);
+    }
+
+    #[tokio::test]
+    async fn prefetch_windows_stay_in_the_cache() {
+        let mock_server = start_mock_server().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.index");
+        let pairs: Vec<(String, String)> =
+            (10..40).map(|n| ("P356".to_string(), format!("10.1/{n}"))).collect();
+        let lines: Vec<String> =
+            pairs.iter().enumerate().map(|(n, (p, key))| format!("{p}\t{key}\tQ{n}\n")).collect();
+        std::fs::write(&path, lines.concat()).unwrap();
+        let index = Arc::new(IdentifierIndex::open(&path).unwrap());
+        let mut wsc = WikidataStringCache::new(mock_api(&mock_server).await).with_index(index);
+        wsc.max_cache_size_per_property = 8;
+        // More keys than the cache holds, so they can only be prefetched in
+        // windows
+        for window in pairs.chunks(wsc.prefetch_window()) {
+            assert_eq!(wsc.prefetch(window).await, window.len());
+            for (property, key) in window {
+                assert!(wsc.is_cached(property, key).await, "{key} was pruned");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn get_uses_identifier_index_before_search() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P698=16116339", SEARCH_Q46664291).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.index");
+        std::fs::write(&path, "P356\t10.1/X\tQ1\nP356\t10.1/Y\tQ2\nP356\t10.1/Y\tQ3\n").unwrap();
+        let index = Arc::new(IdentifierIndex::open(&path).unwrap());
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await).with_index(index);
+        // Nothing is mocked for the DOIs, so these can only come from the index
+        assert_eq!(wsc.get("P356", "10.1/X").await, Some("Q1".to_string()));
+        let result = wsc.get_unique("P356", "10.1/y").await;
+        assert!(matches!(result, Err(LookupError::Ambiguous(items)) if items == ["Q2", "Q3"]));
+        // Newer than the index
+        assert_eq!(wsc.get("P698", "16116339").await, Some("Q46664291".to_string()));
+    }
+
+    fn issns(issns: &[&str]) -> Vec<String> {
+        issns.iter().map(|s| s.to_string()).collect()
+    }
+
     #[tokio::test]
     async fn issn2q() {
         let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1351-5101", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P236=1351-5101", SEARCH_Q15757256).await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        assert_eq!(wsc.issn2q(&issns(&["13515101"])).await, Some("Q15757256".to_string()));
+        assert_eq!(wsc.issn2q(&issns(&["nope-di-dope"])).await, None);
+        assert_eq!(wsc.issn2q(&[]).await, None);
+    }
+
+    #[tokio::test]
+    async fn issn2q_prefers_issn_l() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1351-5101", SEARCH_Q46664291).await;
         add_search_mock(&mock_server, "haswbstatement:P236=1351-5101", SEARCH_Q15757256).await;
-        add_search_mock(&mock_server, "haswbstatement:P236=nope-di-dope", SEARCH_EMPTY).await;
         let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
-        assert_eq!(wsc.issn2q("1351-5101").await, Some("Q15757256".to_string()));
-        assert_eq!(wsc.issn2q("nope-di-dope").await, None);
+        assert_eq!(wsc.issn2q(&issns(&["1351-5101"])).await, Some("Q46664291".to_string()));
+    }
+
+    #[tokio::test]
+    async fn issn2q_falls_back_to_electronic_issn() {
+        let mock_server = start_mock_server().await;
+        for key in ["P7363=1351-5101", "P236=1351-5101", "P7363=1468-5833"] {
+            add_search_mock(&mock_server, &format!("haswbstatement:{key}"), SEARCH_EMPTY).await;
+        }
+        add_search_mock(&mock_server, "haswbstatement:P236=1468-5833", SEARCH_Q15757256).await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        let found = wsc.issn2q(&issns(&["1351-5101", "1468-5833"])).await;
+        assert_eq!(found, Some("Q15757256".to_string()));
+    }
+
+    #[tokio::test]
+    async fn issn2q_skips_ambiguous_issn() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1351-5101", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P236=1351-5101", SEARCH_MULTIPLE).await;
+        add_search_mock(&mock_server, "haswbstatement:P7363=1468-5833", SEARCH_Q15757256).await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        // Neither a later ISSN nor a second call may pick one of the candidates
+        let candidates = issns(&["1351-5101", "1468-5833"]);
+        assert_eq!(wsc.issn2q(&candidates).await, None);
+        assert_eq!(wsc.issn2q(&candidates).await, None);
+        let result = wsc.get_unique("P236", "1351-5101").await;
+        assert!(matches!(result, Err(LookupError::Ambiguous(items)) if items.len() == 2));
+    }
+
+    #[tokio::test]
+    async fn isbn2q_falls_back_to_unhyphenated_and_isbn10() {
+        let mock_server = start_mock_server().await;
+        add_search_mock(&mock_server, "haswbstatement:P212=978-0-306-40615-7", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P212=9780306406157", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P957=0-306-40615-2", SEARCH_Q15757256).await;
+        add_search_mock(&mock_server, "haswbstatement:P212=978-3-16-148410-0", SEARCH_EMPTY).await;
+        add_search_mock(&mock_server, "haswbstatement:P212=9783161484100", SEARCH_Q46664291).await;
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        let isbn = Isbn::parse("0306406152").unwrap();
+        assert_eq!(wsc.isbn2q(&isbn).await.unwrap(), Some("Q15757256".to_string()));
+        let isbn = Isbn::parse("978-3-16-148410-0").unwrap();
+        assert_eq!(wsc.isbn2q(&isbn).await.unwrap(), Some("Q46664291".to_string()));
+    }
+
+    #[tokio::test]
+    async fn isbn2q_not_found() {
+        let mock_server = start_mock_server().await;
+        for key in ["P212=979-10-90636-07-1", "P212=9791090636071"] {
+            add_search_mock(&mock_server, &format!("haswbstatement:{key}"), SEARCH_EMPTY).await;
+        }
+        let wsc = WikidataStringCache::new(mock_api(&mock_server).await);
+        let isbn = Isbn::parse("979-10-90636-07-1").unwrap();
+        assert_eq!(wsc.isbn2q(&isbn).await.unwrap(), None);
     }
 
     #[tokio::test]